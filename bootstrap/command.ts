@@ -0,0 +1,237 @@
+// `Edon.Command`, subprocesses run to completion with `output()` or streamed with `spawn()`,
+// spawning needs the run permission for the command
+
+type Stdio = "inherit" | "piped" | "null"
+
+const STDIO = ["inherit", "piped", "null"]
+
+interface CommandOptions {
+  args?: string[]
+  // the current directory by default
+  cwd?: string | { toString(): string }
+  // added to the environment of the runtime
+  env?: Record<string, string>
+  // "inherit" by default, "null" for `output()`
+  stdin?: Stdio
+  // "piped" by default
+  stdout?: Stdio
+  stderr?: Stdio
+}
+
+interface CommandStatus {
+  success: boolean
+  // 128 plus the signal number when the child was killed by a signal
+  code: number
+  signal: string | null
+}
+
+interface Spawned {
+  rid: number
+  pid: number | null
+  stdin: number | null
+  stdout: number | null
+  stderr: number | null
+}
+
+interface CommandOps {
+  spawn(options: ReturnType<typeof spawnOptions>): Spawned
+  wait(rid: number): Promise<CommandStatus>
+  kill(rid: number, signal: string): void
+  read(rid: number): Promise<Uint8Array | null>
+  write(rid: number, data: Uint8Array): Promise<void>
+}
+
+interface CoreOps {
+  close(rid: number): void
+}
+
+function stdio(name: string, value: Stdio | undefined, fallback: Stdio): Stdio {
+  const stdio = value ?? fallback
+  if (!STDIO.includes(stdio)) {
+    throw new TypeError(`invalid ${name} \`${stdio}\`, expected "inherit", "piped" or "null"`)
+  }
+  return stdio
+}
+
+function spawnOptions(cmd: string, options: CommandOptions) {
+  return {
+    cmd,
+    args: [...(options.args ?? [])].map(String),
+    cwd: options.cwd === undefined ? null : String(options.cwd),
+    env: Object.fromEntries(Object.entries(options.env ?? {}).map(([key, value]) => [key, String(value)])),
+    stdin: stdio("stdin", options.stdin, "inherit"),
+    stdout: stdio("stdout", options.stdout, "piped"),
+    stderr: stdio("stderr", options.stderr, "piped"),
+  }
+}
+
+export default function (this: { command: CommandOps; core: CoreOps; internal: any }) {
+  const ops = this.command
+  const core = this.core
+  const internal = this.internal
+  const encoder = new TextEncoder()
+
+  // the stdout or stderr of a child, read as it is consumed
+  function pipe(rid: number): ReadableStream {
+    let closed = false
+    const close = () => {
+      if (!closed) {
+        closed = true
+        core.close(rid)
+      }
+    }
+    return new ReadableStream(
+      {
+        async pull(controller) {
+          const chunk = await ops.read(rid)
+          if (chunk === null) {
+            close()
+            controller.close()
+          } else {
+            controller.enqueue(chunk)
+          }
+        },
+        cancel: close,
+      },
+      { highWaterMark: 0 },
+    )
+  }
+
+  // the stdin of a child, it reads the end of its input once this is closed
+  class ChildStdin {
+    #rid: number
+    #closed = false
+
+    constructor(rid: number) {
+      this.#rid = rid
+    }
+
+    async write(data: Uint8Array | string): Promise<void> {
+      await ops.write(this.#rid, typeof data === "string" ? encoder.encode(data) : data)
+    }
+    close() {
+      if (!this.#closed) {
+        this.#closed = true
+        core.close(this.#rid)
+      }
+    }
+  }
+
+  class CommandOutput {
+    readonly success: boolean
+    readonly code: number
+    readonly signal: string | null
+    #stdout: Uint8Array | null
+    #stderr: Uint8Array | null
+
+    constructor(status: CommandStatus, stdout: Uint8Array | null, stderr: Uint8Array | null) {
+      this.success = status.success
+      this.code = status.code
+      this.signal = status.signal
+      this.#stdout = stdout
+      this.#stderr = stderr
+    }
+
+    get stdout(): Uint8Array {
+      if (this.#stdout === null) {
+        throw new TypeError("stdout is not piped")
+      }
+      return this.#stdout
+    }
+    get stderr(): Uint8Array {
+      if (this.#stderr === null) {
+        throw new TypeError("stderr is not piped")
+      }
+      return this.#stderr
+    }
+  }
+
+  class ChildProcess {
+    #rid: number
+    #pid: number | null
+    #stdin: ChildStdin | null
+    #stdout: ReadableStream | null
+    #stderr: ReadableStream | null
+    #status: Promise<CommandStatus>
+    #exited = false
+
+    constructor(spawned: Spawned) {
+      this.#rid = spawned.rid
+      this.#pid = spawned.pid
+      this.#stdin = spawned.stdin === null ? null : new ChildStdin(spawned.stdin)
+      this.#stdout = spawned.stdout === null ? null : pipe(spawned.stdout)
+      this.#stderr = spawned.stderr === null ? null : pipe(spawned.stderr)
+      // the child is reaped whether or not `status` is awaited
+      this.#status = ops.wait(spawned.rid).finally(() => {
+        this.#exited = true
+        core.close(spawned.rid)
+      })
+    }
+
+    get pid() {
+      return this.#pid
+    }
+    get stdin(): ChildStdin {
+      if (this.#stdin === null) {
+        throw new TypeError("stdin is not piped")
+      }
+      return this.#stdin
+    }
+    get stdout(): ReadableStream {
+      if (this.#stdout === null) {
+        throw new TypeError("stdout is not piped")
+      }
+      return this.#stdout
+    }
+    get stderr(): ReadableStream {
+      if (this.#stderr === null) {
+        throw new TypeError("stderr is not piped")
+      }
+      return this.#stderr
+    }
+    get status(): Promise<CommandStatus> {
+      return this.#status
+    }
+
+    // throws a TypeError once the child has exited
+    kill(signal = "SIGTERM") {
+      if (this.#exited) {
+        throw new TypeError("the child process has already exited")
+      }
+      ops.kill(this.#rid, String(signal))
+    }
+
+    // wait for the child and collect what it wrote to its piped stdout and stderr
+    async output(): Promise<CommandOutput> {
+      const read = (stream: ReadableStream | null) => stream && internal.streams.readAll(stream)
+      const [status, stdout, stderr] = await Promise.all([this.#status, read(this.#stdout), read(this.#stderr)])
+      return new CommandOutput(status, stdout, stderr)
+    }
+  }
+
+  class Command {
+    #cmd: string
+    #options: CommandOptions
+
+    constructor(cmd: string | URL, options: CommandOptions = {}) {
+      this.#cmd = String(cmd)
+      this.#options = { ...options }
+    }
+
+    spawn(): ChildProcess {
+      return new ChildProcess(ops.spawn(spawnOptions(this.#cmd, this.#options)))
+    }
+
+    // run the command to completion, stdin can not be piped here, use `spawn()` for that
+    async output(): Promise<CommandOutput> {
+      if (this.#options.stdin === "piped") {
+        throw new TypeError("stdin can not be piped with output(), use spawn()")
+      }
+      const options = { ...this.#options, stdin: this.#options.stdin ?? "null" }
+      return new ChildProcess(ops.spawn(spawnOptions(this.#cmd, options))).output()
+    }
+  }
+
+  // @ts-ignore
+  Edon.Command = Command
+}
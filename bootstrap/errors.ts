@@ -0,0 +1,46 @@
+// `Edon.errors`, the classes native ops throw, named after the failing io kind
+
+const NAMES = [
+  "NotFound",
+  "PermissionDenied",
+  "AlreadyExists",
+  "BadResource",
+  "Busy",
+  "ConnectionRefused",
+  "ConnectionReset",
+  "ConnectionAborted",
+  "NotConnected",
+  "AddrInUse",
+  "AddrNotAvailable",
+  "BrokenPipe",
+  "InvalidData",
+  "TimedOut",
+  "Interrupted",
+  "WriteZero",
+  "UnexpectedEof",
+  "IsADirectory",
+  "NotADirectory",
+  "DirectoryNotEmpty",
+  "NotSupported",
+  "Http",
+]
+
+function errorClass(name: string) {
+  const ErrorClass = class extends Error {
+    constructor(message?: string, options?: ErrorOptions) {
+      super(message, options)
+      this.name = name
+    }
+  }
+  Object.defineProperty(ErrorClass, "name", { value: name })
+  return ErrorClass
+}
+
+export default function () {
+  const errors: Record<string, typeof Error> = {}
+  for (const name of NAMES) {
+    errors[name] = errorClass(name)
+  }
+  // @ts-ignore
+  globalThis.Edon.errors = Object.freeze(errors)
+}
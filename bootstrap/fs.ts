@@ -0,0 +1,274 @@
+// the file system functions of `Edon`, every one with a `*Sync` variant, failures throw the
+// matching `Edon.errors` class, e.g. `Edon.errors.NotFound`, and `Edon.watchFs`
+
+interface RawFileInfo {
+  isFile: boolean
+  isDirectory: boolean
+  isSymlink: boolean
+  size: number
+  mtime: number | null
+  atime: number | null
+  birthtime: number | null
+  mode: number
+  uid: number
+  gid: number
+  dev: number
+  ino: number
+  nlink: number
+}
+
+interface DirEntry {
+  name: string
+  isFile: boolean
+  isDirectory: boolean
+  isSymlink: boolean
+}
+
+interface WriteFileOptions {
+  // append instead of replacing the contents
+  append?: boolean
+  // create the file if missing, true by default
+  create?: boolean
+  // fail with `AlreadyExists` if the file exists
+  createNew?: boolean
+  mode?: number
+}
+
+interface MkdirOptions {
+  recursive?: boolean
+  mode?: number
+}
+
+interface TempOptions {
+  // the system temp directory by default
+  dir?: string
+  prefix?: string
+  suffix?: string
+}
+
+type Path = string | { toString(): string }
+
+// every op has a `*Sync` twin with the same arguments, returning instead of resolving
+interface FsOps {
+  readFile(path: string): Promise<Uint8Array>
+  readTextFile(path: string): Promise<string>
+  writeFile(path: string, data: string | Uint8Array, options: ReturnType<typeof writeOptions>): Promise<void>
+  stat(path: string): Promise<RawFileInfo>
+  lstat(path: string): Promise<RawFileInfo>
+  readDir(path: string): Promise<DirEntry[]>
+  mkdir(path: string, options: ReturnType<typeof mkdirOptions>): Promise<void>
+  remove(path: string, recursive: boolean): Promise<void>
+  rename(from: string, to: string): Promise<void>
+  copyFile(from: string, to: string): Promise<void>
+  symlink(target: string, path: string): Promise<void>
+  realPath(path: string): Promise<string>
+  chmod(path: string, mode: number): Promise<void>
+  truncate(path: string, len: number): Promise<void>
+  makeTempDir(options: ReturnType<typeof tempOptions>): Promise<string>
+  makeTempFile(options: ReturnType<typeof tempOptions>): Promise<string>
+  [sync: string]: (...args: any[]) => any
+}
+
+interface FsEvent {
+  kind: "create" | "modify" | "remove" | "rename"
+  paths: string[]
+}
+
+interface WatcherOps {
+  watch(options: { paths: string[]; recursive: boolean }): number
+  next(rid: number): Promise<FsEvent | null>
+}
+
+interface CoreOps {
+  close(rid: number): void
+}
+
+class FileInfo {
+  readonly isFile: boolean
+  readonly isDirectory: boolean
+  readonly isSymlink: boolean
+  readonly size: number
+  readonly mtime: Date | null
+  readonly atime: Date | null
+  readonly birthtime: Date | null
+  readonly mode: number
+  readonly uid: number
+  readonly gid: number
+  readonly dev: number
+  readonly ino: number
+  readonly nlink: number
+
+  constructor(info: RawFileInfo) {
+    const date = (millis: number | null) => (millis === null ? null : new Date(millis))
+    this.isFile = info.isFile
+    this.isDirectory = info.isDirectory
+    this.isSymlink = info.isSymlink
+    this.size = info.size
+    this.mtime = date(info.mtime)
+    this.atime = date(info.atime)
+    this.birthtime = date(info.birthtime)
+    this.mode = info.mode
+    this.uid = info.uid
+    this.gid = info.gid
+    this.dev = info.dev
+    this.ino = info.ino
+    this.nlink = info.nlink
+  }
+}
+
+function writeOptions(options: WriteFileOptions) {
+  return {
+    append: !!options.append,
+    create: options.create ?? true,
+    createNew: !!options.createNew,
+    mode: options.mode === undefined ? null : Number(options.mode),
+  }
+}
+
+function mkdirOptions(options: MkdirOptions) {
+  return { recursive: !!options.recursive, mode: options.mode === undefined ? null : Number(options.mode) }
+}
+
+function tempOptions(options: TempOptions) {
+  return {
+    dir: options.dir === undefined ? null : String(options.dir),
+    prefix: String(options.prefix ?? ""),
+    suffix: String(options.suffix ?? ""),
+  }
+}
+
+export default function (this: { fs: FsOps; watcher: WatcherOps; core: CoreOps }) {
+  const ops = this.fs
+  const watcherOps = this.watcher
+  const core = this.core
+
+  // changes under the watched paths, bursts of the same change arrive once
+  class FsWatcher {
+    #rid: number
+    #closed = false
+
+    constructor(rid: number) {
+      this.#rid = rid
+    }
+
+    get rid() {
+      return this.#rid
+    }
+
+    close() {
+      if (!this.#closed) {
+        this.#closed = true
+        core.close(this.#rid)
+      }
+    }
+
+    // breaking out of `for await` closes the watcher
+    async *[Symbol.asyncIterator](): AsyncGenerator<FsEvent> {
+      try {
+        for (;;) {
+          const event = await watcherOps.next(this.#rid)
+          if (event === null) {
+            return
+          }
+          yield event
+        }
+      } finally {
+        this.close()
+      }
+    }
+  }
+
+  // watching starts right away, not on the first step of the iteration
+  function watchFs(paths: Path | Path[], options: { recursive?: boolean } = {}): FsWatcher {
+    const list = (Array.isArray(paths) ? paths : [paths]).map(String)
+    return new FsWatcher(watcherOps.watch({ paths: list, recursive: options.recursive ?? true }))
+  }
+
+  const fs = {
+    readFile: async (path: Path) => ops.readFile(String(path)),
+    readFileSync: (path: Path): Uint8Array => ops.readFileSync(String(path)),
+    // fails with `InvalidData` unless the file is UTF-8
+    readTextFile: async (path: Path) => ops.readTextFile(String(path)),
+    readTextFileSync: (path: Path): string => ops.readTextFileSync(String(path)),
+
+    writeFile: async (path: Path, data: Uint8Array, options: WriteFileOptions = {}) => {
+      await ops.writeFile(String(path), data, writeOptions(options))
+    },
+    writeFileSync: (path: Path, data: Uint8Array, options: WriteFileOptions = {}) => {
+      ops.writeFileSync(String(path), data, writeOptions(options))
+    },
+    writeTextFile: async (path: Path, text: string, options: WriteFileOptions = {}) => {
+      await ops.writeFile(String(path), String(text), writeOptions(options))
+    },
+    writeTextFileSync: (path: Path, text: string, options: WriteFileOptions = {}) => {
+      ops.writeFileSync(String(path), String(text), writeOptions(options))
+    },
+
+    stat: async (path: Path) => new FileInfo(await ops.stat(String(path))),
+    statSync: (path: Path) => new FileInfo(ops.statSync(String(path))),
+    // the symlink itself instead of its target
+    lstat: async (path: Path) => new FileInfo(await ops.lstat(String(path))),
+    lstatSync: (path: Path) => new FileInfo(ops.lstatSync(String(path))),
+
+    // `for await (const entry of Edon.readDir(path))`, the directory is listed on the first step
+    readDir: async function* (path: Path): AsyncGenerator<DirEntry> {
+      yield* await ops.readDir(String(path))
+    },
+    readDirSync: (path: Path): DirEntry[] => ops.readDirSync(String(path)),
+
+    mkdir: async (path: Path, options: MkdirOptions = {}) => {
+      await ops.mkdir(String(path), mkdirOptions(options))
+    },
+    mkdirSync: (path: Path, options: MkdirOptions = {}) => {
+      ops.mkdirSync(String(path), mkdirOptions(options))
+    },
+    // a non-empty directory needs `recursive`
+    remove: async (path: Path, options: { recursive?: boolean } = {}) => {
+      await ops.remove(String(path), !!options.recursive)
+    },
+    removeSync: (path: Path, options: { recursive?: boolean } = {}) => {
+      ops.removeSync(String(path), !!options.recursive)
+    },
+    rename: async (from: Path, to: Path) => {
+      await ops.rename(String(from), String(to))
+    },
+    renameSync: (from: Path, to: Path) => {
+      ops.renameSync(String(from), String(to))
+    },
+    copyFile: async (from: Path, to: Path) => {
+      await ops.copyFile(String(from), String(to))
+    },
+    copyFileSync: (from: Path, to: Path) => {
+      ops.copyFileSync(String(from), String(to))
+    },
+    // `path` becomes a link to `target`
+    symlink: async (target: Path, path: Path) => {
+      await ops.symlink(String(target), String(path))
+    },
+    symlinkSync: (target: Path, path: Path) => {
+      ops.symlinkSync(String(target), String(path))
+    },
+    realPath: async (path: Path) => ops.realPath(String(path)),
+    realPathSync: (path: Path): string => ops.realPathSync(String(path)),
+    chmod: async (path: Path, mode: number) => {
+      await ops.chmod(String(path), Number(mode))
+    },
+    chmodSync: (path: Path, mode: number) => {
+      ops.chmodSync(String(path), Number(mode))
+    },
+    truncate: async (path: Path, len = 0) => {
+      await ops.truncate(String(path), Number(len))
+    },
+    truncateSync: (path: Path, len = 0) => {
+      ops.truncateSync(String(path), Number(len))
+    },
+
+    // resolve to the path of a new directory or file only the current user can access
+    makeTempDir: async (options: TempOptions = {}) => ops.makeTempDir(tempOptions(options)),
+    makeTempDirSync: (options: TempOptions = {}): string => ops.makeTempDirSync(tempOptions(options)),
+    makeTempFile: async (options: TempOptions = {}) => ops.makeTempFile(tempOptions(options)),
+    makeTempFileSync: (options: TempOptions = {}): string => ops.makeTempFileSync(tempOptions(options)),
+  }
+
+  Object.assign(Edon, fs, { watchFs })
+}
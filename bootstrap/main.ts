@@ -3,7 +3,7 @@ interface RuntimeData {
   count: number
 }
 
-export default async function bootstrap(entry: string) {
+export default async function bootstrap(entry?: string) {
   var runtime: RuntimeData = {
     asyncHandle: [],
     count: 0,
@@ -24,5 +24,7 @@ export default async function bootstrap(entry: string) {
     }
   }
 
-  await import(entry)
+  if (entry) {
+    await import(entry)
+  }
 }
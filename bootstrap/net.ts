@@ -0,0 +1,358 @@
+// `Edon.connect` and `Edon.listen` for TCP and unix domain sockets, their TLS variants,
+// `Edon.listenDatagram` for UDP and `Edon.resolveDns`
+
+type NetAddr =
+  | { transport: "tcp" | "udp"; hostname: string; port: number }
+  | { transport: "unix"; path: string | null }
+
+interface Connected {
+  rid: number
+  localAddr: NetAddr
+  remoteAddr: NetAddr
+}
+
+interface NetOps {
+  connect(options: { hostname: string; port: number }): Promise<Connected>
+  connectUnix(options: { path: string }): Promise<Connected>
+  listen(options: { hostname: string; port: number }): { rid: number; addr: NetAddr }
+  listenUnix(options: { path: string }): { rid: number; addr: NetAddr }
+  accept(rid: number): Promise<Connected | null>
+  read(rid: number, buffer: Uint8Array): Promise<number | null>
+  write(rid: number, data: Uint8Array): Promise<number>
+  closeWrite(rid: number): Promise<void>
+  listenDatagram(options: { hostname: string; port: number }): { rid: number; addr: NetAddr }
+  sendDatagram(rid: number, to: { hostname: string; port: number }, data: Uint8Array): Promise<number>
+  receiveDatagram(rid: number, buffer: Uint8Array): Promise<[number, NetAddr]>
+}
+
+interface TlsClientOptions {
+  // PEM encoded certificates trusted next to the bundled Mozilla roots
+  caCerts?: string[]
+  // PEM encoded client certificate chain and private key
+  cert?: string
+  key?: string
+  alpnProtocols?: string[]
+}
+
+interface TlsOps {
+  connectTls(
+    addr: { hostname: string; port: number; serverName: string | null },
+    options: ReturnType<typeof clientOptions>,
+  ): Promise<Connected>
+  startTls(rid: number, hostname: string, options: ReturnType<typeof clientOptions>): Promise<number>
+  listenTls(options: { hostname: string; port: number; cert: string; key: string; alpnProtocols: string[] }): {
+    rid: number
+    addr: NetAddr
+  }
+  acceptTls(rid: number): Promise<Connected | null>
+  handshake(rid: number): Promise<{ alpnProtocol: string | null }>
+}
+
+interface ListenTlsOptions {
+  hostname?: string
+  port?: number
+  cert: string
+  key: string
+  alpnProtocols?: string[]
+}
+
+type RecordType = "A" | "AAAA" | "CNAME" | "MX" | "TXT" | "SRV" | "NS"
+
+const RECORD_TYPES = ["A", "AAAA", "CNAME", "MX", "TXT", "SRV", "NS"]
+
+interface ResolveDnsOptions {
+  // the system configuration is used without one
+  nameServer?: { ipAddr: string; port?: number }
+}
+
+type DnsRecord =
+  | string
+  | string[]
+  | { preference: number; exchange: string }
+  | { priority: number; weight: number; port: number; target: string }
+
+interface DnsOps {
+  resolve(args: {
+    name: string
+    recordType: RecordType
+    nameServer: { ipAddr: string; port: number } | null
+  }): Promise<DnsRecord[]>
+}
+
+interface CoreOps {
+  close(rid: number): void
+}
+
+type ConnectOptions = { transport?: "tcp"; hostname?: string; port: number } | { transport: "unix"; path: string }
+
+interface DatagramOptions {
+  transport: "udp"
+  hostname?: string
+  port?: number
+}
+
+// the largest payload of a udp datagram over ipv4
+const MAX_DATAGRAM = 65507
+
+type ListenOptions = { transport?: "tcp"; hostname?: string; port?: number } | { transport: "unix"; path: string }
+
+function clientOptions(options: TlsClientOptions) {
+  return {
+    caCerts: [...(options.caCerts ?? [])].map(String),
+    cert: options.cert === undefined ? null : String(options.cert),
+    key: options.key === undefined ? null : String(options.key),
+    alpnProtocols: [...(options.alpnProtocols ?? [])].map(String),
+  }
+}
+
+// the resource id of a connection moves to its TLS connection in `startTls`
+const detach = Symbol("detach")
+
+export default function (this: { net: NetOps; tls: TlsOps; dns: DnsOps; core: CoreOps; internal: any }) {
+  const ops = this.net
+  const tlsOps = this.tls
+  const dnsOps = this.dns
+  const core = this.core
+
+  class Conn {
+    #rid: number
+    #localAddr: NetAddr
+    #remoteAddr: NetAddr
+    #closed = false
+
+    constructor(connected: Connected) {
+      this.#rid = connected.rid
+      this.#localAddr = connected.localAddr
+      this.#remoteAddr = connected.remoteAddr
+    }
+
+    get rid() {
+      return this.#rid
+    }
+    get localAddr() {
+      return this.#localAddr
+    }
+    get remoteAddr() {
+      return this.#remoteAddr
+    }
+
+    // resolves to the number of bytes read into `buffer`, null at the end of the stream
+    read(buffer: Uint8Array): Promise<number | null> {
+      return ops.read(this.#rid, buffer)
+    }
+    // resolves to the number of bytes written, which may be less than `data.length`
+    write(data: Uint8Array): Promise<number> {
+      return ops.write(this.#rid, data)
+    }
+    closeWrite(): Promise<void> {
+      return ops.closeWrite(this.#rid)
+    }
+    close() {
+      if (!this.#closed) {
+        this.#closed = true
+        core.close(this.#rid)
+      }
+    }
+
+    [detach]() {
+      this.#closed = true
+    }
+  }
+
+  class TlsConn extends Conn {
+    // accepted connections finish their handshake on first read or write, or here
+    handshake(): Promise<{ alpnProtocol: string | null }> {
+      return tlsOps.handshake(this.rid)
+    }
+  }
+
+  class Listener {
+    #rid: number
+    #addr: NetAddr
+    #tls: boolean
+    #closed = false
+
+    constructor(rid: number, addr: NetAddr, tls = false) {
+      this.#rid = rid
+      this.#addr = addr
+      this.#tls = tls
+    }
+
+    #next(): Promise<Connected | null> {
+      return this.#tls ? tlsOps.acceptTls(this.#rid) : ops.accept(this.#rid)
+    }
+    #wrap(connected: Connected): Conn {
+      return this.#tls ? new TlsConn(connected) : new Conn(connected)
+    }
+
+    get rid() {
+      return this.#rid
+    }
+    get addr() {
+      return this.#addr
+    }
+
+    async accept(): Promise<Conn> {
+      const connected = await this.#next()
+      if (connected === null) {
+        throw new Edon.errors.BadResource("the listener was closed")
+      }
+      return this.#wrap(connected)
+    }
+    close() {
+      if (!this.#closed) {
+        this.#closed = true
+        core.close(this.#rid)
+      }
+    }
+
+    // accepted connections until the listener is closed
+    async *[Symbol.asyncIterator](): AsyncGenerator<Conn> {
+      for (;;) {
+        let connected: Connected | null
+        try {
+          connected = await this.#next()
+        } catch (err) {
+          if (this.#closed) {
+            return
+          }
+          throw err
+        }
+        if (connected === null) {
+          return
+        }
+        yield this.#wrap(connected)
+      }
+    }
+  }
+
+  class DatagramConn {
+    #rid: number
+    #addr: NetAddr
+    #closed = false
+
+    constructor(rid: number, addr: NetAddr) {
+      this.#rid = rid
+      this.#addr = addr
+    }
+
+    get rid() {
+      return this.#rid
+    }
+    get addr() {
+      return this.#addr
+    }
+
+    // a datagram longer than `buffer` is truncated
+    async receive(buffer?: Uint8Array): Promise<[Uint8Array, NetAddr]> {
+      buffer ??= new Uint8Array(MAX_DATAGRAM)
+      const [length, addr] = await ops.receiveDatagram(this.#rid, buffer)
+      return [buffer.subarray(0, length), addr]
+    }
+    send(data: Uint8Array, addr: { hostname: string; port: number }): Promise<number> {
+      return ops.sendDatagram(this.#rid, { hostname: String(addr.hostname), port: Number(addr.port) }, data)
+    }
+    close() {
+      if (!this.#closed) {
+        this.#closed = true
+        core.close(this.#rid)
+      }
+    }
+
+    // received datagrams until the socket is closed
+    async *[Symbol.asyncIterator](): AsyncGenerator<[Uint8Array, NetAddr]> {
+      for (;;) {
+        try {
+          yield await this.receive()
+        } catch (err) {
+          if (this.#closed) {
+            return
+          }
+          throw err
+        }
+      }
+    }
+  }
+
+  async function connect(options: ConnectOptions): Promise<Conn> {
+    if (options.transport === "unix") {
+      return new Conn(await ops.connectUnix({ path: String(options.path) }))
+    }
+    const hostname = options.hostname ?? "127.0.0.1"
+    return new Conn(await ops.connect({ hostname, port: Number(options.port) }))
+  }
+
+  function listen(options: ListenOptions): Listener {
+    if (options.transport === "unix") {
+      const { rid, addr } = ops.listenUnix({ path: String(options.path) })
+      return new Listener(rid, addr)
+    }
+    const hostname = options.hostname ?? "0.0.0.0"
+    const { rid, addr } = ops.listen({ hostname, port: Number(options.port ?? 0) })
+    return new Listener(rid, addr)
+  }
+
+  async function connectTls(
+    options: TlsClientOptions & { hostname?: string; port: number; serverName?: string },
+  ): Promise<TlsConn> {
+    const addr = {
+      hostname: options.hostname ?? "127.0.0.1",
+      port: Number(options.port),
+      serverName: options.serverName ?? null,
+    }
+    return new TlsConn(await tlsOps.connectTls(addr, clientOptions(options)))
+  }
+
+  function listenTls(options: ListenTlsOptions): Listener {
+    const { rid, addr } = tlsOps.listenTls({
+      hostname: options.hostname ?? "0.0.0.0",
+      port: Number(options.port ?? 0),
+      cert: String(options.cert),
+      key: String(options.key),
+      alpnProtocols: [...(options.alpnProtocols ?? [])].map(String),
+    })
+    return new Listener(rid, addr, true)
+  }
+
+  // `conn` is unusable afterwards, reads and writes go through the returned connection
+  async function startTls(conn: Conn, options: TlsClientOptions & { hostname?: string } = {}): Promise<TlsConn> {
+    const pending = tlsOps.startTls(conn.rid, options.hostname ?? "127.0.0.1", clientOptions(options))
+    conn[detach]()
+    const rid = await pending
+    return new TlsConn({ rid, localAddr: conn.localAddr, remoteAddr: conn.remoteAddr })
+  }
+
+  function listenDatagram(options: DatagramOptions): DatagramConn {
+    if (options.transport !== "udp") {
+      throw new TypeError(`unsupported datagram transport \`${options.transport}\``)
+    }
+    const hostname = options.hostname ?? "0.0.0.0"
+    const { rid, addr } = ops.listenDatagram({ hostname, port: Number(options.port ?? 0) })
+    return new DatagramConn(rid, addr)
+  }
+
+  // rejects with `Edon.errors.NotFound` when the name has no records of that type
+  async function resolveDns(name: string, recordType: RecordType, options: ResolveDnsOptions = {}): Promise<DnsRecord[]> {
+    if (!RECORD_TYPES.includes(recordType)) {
+      throw new TypeError(`unsupported dns record type \`${recordType}\``)
+    }
+    const server = options.nameServer
+    const nameServer = server ? { ipAddr: String(server.ipAddr), port: Number(server.port ?? 53) } : null
+    return dnsOps.resolve({ name: String(name), recordType, nameServer })
+  }
+
+  // @ts-ignore
+  Edon.connect = connect
+  // @ts-ignore
+  Edon.listen = listen
+  // @ts-ignore
+  Edon.listenDatagram = listenDatagram
+  // @ts-ignore
+  Edon.connectTls = connectTls
+  // @ts-ignore
+  Edon.listenTls = listenTls
+  // @ts-ignore
+  Edon.startTls = startTls
+  // @ts-ignore
+  Edon.resolveDns = resolveDns
+}
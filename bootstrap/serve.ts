@@ -0,0 +1,251 @@
+// `Edon.serve`, an HTTP/1.1 and HTTP/2 server calling a handler for every request
+
+interface Addr {
+  transport: "tcp"
+  hostname: string
+  port: number
+}
+
+interface NextRequest {
+  rid: number
+  method: string
+  url: string
+  headers: [string, string][]
+  hasBody: boolean
+  remoteAddr: Addr
+}
+
+interface RespondArgs {
+  status: number
+  headers: [string, string][]
+  body: Uint8Array | null
+  stream: boolean
+}
+
+interface ServeOps {
+  listen(options: { hostname: string; port: number }): { rid: number; addr: Addr }
+  next(rid: number): Promise<NextRequest | null>
+  shutdown(rid: number): void
+  read(rid: number): Promise<Uint8Array | null>
+  respond(rid: number, response: RespondArgs): number | null
+  write(rid: number, chunk: Uint8Array): Promise<void>
+  upgrade(rid: number): Promise<number>
+}
+
+interface CoreOps {
+  close(rid: number): void
+}
+
+interface ServeInfo {
+  remoteAddr: Addr
+}
+
+type Handler = (request: Request, info: ServeInfo) => Response | Promise<Response>
+
+interface ServeOptions {
+  port?: number
+  hostname?: string
+  signal?: AbortSignal
+  handler?: Handler
+  onListen?(addr: Addr): void
+  onError?(error: unknown): Response | Promise<Response>
+}
+
+interface UpgradeOptions {
+  protocol?: string
+}
+
+interface HttpServer {
+  readonly addr: Addr
+  readonly finished: Promise<void>
+  shutdown(): Promise<void>
+}
+
+function defaultOnError(error: unknown): Response {
+  console.error(error)
+  return new Response("Internal Server Error", { status: 500 })
+}
+
+export default function (this: { serve: ServeOps; core: CoreOps; internal: any }) {
+  const ops = this.serve
+  const core = this.core
+  const internal = this.internal
+  // requests received by a server, with the resource id their response goes to
+  const requests = new WeakMap<Request, number>()
+  const upgrades = new WeakMap<Response, { socket: WebSocket; protocol: string }>()
+
+  // the request body is pulled from the connection as it is read
+  function requestBody(rid: number): ReadableStream {
+    return new ReadableStream(
+      {
+        async pull(controller) {
+          const chunk = await ops.read(rid)
+          if (chunk === null) {
+            controller.close()
+          } else {
+            controller.enqueue(chunk)
+          }
+        },
+      },
+      { highWaterMark: 0 },
+    )
+  }
+
+  async function respond(rid: number, response: Response) {
+    // a network error has no status to send
+    const status = response.type === "error" ? 500 : response.status
+    const headers = [...response.headers]
+    const source = internal.fetch.bodySource(response)
+    if (response.body === null || source !== null) {
+      ops.respond(rid, { status, headers, body: source, stream: false })
+      return
+    }
+
+    const writer = ops.respond(rid, { status, headers, body: null, stream: true })!
+    const encoder = new TextEncoder()
+    try {
+      // leaving the loop early cancels the body, e.g. when the client went away
+      for await (const chunk of response.body) {
+        const bytes = typeof chunk === "string" ? encoder.encode(chunk) : internal.toBytes(chunk)
+        await ops.write(writer, bytes)
+      }
+    } finally {
+      core.close(writer)
+    }
+  }
+
+  async function handle(next: NextRequest, handler: Handler, onError: ServeOptions["onError"]) {
+    const bodyless = next.method === "GET" || next.method === "HEAD"
+    const request = new Request(next.url, {
+      method: next.method,
+      headers: next.headers,
+      body: next.hasBody && !bodyless ? requestBody(next.rid) : null,
+    })
+    requests.set(request, next.rid)
+    const info = { remoteAddr: next.remoteAddr }
+
+    let response: Response
+    try {
+      response = await handler(request, info)
+      if (!(response instanceof Response)) {
+        throw new TypeError("the handler must return a Response")
+      }
+    } catch (error) {
+      try {
+        response = await (onError ?? defaultOnError)(error)
+      } catch (error) {
+        response = defaultOnError(error)
+      }
+    }
+    const upgrade = upgrades.get(response)
+    try {
+      await respond(next.rid, response)
+      if (upgrade) {
+        const rid = await ops.upgrade(next.rid)
+        internal.websocket.open(upgrade.socket, rid, upgrade.protocol)
+      }
+    } catch (error) {
+      if (upgrade) {
+        internal.websocket.fail(upgrade.socket)
+      }
+      // the response could not be written, the connection is gone
+      if (!(error instanceof Edon.errors.BrokenPipe)) {
+        console.error(error)
+      }
+    } finally {
+      core.close(next.rid)
+    }
+  }
+
+  // the socket opens once the handler returned `response`
+  function upgradeWebSocket(request: Request, options: UpgradeOptions = {}) {
+    const rid = requests.get(request)
+    if (rid === undefined) {
+      throw new TypeError("only requests received by Edon.serve can be upgraded")
+    }
+    const tokens = (name: string) =>
+      (request.headers.get(name) ?? "")
+        .split(",")
+        .map((token) => token.trim())
+        .filter((token) => token)
+    const key = request.headers.get("sec-websocket-key")
+    if (
+      !tokens("upgrade").some((token) => token.toLowerCase() === "websocket") ||
+      !tokens("connection").some((token) => token.toLowerCase() === "upgrade") ||
+      request.headers.get("sec-websocket-version") !== "13" ||
+      key === null
+    ) {
+      throw new TypeError("the request is not a WebSocket handshake")
+    }
+    const protocol = options.protocol ?? ""
+    if (protocol && !tokens("sec-websocket-protocol").includes(protocol)) {
+      throw new TypeError(`the client did not offer the protocol \`${protocol}\``)
+    }
+
+    const headers: [string, string][] = [
+      ["upgrade", "websocket"],
+      ["connection", "Upgrade"],
+      ["sec-websocket-accept", internal.websocket.acceptKey(key)],
+    ]
+    if (protocol) {
+      headers.push(["sec-websocket-protocol", protocol])
+    }
+    const response: Response = internal.fetch.switchingProtocols(headers)
+    const socket: WebSocket = internal.websocket.accept(request.url)
+    upgrades.set(response, { socket, protocol })
+    return { socket, response }
+  }
+
+  function serve(options: ServeOptions | Handler, handler?: Handler): HttpServer {
+    if (typeof options === "function") {
+      handler = options
+      options = {}
+    }
+    handler ??= options.handler
+    if (typeof handler !== "function") {
+      throw new TypeError("serve requires a handler function")
+    }
+    const { signal, onError } = options
+    signal?.throwIfAborted()
+
+    const listener = ops.listen({
+      hostname: options.hostname ?? "0.0.0.0",
+      port: options.port ?? 8000,
+    })
+    const { addr } = listener
+    if (options.onListen) {
+      options.onListen(addr)
+    } else {
+      console.log(`Listening on http://${addr.hostname}:${addr.port}/`)
+    }
+
+    // requests are handled concurrently, `next` resolves to null once the server is drained
+    const finished = (async () => {
+      const inflight = new Set<Promise<void>>()
+      for (let next = await ops.next(listener.rid); next; next = await ops.next(listener.rid)) {
+        const handled = handle(next, handler!, onError).finally(() => inflight.delete(handled))
+        inflight.add(handled)
+      }
+      await Promise.all(inflight)
+      core.close(listener.rid)
+    })()
+
+    let stopped = false
+    const shutdown = () => {
+      if (!stopped) {
+        stopped = true
+        signal?.removeEventListener("abort", shutdown)
+        ops.shutdown(listener.rid)
+      }
+      return finished
+    }
+    signal?.addEventListener("abort", shutdown, { once: true })
+
+    return { addr, finished, shutdown }
+  }
+
+  // @ts-ignore
+  Edon.serve = serve
+  // @ts-ignore
+  Edon.upgradeWebSocket = upgradeWebSocket
+}
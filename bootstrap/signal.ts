@@ -0,0 +1,67 @@
+// `Edon.addSignalListener` and `Edon.removeSignalListener`, the program keeps running while a
+// signal has listeners
+
+interface SignalOps {
+  bind(signal: string): number
+  poll(rid: number): Promise<boolean>
+}
+
+interface CoreOps {
+  close(rid: number): void
+}
+
+export default function (this: { signal: SignalOps; core: CoreOps }) {
+  const ops = this.signal
+  const core = this.core
+
+  // every signal with listeners, bound once
+  const bound = new Map<string, { rid: number; listeners: Set<() => void> }>()
+
+  async function dispatch(signal: string, entry: { rid: number; listeners: Set<() => void> }) {
+    while (await ops.poll(entry.rid)) {
+      for (const listener of [...entry.listeners]) {
+        // a throwing listener is reported, the others still run and the signal stays bound
+        try {
+          listener()
+        } catch (error) {
+          console.error(error)
+        }
+      }
+      // the last listener may have removed itself, closing the resource
+      if (bound.get(signal) !== entry) {
+        return
+      }
+    }
+  }
+
+  // e.g. `Edon.addSignalListener("SIGINT", () => server.shutdown())`
+  function addSignalListener(signal: string, listener: () => void) {
+    if (typeof listener !== "function") {
+      throw new TypeError("signal listener must be a function")
+    }
+    signal = String(signal)
+    let entry = bound.get(signal)
+    if (!entry) {
+      entry = { rid: ops.bind(signal), listeners: new Set() }
+      bound.set(signal, entry)
+      dispatch(signal, entry)
+    }
+    entry.listeners.add(listener)
+  }
+
+  // the signal is released with its last listener, and gets its default action back
+  function removeSignalListener(signal: string, listener: () => void) {
+    signal = String(signal)
+    const entry = bound.get(signal)
+    if (!entry?.listeners.delete(listener) || entry.listeners.size) {
+      return
+    }
+    bound.delete(signal)
+    core.close(entry.rid)
+  }
+
+  // @ts-ignore
+  Edon.addSignalListener = addSignalListener
+  // @ts-ignore
+  Edon.removeSignalListener = removeSignalListener
+}
@@ -0,0 +1,85 @@
+// TextEncoder and TextDecoder, utf-8 only
+
+interface EncodingOps {
+  encode(input: string): Uint8Array
+  decode(input: Uint8Array, fatal: boolean, ignoreBOM: boolean): string
+}
+
+const UTF8_LABELS = ["utf-8", "utf8", "unicode-1-1-utf-8"]
+
+// views and array buffers as a Uint8Array over the same memory
+function toBytes(input: ArrayBuffer | ArrayBufferView): Uint8Array {
+  if (input instanceof Uint8Array) {
+    return input
+  }
+  if (ArrayBuffer.isView(input)) {
+    return new Uint8Array(input.buffer, input.byteOffset, input.byteLength)
+  }
+  if (input instanceof ArrayBuffer) {
+    return new Uint8Array(input)
+  }
+  throw new TypeError("expected an ArrayBuffer or an ArrayBufferView")
+}
+
+export default function (this: { encoding: EncodingOps; internal: any }) {
+  const ops = this.encoding
+  this.internal.toBytes = toBytes
+
+  class TextEncoder {
+    get encoding() {
+      return "utf-8"
+    }
+    encode(input = ""): Uint8Array {
+      return ops.encode(String(input))
+    }
+    encodeInto(input: string, dest: Uint8Array) {
+      const bytes = ops.encode(String(input))
+      let read = 0
+      let written = 0
+      // only copy whole characters
+      for (const char of String(input)) {
+        const size = ops.encode(char).length
+        if (written + size > dest.length) {
+          break
+        }
+        read += char.length
+        written += size
+      }
+      dest.set(bytes.subarray(0, written))
+      return { read, written }
+    }
+  }
+
+  class TextDecoder {
+    #fatal: boolean
+    #ignoreBOM: boolean
+
+    constructor(
+      label = "utf-8",
+      options: { fatal?: boolean; ignoreBOM?: boolean } = {},
+    ) {
+      if (!UTF8_LABELS.includes(String(label).trim().toLowerCase())) {
+        throw new RangeError(`The encoding label provided ('${label}') is invalid.`)
+      }
+      this.#fatal = !!options.fatal
+      this.#ignoreBOM = !!options.ignoreBOM
+    }
+    get encoding() {
+      return "utf-8"
+    }
+    get fatal() {
+      return this.#fatal
+    }
+    get ignoreBOM() {
+      return this.#ignoreBOM
+    }
+    decode(input?: ArrayBuffer | ArrayBufferView): string {
+      if (input === undefined) {
+        return ""
+      }
+      return ops.decode(toBytes(input), this.#fatal, this.#ignoreBOM)
+    }
+  }
+
+  Object.assign(globalThis, { TextEncoder, TextDecoder })
+}
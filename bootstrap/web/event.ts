@@ -0,0 +1,248 @@
+// DOMException, Event, EventTarget, AbortController and AbortSignal
+
+const ERROR_CODES: Record<string, number> = {
+  IndexSizeError: 1,
+  HierarchyRequestError: 3,
+  WrongDocumentError: 4,
+  InvalidCharacterError: 5,
+  NoModificationAllowedError: 7,
+  NotFoundError: 8,
+  NotSupportedError: 9,
+  InvalidStateError: 11,
+  SyntaxError: 12,
+  InvalidModificationError: 13,
+  NamespaceError: 14,
+  InvalidAccessError: 15,
+  TypeMismatchError: 17,
+  SecurityError: 18,
+  NetworkError: 19,
+  AbortError: 20,
+  URLMismatchError: 21,
+  QuotaExceededError: 22,
+  TimeoutError: 23,
+  InvalidNodeTypeError: 24,
+  DataCloneError: 25,
+}
+
+class DOMException extends Error {
+  constructor(message = "", name = "Error") {
+    super(message)
+    Object.defineProperty(this, "name", {
+      value: name,
+      configurable: true,
+      writable: true,
+    })
+  }
+  get code(): number {
+    return ERROR_CODES[this.name] ?? 0
+  }
+}
+
+interface EventInit {
+  bubbles?: boolean
+  cancelable?: boolean
+  composed?: boolean
+}
+
+const stopped = new WeakSet<Event>()
+
+class Event {
+  readonly type: string
+  readonly bubbles: boolean
+  readonly cancelable: boolean
+  readonly composed: boolean
+  readonly timeStamp = Date.now()
+  defaultPrevented = false
+  target: EventTarget | null = null
+  currentTarget: EventTarget | null = null
+
+  constructor(type: string, init: EventInit = {}) {
+    if (arguments.length === 0) {
+      throw new TypeError("Event constructor requires a type")
+    }
+    this.type = String(type)
+    this.bubbles = !!init.bubbles
+    this.cancelable = !!init.cancelable
+    this.composed = !!init.composed
+  }
+  preventDefault() {
+    if (this.cancelable) {
+      this.defaultPrevented = true
+    }
+  }
+  stopPropagation() {}
+  stopImmediatePropagation() {
+    stopped.add(this)
+  }
+}
+
+type Listener = ((event: Event) => void) | { handleEvent(event: Event): void }
+
+interface ListenerEntry {
+  callback: Listener
+  once: boolean
+}
+
+interface ListenerOptions {
+  once?: boolean
+  signal?: AbortSignal
+}
+
+class EventTarget {
+  #listeners = new Map<string, ListenerEntry[]>()
+
+  addEventListener(
+    type: string,
+    callback: Listener | null,
+    options: ListenerOptions | boolean = {},
+  ) {
+    if (!callback) {
+      return
+    }
+    const { once = false, signal } = typeof options === "object" ? options : {}
+    if (signal?.aborted) {
+      return
+    }
+    const listeners = this.#listeners.get(type) ?? []
+    if (listeners.some((entry) => entry.callback === callback)) {
+      return
+    }
+    listeners.push({ callback, once })
+    this.#listeners.set(type, listeners)
+    signal?.addEventListener("abort", () => this.removeEventListener(type, callback))
+  }
+
+  removeEventListener(type: string, callback: Listener | null) {
+    const listeners = this.#listeners.get(type)
+    if (!listeners) {
+      return
+    }
+    const index = listeners.findIndex((entry) => entry.callback === callback)
+    if (index >= 0) {
+      listeners.splice(index, 1)
+    }
+  }
+
+  dispatchEvent(event: Event): boolean {
+    event.target = this
+    event.currentTarget = this
+    for (const entry of [...(this.#listeners.get(event.type) ?? [])]) {
+      if (entry.once) {
+        this.removeEventListener(event.type, entry.callback)
+      }
+      try {
+        if (typeof entry.callback === "function") {
+          entry.callback.call(this, event)
+        } else {
+          entry.callback.handleEvent(event)
+        }
+      } catch (err) {
+        reportError(err)
+      }
+      if (stopped.has(event)) {
+        break
+      }
+    }
+    event.currentTarget = null
+    return !event.defaultPrevented
+  }
+}
+
+// errors thrown by listeners do not stop the dispatch, they surface as unhandled rejections
+function reportError(err: unknown) {
+  Promise.reject(err)
+}
+
+const signalAbort = Symbol("signalAbort")
+
+class AbortSignal extends EventTarget {
+  #aborted = false
+  #reason: any = undefined
+  onabort: ((event: Event) => void) | null = null
+
+  constructor(key?: symbol) {
+    super()
+    if (key !== signalAbort) {
+      throw new TypeError("Illegal constructor")
+    }
+  }
+
+  get aborted() {
+    return this.#aborted
+  }
+  get reason() {
+    return this.#reason
+  }
+
+  throwIfAborted() {
+    if (this.#aborted) {
+      throw this.#reason
+    }
+  }
+
+  [signalAbort](reason?: any) {
+    if (this.#aborted) {
+      return
+    }
+    this.#aborted = true
+    this.#reason =
+      reason === undefined
+        ? new DOMException("This operation was aborted", "AbortError")
+        : reason
+    const event = new Event("abort")
+    this.onabort?.call(this, event)
+    this.dispatchEvent(event)
+  }
+
+  static abort(reason?: any): AbortSignal {
+    const signal = new AbortSignal(signalAbort)
+    signal[signalAbort](reason)
+    return signal
+  }
+
+  static timeout(ms: number): AbortSignal {
+    const signal = new AbortSignal(signalAbort)
+    setTimeout(() => {
+      signal[signalAbort](new DOMException("Signal timed out.", "TimeoutError"))
+    }, ms)
+    return signal
+  }
+
+  static any(signals: AbortSignal[]): AbortSignal {
+    const signal = new AbortSignal(signalAbort)
+    for (const source of signals) {
+      if (source.aborted) {
+        signal[signalAbort](source.reason)
+        return signal
+      }
+    }
+    for (const source of signals) {
+      source.addEventListener("abort", () => signal[signalAbort](source.reason), {
+        once: true,
+      })
+    }
+    return signal
+  }
+}
+
+class AbortController {
+  #signal = new AbortSignal(signalAbort)
+
+  get signal() {
+    return this.#signal
+  }
+
+  abort(reason?: any) {
+    this.#signal[signalAbort](reason)
+  }
+}
+
+export default function () {
+  Object.assign(globalThis, {
+    DOMException,
+    Event,
+    EventTarget,
+    AbortSignal,
+    AbortController,
+  })
+}
@@ -0,0 +1,590 @@
+// fetch, Headers, Request and Response
+
+interface FetchRequest {
+  method: string
+  url: string
+  headers: [string, string][]
+  body: Uint8Array | null
+  redirect: RequestRedirect
+  cancelRid: number | null
+}
+
+interface FetchResponse {
+  status: number
+  statusText: string
+  url: string
+  redirected: boolean
+  headers: [string, string][]
+  bodyRid: number | null
+}
+
+interface FetchOps {
+  send(request: FetchRequest): Promise<FetchResponse>
+  read(rid: number): Promise<Uint8Array | null>
+}
+
+interface CoreOps {
+  close(rid: number): void
+  cancelHandle(): number
+}
+
+type RequestRedirect = "follow" | "error" | "manual"
+type HeadersInit = Headers | [string, string][] | Record<string, string>
+
+const REDIRECTS = ["follow", "error", "manual"]
+const TOKEN = /^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/
+const NULL_BODY_STATUS = [101, 103, 204, 205, 304]
+
+function headerName(name: string): string {
+  name = String(name)
+  if (!TOKEN.test(name)) {
+    throw new TypeError(`Header name is not valid: "${name}"`)
+  }
+  return name.toLowerCase()
+}
+
+function headerValue(value: string): string {
+  value = String(value).replace(/^[\t\n\r ]+|[\t\n\r ]+$/g, "")
+  if (/[\0\r\n]/.test(value)) {
+    throw new TypeError(`Header value is not valid: "${value}"`)
+  }
+  return value
+}
+
+const immutable = new WeakSet<Headers>()
+
+class Headers {
+  // names are lowercased, order of insertion is kept until iteration sorts them
+  #list: [string, string][] = []
+
+  constructor(init?: HeadersInit) {
+    if (init === undefined || init === null) {
+      return
+    }
+    if (init instanceof Headers) {
+      this.#list = init.#list.map(([name, value]) => [name, value])
+    } else if (typeof init === "object" && Symbol.iterator in init) {
+      for (const pair of init as Iterable<string[]>) {
+        if (pair.length !== 2) {
+          throw new TypeError("Each header pair must be an iterable [name, value] tuple")
+        }
+        this.append(pair[0], pair[1])
+      }
+    } else if (typeof init === "object") {
+      for (const [name, value] of Object.entries(init)) {
+        this.append(name, value)
+      }
+    } else {
+      throw new TypeError("Headers init must be an object or an iterable of pairs")
+    }
+  }
+
+  #mutate() {
+    if (immutable.has(this)) {
+      throw new TypeError("Headers are immutable")
+    }
+  }
+
+  append(name: string, value: string) {
+    const pair: [string, string] = [headerName(name), headerValue(value)]
+    this.#mutate()
+    this.#list.push(pair)
+  }
+  delete(name: string) {
+    name = headerName(name)
+    this.#mutate()
+    this.#list = this.#list.filter(([n]) => n !== name)
+  }
+  get(name: string): string | null {
+    name = headerName(name)
+    const values = this.#list.filter(([n]) => n === name).map(([, v]) => v)
+    return values.length ? values.join(", ") : null
+  }
+  getSetCookie(): string[] {
+    return this.#list.filter(([n]) => n === "set-cookie").map(([, v]) => v)
+  }
+  has(name: string): boolean {
+    name = headerName(name)
+    return this.#list.some(([n]) => n === name)
+  }
+  set(name: string, value: string) {
+    const pair: [string, string] = [headerName(name), headerValue(value)]
+    this.#mutate()
+    const index = this.#list.findIndex(([n]) => n === pair[0])
+    if (index < 0) {
+      this.#list.push(pair)
+      return
+    }
+    this.#list[index] = pair
+    this.#list = this.#list.filter(([n], i) => i <= index || n !== pair[0])
+  }
+
+  // sorted by name with the values of a name combined, `set-cookie` is never combined
+  #sorted(): [string, string][] {
+    const names = [...new Set(this.#list.map(([name]) => name))].sort()
+    return names.flatMap((name): [string, string][] =>
+      name === "set-cookie"
+        ? this.getSetCookie().map((value) => [name, value])
+        : [[name, this.get(name)!]],
+    )
+  }
+  forEach(callback: (value: string, name: string, headers: Headers) => void, thisArg?: any) {
+    for (const [name, value] of this.#sorted()) {
+      callback.call(thisArg, value, name, this)
+    }
+  }
+  keys() {
+    return this.#sorted().map(([name]) => name)[Symbol.iterator]()
+  }
+  values() {
+    return this.#sorted().map(([, value]) => value)[Symbol.iterator]()
+  }
+  entries() {
+    return this.#sorted()[Symbol.iterator]()
+  }
+  [Symbol.iterator]() {
+    return this.entries()
+  }
+}
+
+interface ExtractedBody {
+  stream: ReadableStream
+  // the bytes of the body when they are known up front
+  source: Uint8Array | null
+  type: string | null
+}
+
+// streams over bytes known up front, a server can send them with a content length
+const sources = new WeakMap<ReadableStream, Uint8Array>()
+
+function streamOf(bytes: Uint8Array): ReadableStream {
+  const stream = new ReadableStream({
+    start(controller) {
+      if (bytes.length) {
+        controller.enqueue(bytes)
+      }
+      controller.close()
+    },
+  })
+  sources.set(stream, bytes)
+  return stream
+}
+
+function extractBody(internal: any, body: any): ExtractedBody {
+  const encode = (text: string) => new TextEncoder().encode(text)
+  if (typeof body === "string") {
+    const source = encode(body)
+    return { stream: streamOf(source), source, type: "text/plain;charset=UTF-8" }
+  }
+  if (body instanceof URLSearchParams) {
+    const source = encode(body.toString())
+    return {
+      stream: streamOf(source),
+      source,
+      type: "application/x-www-form-urlencoded;charset=UTF-8",
+    }
+  }
+  if (body instanceof ArrayBuffer || ArrayBuffer.isView(body)) {
+    // copy, later writes to the buffer must not change the body
+    const source = internal.toBytes(body).slice()
+    return { stream: streamOf(source), source, type: null }
+  }
+  if (body instanceof ReadableStream) {
+    if (body.locked || internal.streams.isDisturbed(body)) {
+      throw new TypeError("ReadableStream body is locked or disturbed")
+    }
+    return { stream: body, source: null, type: null }
+  }
+  if (body instanceof FormData) {
+    const boundary = internal.form.boundary()
+    const stream = new ReadableStream({
+      async start(controller) {
+        controller.enqueue(await internal.form.multipart(body, boundary, encode))
+        controller.close()
+      },
+    })
+    return { stream, source: null, type: `multipart/form-data; boundary=${boundary}` }
+  }
+  if (internal.form.isBlobLike(body)) {
+    const stream = new ReadableStream({
+      async start(controller) {
+        controller.enqueue(new Uint8Array(await body.arrayBuffer()))
+        controller.close()
+      },
+    })
+    return { stream, source: null, type: body.type || null }
+  }
+  return extractBody(internal, String(body))
+}
+
+// the body of a Request or Response, read once by text(), json() or arrayBuffer()
+const bodies = new WeakMap<Body, ReadableStream | null>()
+
+class Body {
+  #internal: any
+
+  constructor(internal: any, body: ReadableStream | null) {
+    this.#internal = internal
+    bodies.set(this, body)
+  }
+
+  get body(): ReadableStream | null {
+    return bodies.get(this) ?? null
+  }
+  get bodyUsed(): boolean {
+    const body = this.body
+    return body !== null && this.#internal.streams.isDisturbed(body)
+  }
+
+  async #consume(): Promise<Uint8Array> {
+    const body = this.body
+    if (body === null) {
+      return new Uint8Array()
+    }
+    if (this.bodyUsed || body.locked) {
+      throw new TypeError("Body already consumed")
+    }
+    return this.#internal.streams.readAll(body)
+  }
+
+  async arrayBuffer(): Promise<ArrayBuffer> {
+    const bytes = await this.#consume()
+    return bytes.buffer.slice(bytes.byteOffset, bytes.byteOffset + bytes.byteLength)
+  }
+  async bytes(): Promise<Uint8Array> {
+    return this.#consume()
+  }
+  async text(): Promise<string> {
+    return new TextDecoder().decode(await this.#consume())
+  }
+  async json(): Promise<any> {
+    return JSON.parse(await this.text())
+  }
+}
+
+// tee the body, the source keeps one branch and the clone gets the other
+function cloneBody(source: Body): ReadableStream | null {
+  const body = source.body
+  if (body === null) {
+    return null
+  }
+  if (source.bodyUsed || body.locked) {
+    throw new TypeError("Body has already been consumed")
+  }
+  const [kept, cloned] = body.tee()
+  bodies.set(source, kept)
+  return cloned
+}
+
+interface ResponseInit {
+  status?: number
+  statusText?: string
+  headers?: HeadersInit
+}
+
+interface RequestInit {
+  method?: string
+  headers?: HeadersInit
+  body?: any
+  redirect?: RequestRedirect
+  signal?: AbortSignal | null
+}
+
+interface ResponseMeta {
+  url: string
+  redirected: boolean
+  type: "basic" | "default" | "error"
+}
+
+const responses = new WeakMap<Response, ResponseMeta>()
+
+function normalizeMethod(method: string): string {
+  method = String(method)
+  if (!TOKEN.test(method)) {
+    throw new TypeError(`"${method}" is not a valid HTTP method`)
+  }
+  const upper = method.toUpperCase()
+  if (["CONNECT", "TRACE", "TRACK"].includes(upper)) {
+    throw new TypeError(`"${method}" HTTP method is unsupported`)
+  }
+  return ["DELETE", "GET", "HEAD", "OPTIONS", "POST", "PUT"].includes(upper) ? upper : method
+}
+
+export default function (this: { fetch: FetchOps; core: CoreOps; internal: any }) {
+  const ops = this.fetch
+  const core = this.core
+  const internal = this.internal
+
+  class Response extends Body {
+    #status: number
+    #statusText: string
+    #headers: Headers
+
+    constructor(body: any = null, init: ResponseInit = {}) {
+      const status = init.status ?? 200
+      if (!Number.isInteger(status) || status < 200 || status > 599) {
+        throw new RangeError(`The status provided (${status}) is outside the range [200, 599]`)
+      }
+      const extracted = body === null || body === undefined ? null : extractBody(internal, body)
+      if (extracted && NULL_BODY_STATUS.includes(status)) {
+        throw new TypeError(`Response with null body status (${status}) cannot have a body`)
+      }
+      super(internal, extracted?.stream ?? null)
+      this.#status = status
+      this.#statusText = String(init.statusText ?? "")
+      this.#headers = new Headers(init.headers)
+      if (extracted?.type && !this.#headers.has("content-type")) {
+        this.#headers.set("content-type", extracted.type)
+      }
+    }
+
+    // a network error, the only response with a status of 0
+    static error(): Response {
+      const response = new Response()
+      response.#status = 0
+      immutable.add(response.#headers)
+      responses.set(response, { url: "", redirected: false, type: "error" })
+      return response
+    }
+
+    static redirect(url: string | { toString(): string }, status = 302): Response {
+      if (![301, 302, 303, 307, 308].includes(status)) {
+        throw new RangeError(`Invalid redirect status ${status}`)
+      }
+      const response = new Response(null, { status, headers: { location: String(url) } })
+      immutable.add(response.#headers)
+      return response
+    }
+
+    // the response to a websocket upgrade, the only one with a 101 status
+    static switchingProtocols(headers: [string, string][]): Response {
+      const response = new Response(null, { headers })
+      response.#status = 101
+      immutable.add(response.#headers)
+      return response
+    }
+
+    static json(data: any, init: ResponseInit = {}): Response {
+      const body = JSON.stringify(data)
+      if (body === undefined) {
+        throw new TypeError("The data is not JSON serializable")
+      }
+      const headers = new Headers(init.headers)
+      if (!headers.has("content-type")) {
+        headers.set("content-type", "application/json")
+      }
+      return new Response(body, { ...init, headers })
+    }
+
+    get status() {
+      return this.#status
+    }
+    get statusText() {
+      return this.#statusText
+    }
+    get ok() {
+      return this.#status >= 200 && this.#status < 300
+    }
+    get headers() {
+      return this.#headers
+    }
+    get url() {
+      return responses.get(this)?.url ?? ""
+    }
+    get redirected() {
+      return responses.get(this)?.redirected ?? false
+    }
+    get type() {
+      return responses.get(this)?.type ?? "default"
+    }
+
+    clone(): Response {
+      const response = new Response(null, {
+        status: this.#status === 0 ? 200 : this.#status,
+        statusText: this.#statusText,
+        headers: this.#headers,
+      })
+      response.#status = this.#status
+      bodies.set(response, cloneBody(this))
+      if (immutable.has(this.#headers)) {
+        immutable.add(response.#headers)
+      }
+      const meta = responses.get(this)
+      if (meta) {
+        responses.set(response, { ...meta })
+      }
+      return response
+    }
+  }
+
+  class Request extends Body {
+    #method: string
+    #url: string
+    #headers: Headers
+    #redirect: RequestRedirect
+    #signal: AbortSignal
+
+    constructor(input: Request | string | { toString(): string }, init: RequestInit = {}) {
+      const source = input instanceof Request ? input : null
+      const method = normalizeMethod(init.method ?? source?.method ?? "GET")
+      const redirect = init.redirect ?? source?.redirect ?? "follow"
+      if (!REDIRECTS.includes(redirect)) {
+        throw new TypeError(`"${redirect}" is not a valid redirect mode`)
+      }
+      const headers = new Headers(init.headers ?? source?.headers)
+
+      let body: ReadableStream | null = null
+      const bodyless = method === "GET" || method === "HEAD"
+      if (init.body !== undefined && init.body !== null) {
+        if (bodyless) {
+          throw new TypeError("Request with GET/HEAD method cannot have body")
+        }
+        const extracted = extractBody(internal, init.body)
+        if (extracted.type && !headers.has("content-type")) {
+          headers.set("content-type", extracted.type)
+        }
+        body = extracted.stream
+      } else if (init.body === undefined && source?.body) {
+        if (bodyless) {
+          throw new TypeError("Request with GET/HEAD method cannot have body")
+        }
+        if (source.bodyUsed || source.body.locked) {
+          throw new TypeError("Cannot construct a Request with a Request whose body is already used")
+        }
+        // the body moves over, reading it marks the source as used
+        body = source.body
+      }
+      super(internal, body)
+
+      this.#method = method
+      this.#url = source?.url ?? String(input)
+      this.#headers = headers
+      this.#redirect = redirect
+      const signal = init.signal === undefined ? source?.signal : init.signal
+      this.#signal = signal ?? new AbortController().signal
+    }
+
+    get method() {
+      return this.#method
+    }
+    get url() {
+      return this.#url
+    }
+    get headers() {
+      return this.#headers
+    }
+    get redirect() {
+      return this.#redirect
+    }
+    get signal() {
+      return this.#signal
+    }
+
+    clone(): Request {
+      const request = new Request(this.#url, {
+        method: this.#method,
+        headers: this.#headers,
+        redirect: this.#redirect,
+        signal: this.#signal,
+      })
+      bodies.set(request, cloneBody(this))
+      return request
+    }
+  }
+
+  // the body of a fetched response, read from the `bodyRid` resource as it is pulled
+  function responseBody(rid: number, signal?: AbortSignal): ReadableStream {
+    let closed = false
+    let controller: ReadableStreamDefaultController
+    const onAbort = () => {
+      close()
+      controller.error(signal!.reason)
+    }
+    const close = () => {
+      if (!closed) {
+        closed = true
+        signal?.removeEventListener("abort", onAbort)
+        core.close(rid)
+      }
+    }
+    signal?.addEventListener("abort", onAbort, { once: true })
+    return new ReadableStream(
+      {
+        start(c) {
+          controller = c
+        },
+        async pull(controller) {
+          let chunk: Uint8Array | null
+          try {
+            chunk = await ops.read(rid)
+          } catch (err) {
+            close()
+            throw signal?.aborted ? signal.reason : err
+          }
+          if (chunk === null) {
+            close()
+            controller.close()
+          } else {
+            controller.enqueue(chunk)
+          }
+        },
+        cancel: close,
+      },
+      { highWaterMark: 0 },
+    )
+  }
+
+  async function fetch(input: Request | string | { toString(): string }, init?: RequestInit) {
+    const request = new Request(input, init)
+    const { method, url, redirect, signal } = request
+    signal.throwIfAborted()
+
+    const body = request.body && (await internal.streams.readAll(request.body))
+    signal.throwIfAborted()
+
+    // aborting closes the cancel handle, which fails the pending send
+    const cancelRid = core.cancelHandle()
+    const onAbort = () => core.close(cancelRid)
+    signal.addEventListener("abort", onAbort, { once: true })
+
+    let res: FetchResponse
+    try {
+      const headers = [...request.headers]
+      res = await ops.send({ method, url, headers, body, redirect, cancelRid })
+    } catch (err) {
+      throw signal.aborted ? signal.reason : err
+    } finally {
+      signal.removeEventListener("abort", onAbort)
+      if (!signal.aborted) {
+        core.close(cancelRid)
+      }
+    }
+
+    const stream = res.bodyRid === null ? null : responseBody(res.bodyRid, signal)
+    const response = new Response(null, {
+      status: res.status,
+      statusText: res.statusText,
+      headers: res.headers,
+    })
+    bodies.set(response, stream)
+    immutable.add(response.headers)
+    responses.set(response, { url: res.url, redirected: res.redirected, type: "basic" })
+    return response
+  }
+
+  internal.fetch = {
+    extractBody: (body: any) => extractBody(internal, body),
+    // the bytes of an unread body created from a string or a buffer
+    bodySource(body: Body): Uint8Array | null {
+      const stream = body.body
+      if (stream === null || body.bodyUsed || stream.locked) {
+        return null
+      }
+      return sources.get(stream) ?? null
+    },
+    switchingProtocols: Response.switchingProtocols,
+  }
+  // @ts-ignore the static helper stays internal
+  delete Response.switchingProtocols
+  Object.assign(globalThis, { Headers, Request, Response, fetch })
+}
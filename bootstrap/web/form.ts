@@ -0,0 +1,110 @@
+// FormData
+
+// anything with `arrayBuffer()`, e.g. a Blob or a File from a library
+interface BlobLike {
+  readonly type?: string
+  readonly name?: string
+  arrayBuffer(): Promise<ArrayBuffer>
+}
+
+type FormValue = string | BlobLike
+
+function isBlobLike(value: any): value is BlobLike {
+  return typeof value === "object" && value !== null && typeof value.arrayBuffer === "function"
+}
+
+class FormData {
+  #list: [string, FormValue, string | undefined][] = []
+
+  constructor(form?: unknown) {
+    if (form !== undefined) {
+      throw new TypeError("FormData does not support a form element")
+    }
+  }
+
+  #entry(name: string, value: FormValue, filename?: string): [string, FormValue, string | undefined] {
+    if (isBlobLike(value)) {
+      return [String(name), value, filename ?? value.name ?? "blob"]
+    }
+    return [String(name), String(value), undefined]
+  }
+
+  append(name: string, value: FormValue, filename?: string) {
+    this.#list.push(this.#entry(name, value, filename))
+  }
+  delete(name: string) {
+    this.#list = this.#list.filter(([n]) => n !== name)
+  }
+  get(name: string): FormValue | null {
+    return this.#list.find(([n]) => n === name)?.[1] ?? null
+  }
+  getAll(name: string): FormValue[] {
+    return this.#list.filter(([n]) => n === name).map(([, v]) => v)
+  }
+  has(name: string): boolean {
+    return this.#list.some(([n]) => n === name)
+  }
+  set(name: string, value: FormValue, filename?: string) {
+    const index = this.#list.findIndex(([n]) => n === name)
+    if (index < 0) {
+      this.append(name, value, filename)
+      return
+    }
+    this.#list[index] = this.#entry(name, value, filename)
+    this.#list = this.#list.filter(([n], i) => i <= index || n !== name)
+  }
+  forEach(callback: (value: FormValue, name: string, form: FormData) => void, thisArg?: any) {
+    for (const [name, value] of this.#list) {
+      callback.call(thisArg, value, name, this)
+    }
+  }
+  keys() {
+    return this.#list.map(([name]) => name)[Symbol.iterator]()
+  }
+  values() {
+    return this.#list.map(([, value]) => value)[Symbol.iterator]()
+  }
+  entries() {
+    return this.#list.map(([name, value]) => [name, value])[Symbol.iterator]()
+  }
+  [Symbol.iterator]() {
+    return this.entries()
+  }
+
+  // multipart/form-data body, the boundary is picked up front for the content type
+  static async multipart(form: FormData, boundary: string, toBytes: (text: string) => Uint8Array) {
+    const escape = (value: string) =>
+      value.replace(/\n/g, "%0A").replace(/\r/g, "%0D").replace(/"/g, "%22")
+    const parts: Uint8Array[] = []
+    for (const [name, value, filename] of form.#list) {
+      let head = `--${boundary}\r\nContent-Disposition: form-data; name="${escape(name)}"`
+      if (typeof value === "string") {
+        parts.push(toBytes(`${head}\r\n\r\n${value.replace(/\r?\n|\r/g, "\r\n")}\r\n`))
+        continue
+      }
+      head += `; filename="${escape(filename!)}"\r\n`
+      head += `Content-Type: ${value.type || "application/octet-stream"}\r\n\r\n`
+      parts.push(toBytes(head), new Uint8Array(await value.arrayBuffer()), toBytes("\r\n"))
+    }
+    parts.push(toBytes(`--${boundary}--\r\n`))
+
+    const body = new Uint8Array(parts.reduce((length, part) => length + part.length, 0))
+    let offset = 0
+    for (const part of parts) {
+      body.set(part, offset)
+      offset += part.length
+    }
+    return body
+  }
+}
+
+export default function (this: { internal: any }) {
+  this.internal.form = {
+    isBlobLike,
+    boundary: () => "----EdonFormBoundary" + Math.random().toString(16).slice(2),
+    multipart: FormData.multipart,
+  }
+  // @ts-ignore the static helper stays internal
+  delete FormData.multipart
+  Object.assign(globalThis, { FormData })
+}
@@ -0,0 +1,417 @@
+// ReadableStream with a default controller and reader, enough for fetch bodies
+
+interface UnderlyingSource {
+  start?(controller: ReadableStreamDefaultController): any
+  pull?(controller: ReadableStreamDefaultController): any
+  cancel?(reason?: any): any
+  type?: string
+}
+
+interface ReadRequest {
+  resolve(result: ReadableStreamReadResult): void
+  reject(reason: any): void
+}
+
+interface ReadableStreamReadResult {
+  value: any
+  done: boolean
+}
+
+interface Deferred {
+  promise: Promise<void>
+  resolve(): void
+  reject(reason: any): void
+}
+
+interface StreamState {
+  state: "readable" | "closed" | "errored"
+  queue: any[]
+  error: any
+  source: UnderlyingSource
+  controller: ReadableStreamDefaultController
+  highWaterMark: number
+  reader: ReadableStreamDefaultReader | null
+  requests: ReadRequest[]
+  closed: Deferred | null
+  started: boolean
+  pulling: boolean
+  pullAgain: boolean
+  closeRequested: boolean
+  disturbed: boolean
+}
+
+const states = new WeakMap<object, StreamState>()
+const illegal = Symbol("illegal")
+
+function deferred(): Deferred {
+  let resolve!: () => void
+  let reject!: (reason: any) => void
+  const promise = new Promise<void>((res, rej) => {
+    resolve = res
+    reject = rej
+  })
+  // a rejected `closed` is not an unhandled rejection
+  promise.catch(() => {})
+  return { promise, resolve, reject }
+}
+
+function stateOf(stream: ReadableStream): StreamState {
+  const state = states.get(stream)
+  if (!state) {
+    throw new TypeError("Illegal invocation")
+  }
+  return state
+}
+
+function desiredSize(s: StreamState): number | null {
+  switch (s.state) {
+    case "errored":
+      return null
+    case "closed":
+      return 0
+    default:
+      return s.highWaterMark - s.queue.length
+  }
+}
+
+function finishClose(s: StreamState) {
+  s.state = "closed"
+  for (const request of s.requests.splice(0)) {
+    request.resolve({ value: undefined, done: true })
+  }
+  s.closed?.resolve()
+}
+
+function enqueue(s: StreamState, chunk: any) {
+  if (s.state !== "readable" || s.closeRequested) {
+    throw new TypeError("The stream is not in a state that permits enqueue")
+  }
+  const request = s.requests.shift()
+  if (request) {
+    request.resolve({ value: chunk, done: false })
+  } else {
+    s.queue.push(chunk)
+  }
+  pullIfNeeded(s)
+}
+
+function close(s: StreamState) {
+  if (s.state !== "readable" || s.closeRequested) {
+    throw new TypeError("The stream is not in a state that permits close")
+  }
+  s.closeRequested = true
+  if (s.queue.length === 0) {
+    finishClose(s)
+  }
+}
+
+function error(s: StreamState, reason: any) {
+  if (s.state !== "readable") {
+    return
+  }
+  s.state = "errored"
+  s.error = reason
+  s.queue = []
+  for (const request of s.requests.splice(0)) {
+    request.reject(reason)
+  }
+  s.closed?.reject(reason)
+}
+
+function pullIfNeeded(s: StreamState) {
+  if (!s.started || s.state !== "readable" || s.closeRequested) {
+    return
+  }
+  if (s.requests.length === 0 && desiredSize(s)! <= 0) {
+    return
+  }
+  if (s.pulling) {
+    s.pullAgain = true
+    return
+  }
+  s.pulling = true
+  Promise.resolve()
+    .then(() => s.source.pull?.call(s.source, s.controller))
+    .then(
+      () => {
+        s.pulling = false
+        if (s.pullAgain) {
+          s.pullAgain = false
+          pullIfNeeded(s)
+        }
+      },
+      (reason) => error(s, reason),
+    )
+}
+
+function read(s: StreamState): Promise<ReadableStreamReadResult> {
+  s.disturbed = true
+  if (s.queue.length > 0) {
+    const value = s.queue.shift()
+    if (s.closeRequested && s.queue.length === 0) {
+      finishClose(s)
+    } else {
+      pullIfNeeded(s)
+    }
+    return Promise.resolve({ value, done: false })
+  }
+  switch (s.state) {
+    case "closed":
+      return Promise.resolve({ value: undefined, done: true })
+    case "errored":
+      return Promise.reject(s.error)
+  }
+  return new Promise((resolve, reject) => {
+    s.requests.push({ resolve, reject })
+    pullIfNeeded(s)
+  })
+}
+
+function cancel(s: StreamState, reason?: any): Promise<void> {
+  s.disturbed = true
+  switch (s.state) {
+    case "closed":
+      return Promise.resolve()
+    case "errored":
+      return Promise.reject(s.error)
+  }
+  s.queue = []
+  finishClose(s)
+  return Promise.resolve()
+    .then(() => s.source.cancel?.call(s.source, reason))
+    .then(() => undefined)
+}
+
+class ReadableStreamDefaultController {
+  #stream: StreamState
+
+  constructor(key: symbol, stream: StreamState) {
+    if (key !== illegal) {
+      throw new TypeError("Illegal constructor")
+    }
+    this.#stream = stream
+  }
+
+  get desiredSize() {
+    return desiredSize(this.#stream)
+  }
+  enqueue(chunk: any) {
+    enqueue(this.#stream, chunk)
+  }
+  close() {
+    close(this.#stream)
+  }
+  error(reason?: any) {
+    error(this.#stream, reason)
+  }
+}
+
+class ReadableStreamDefaultReader {
+  #stream: StreamState | null
+
+  constructor(stream: ReadableStream) {
+    const s = stateOf(stream)
+    if (s.reader) {
+      throw new TypeError("ReadableStream is locked")
+    }
+    s.reader = this
+    s.closed = deferred()
+    if (s.state === "closed") {
+      s.closed.resolve()
+    } else if (s.state === "errored") {
+      s.closed.reject(s.error)
+    }
+    this.#stream = s
+  }
+
+  get closed(): Promise<void> {
+    return this.#stream?.closed?.promise ?? Promise.reject(new TypeError("Reader was released"))
+  }
+
+  read(): Promise<ReadableStreamReadResult> {
+    if (!this.#stream) {
+      return Promise.reject(new TypeError("Reader was released"))
+    }
+    return read(this.#stream)
+  }
+
+  cancel(reason?: any): Promise<void> {
+    if (!this.#stream) {
+      return Promise.reject(new TypeError("Reader was released"))
+    }
+    return cancel(this.#stream, reason)
+  }
+
+  releaseLock() {
+    const s = this.#stream
+    if (!s) {
+      return
+    }
+    const released = new TypeError("Reader was released")
+    for (const request of s.requests.splice(0)) {
+      request.reject(released)
+    }
+    if (s.state === "readable") {
+      s.closed?.reject(released)
+    }
+    s.reader = null
+    s.closed = null
+    this.#stream = null
+  }
+}
+
+class ReadableStream {
+  constructor(source: UnderlyingSource = {}, strategy: { highWaterMark?: number } = {}) {
+    const s: StreamState = {
+      state: "readable",
+      queue: [],
+      error: undefined,
+      source,
+      controller: null!,
+      highWaterMark: strategy.highWaterMark ?? 1,
+      reader: null,
+      requests: [],
+      closed: null,
+      started: false,
+      pulling: false,
+      pullAgain: false,
+      closeRequested: false,
+      disturbed: false,
+    }
+    s.controller = new ReadableStreamDefaultController(illegal, s)
+    states.set(this, s)
+    Promise.resolve(source.start?.call(source, s.controller)).then(
+      () => {
+        s.started = true
+        pullIfNeeded(s)
+      },
+      (reason) => error(s, reason),
+    )
+  }
+
+  get locked(): boolean {
+    return stateOf(this).reader !== null
+  }
+
+  getReader(options: { mode?: string } = {}): ReadableStreamDefaultReader {
+    if (options.mode !== undefined) {
+      throw new RangeError(`unsupported reader mode "${options.mode}"`)
+    }
+    return new ReadableStreamDefaultReader(this)
+  }
+
+  cancel(reason?: any): Promise<void> {
+    if (this.locked) {
+      return Promise.reject(new TypeError("ReadableStream is locked"))
+    }
+    return cancel(stateOf(this), reason)
+  }
+
+  tee(): [ReadableStream, ReadableStream] {
+    const reader = this.getReader()
+    const controllers: ReadableStreamDefaultController[] = []
+    const canceled = [false, false]
+    const reasons: any[] = []
+    let reading = false
+    let cancelSource: (value: Promise<void>) => void
+    const sourceCanceled = new Promise<void>((resolve) => (cancelSource = resolve))
+
+    const pull = () => {
+      if (reading) {
+        return
+      }
+      reading = true
+      return reader.read().then(
+        ({ value, done }) => {
+          reading = false
+          controllers.forEach((controller, i) => {
+            if (canceled[i]) {
+              return
+            }
+            if (done) {
+              controller.close()
+            } else {
+              controller.enqueue(value)
+            }
+          })
+        },
+        (reason) => controllers.forEach((controller) => controller.error(reason)),
+      )
+    }
+    const branch = (i: number) =>
+      new ReadableStream({
+        start: (controller) => {
+          controllers[i] = controller
+        },
+        pull,
+        cancel: (reason) => {
+          canceled[i] = true
+          reasons[i] = reason
+          if (canceled[1 - i]) {
+            cancelSource(reader.cancel(reasons))
+          }
+          return sourceCanceled
+        },
+      })
+    return [branch(0), branch(1)]
+  }
+
+  [Symbol.asyncIterator](options: { preventCancel?: boolean } = {}) {
+    const reader = this.getReader()
+    return {
+      async next(): Promise<ReadableStreamReadResult> {
+        const result = await reader.read()
+        if (result.done) {
+          reader.releaseLock()
+        }
+        return result
+      },
+      async return(value?: any): Promise<ReadableStreamReadResult> {
+        if (!options.preventCancel) {
+          await reader.cancel(value)
+        }
+        reader.releaseLock()
+        return { value, done: true }
+      },
+      [Symbol.asyncIterator]() {
+        return this
+      },
+    }
+  }
+
+  values(options: { preventCancel?: boolean } = {}) {
+    return this[Symbol.asyncIterator](options)
+  }
+}
+
+// read a byte stream to the end
+async function readAll(stream: ReadableStream): Promise<Uint8Array> {
+  const chunks: Uint8Array[] = []
+  let length = 0
+  for await (const chunk of stream) {
+    if (!(chunk instanceof Uint8Array)) {
+      throw new TypeError("body stream chunks must be Uint8Array")
+    }
+    chunks.push(chunk)
+    length += chunk.length
+  }
+  const bytes = new Uint8Array(length)
+  let offset = 0
+  for (const chunk of chunks) {
+    bytes.set(chunk, offset)
+    offset += chunk.length
+  }
+  return bytes
+}
+
+export default function (this: { internal: any }) {
+  this.internal.streams = {
+    readAll,
+    isDisturbed: (stream: ReadableStream) => stateOf(stream).disturbed,
+  }
+  Object.assign(globalThis, {
+    ReadableStream,
+    ReadableStreamDefaultController,
+    ReadableStreamDefaultReader,
+  })
+}
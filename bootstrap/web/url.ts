@@ -0,0 +1,260 @@
+// URL and URLSearchParams, urls are parsed by the url crate
+
+type Pairs = [string, string][]
+
+interface UrlParts {
+  href: string
+  origin: string
+  protocol: string
+  username: string
+  password: string
+  host: string
+  hostname: string
+  port: string
+  pathname: string
+  search: string
+  hash: string
+}
+
+type UrlPart = Exclude<keyof UrlParts, "origin">
+
+interface UrlOps {
+  parse(href: string, base: string | null): UrlParts
+  set(href: string, part: UrlPart, value: string): UrlParts
+}
+
+
+function encode(value: string): string {
+  return encodeURIComponent(value)
+    .replace(/[!'()~]/g, (c) => "%" + c.charCodeAt(0).toString(16).toUpperCase())
+    .replace(/%20/g, "+")
+}
+
+function decode(value: string): string {
+  value = value.replace(/\+/g, " ")
+  try {
+    return decodeURIComponent(value)
+  } catch {
+    return value
+  }
+}
+
+function parse(query: string): Pairs {
+  const list: Pairs = []
+  for (const part of (query.startsWith("?") ? query.slice(1) : query).split("&")) {
+    if (!part) {
+      continue
+    }
+    const index = part.indexOf("=")
+    const [name, value] = index < 0 ? [part, ""] : [part.slice(0, index), part.slice(index + 1)]
+    list.push([decode(name), decode(value)])
+  }
+  return list
+}
+
+// the `URL` a URLSearchParams belongs to is updated through these
+const updates = new WeakMap<URLSearchParams, (query: string) => void>()
+let reset: (params: URLSearchParams, query: string) => void
+
+class URLSearchParams {
+  #list: Pairs = []
+
+  static {
+    reset = (params, query) => {
+      params.#list = parse(query)
+    }
+  }
+
+  constructor(init: string | Pairs | Record<string, string> | URLSearchParams = "") {
+    if (init instanceof URLSearchParams) {
+      this.#list = [...init.#list]
+    } else if (typeof init === "object" && init !== null && Symbol.iterator in init) {
+      for (const pair of init as Iterable<string[]>) {
+        if (pair.length !== 2) {
+          throw new TypeError("Each query pair must be an iterable [name, value] tuple")
+        }
+        this.#list.push([String(pair[0]), String(pair[1])])
+      }
+    } else if (typeof init === "object" && init !== null) {
+      for (const [name, value] of Object.entries(init)) {
+        this.#list.push([name, String(value)])
+      }
+    } else {
+      this.#list = parse(String(init))
+    }
+  }
+
+  #update() {
+    updates.get(this)?.(this.toString())
+  }
+
+  get size() {
+    return this.#list.length
+  }
+  append(name: string, value: string) {
+    this.#list.push([String(name), String(value)])
+    this.#update()
+  }
+  delete(name: string, value?: string) {
+    this.#list = this.#list.filter(([n, v]) => n !== name || (value !== undefined && v !== value))
+    this.#update()
+  }
+  get(name: string): string | null {
+    return this.#list.find(([n]) => n === name)?.[1] ?? null
+  }
+  getAll(name: string): string[] {
+    return this.#list.filter(([n]) => n === name).map(([, v]) => v)
+  }
+  has(name: string, value?: string): boolean {
+    return this.#list.some(([n, v]) => n === name && (value === undefined || v === value))
+  }
+  set(name: string, value: string) {
+    const index = this.#list.findIndex(([n]) => n === name)
+    if (index < 0) {
+      this.append(name, value)
+      return
+    }
+    this.#list[index] = [String(name), String(value)]
+    this.#list = this.#list.filter(([n], i) => i <= index || n !== name)
+    this.#update()
+  }
+  sort() {
+    // Array.prototype.sort is stable
+    this.#list.sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0))
+    this.#update()
+  }
+  forEach(callback: (value: string, name: string, params: URLSearchParams) => void, thisArg?: any) {
+    for (const [name, value] of this.#list) {
+      callback.call(thisArg, value, name, this)
+    }
+  }
+  keys() {
+    return this.#list.map(([name]) => name)[Symbol.iterator]()
+  }
+  values() {
+    return this.#list.map(([, value]) => value)[Symbol.iterator]()
+  }
+  entries() {
+    return this.#list.map(([name, value]) => [name, value])[Symbol.iterator]()
+  }
+  [Symbol.iterator]() {
+    return this.entries()
+  }
+  toString(): string {
+    return this.#list.map(([name, value]) => `${encode(name)}=${encode(value)}`).join("&")
+  }
+}
+
+export default function (this: { url: UrlOps }) {
+  const ops = this.url
+
+  class URL {
+    #parts: UrlParts
+    #searchParams: URLSearchParams | null = null
+
+    constructor(url: string | URL, base?: string | URL) {
+      this.#parts = ops.parse(String(url), base === undefined ? null : String(base))
+    }
+
+    static canParse(url: string | URL, base?: string | URL): boolean {
+      return URL.parse(url, base) !== null
+    }
+    static parse(url: string | URL, base?: string | URL): URL | null {
+      try {
+        return new URL(url, base)
+      } catch {
+        return null
+      }
+    }
+
+    #set(part: UrlPart, value: unknown) {
+      this.#parts = ops.set(this.#parts.href, part, String(value))
+      if (this.#searchParams && (part === "href" || part === "search")) {
+        reset(this.#searchParams, this.#parts.search)
+      }
+    }
+
+    get href() {
+      return this.#parts.href
+    }
+    set href(value: string) {
+      this.#set("href", value)
+    }
+    get origin() {
+      return this.#parts.origin
+    }
+    get protocol() {
+      return this.#parts.protocol
+    }
+    set protocol(value: string) {
+      this.#set("protocol", value)
+    }
+    get username() {
+      return this.#parts.username
+    }
+    set username(value: string) {
+      this.#set("username", value)
+    }
+    get password() {
+      return this.#parts.password
+    }
+    set password(value: string) {
+      this.#set("password", value)
+    }
+    get host() {
+      return this.#parts.host
+    }
+    set host(value: string) {
+      this.#set("host", value)
+    }
+    get hostname() {
+      return this.#parts.hostname
+    }
+    set hostname(value: string) {
+      this.#set("hostname", value)
+    }
+    get port() {
+      return this.#parts.port
+    }
+    set port(value: string) {
+      this.#set("port", value)
+    }
+    get pathname() {
+      return this.#parts.pathname
+    }
+    set pathname(value: string) {
+      this.#set("pathname", value)
+    }
+    get search() {
+      return this.#parts.search
+    }
+    set search(value: string) {
+      this.#set("search", value)
+    }
+    get hash() {
+      return this.#parts.hash
+    }
+    set hash(value: string) {
+      this.#set("hash", value)
+    }
+    // the same object every time, changes to it update the url
+    get searchParams(): URLSearchParams {
+      if (!this.#searchParams) {
+        this.#searchParams = new URLSearchParams(this.#parts.search)
+        updates.set(this.#searchParams, (query) => {
+          this.#parts = ops.set(this.#parts.href, "search", query)
+        })
+      }
+      return this.#searchParams
+    }
+
+    toString() {
+      return this.href
+    }
+    toJSON() {
+      return this.href
+    }
+  }
+
+  Object.assign(globalThis, { URL, URLSearchParams })
+}
@@ -0,0 +1,299 @@
+// WebSocket, server side sockets come from `Edon.upgradeWebSocket` in bootstrap/serve.ts
+
+interface Connected {
+  rid: number
+  protocol: string
+  extensions: string
+}
+
+type WebSocketEvent =
+  | { kind: "text"; data: string }
+  | { kind: "binary"; data: Uint8Array }
+  | { kind: "close"; code: number; reason: string }
+  | { kind: "error"; message: string }
+
+interface WebSocketOps {
+  parseUrl(url: string): string | null
+  acceptKey(key: string): string
+  connect(args: { url: string; protocols: string[]; cancelRid: number }): Promise<Connected>
+  send(rid: number, data: string | Uint8Array): Promise<void>
+  close(rid: number, code: number | null, reason: string): Promise<void>
+  next(rid: number): Promise<WebSocketEvent>
+}
+
+interface CoreOps {
+  close(rid: number): void
+  cancelHandle(): number
+}
+
+interface MessageEventInit<T> {
+  bubbles?: boolean
+  cancelable?: boolean
+  data?: T
+  origin?: string
+  lastEventId?: string
+}
+
+class MessageEvent<T = any> extends Event {
+  readonly data: T
+  readonly origin: string
+  readonly lastEventId: string
+
+  constructor(type: string, init: MessageEventInit<T> = {}) {
+    super(type, init)
+    this.data = init.data as T
+    this.origin = String(init.origin ?? "")
+    this.lastEventId = String(init.lastEventId ?? "")
+  }
+}
+
+interface CloseEventInit {
+  bubbles?: boolean
+  cancelable?: boolean
+  wasClean?: boolean
+  code?: number
+  reason?: string
+}
+
+class CloseEvent extends Event {
+  readonly wasClean: boolean
+  readonly code: number
+  readonly reason: string
+
+  constructor(type: string, init: CloseEventInit = {}) {
+    super(type, init)
+    this.wasClean = !!init.wasClean
+    this.code = init.code ?? 0
+    this.reason = String(init.reason ?? "")
+  }
+}
+
+const READY_STATES = { CONNECTING: 0, OPEN: 1, CLOSING: 2, CLOSED: 3 }
+const { CONNECTING, OPEN, CLOSING, CLOSED } = READY_STATES
+
+// a subprotocol is an http token
+const PROTOCOL = /^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/
+
+const serverSide = Symbol("serverSide")
+const opened = Symbol("opened")
+const failed = Symbol("failed")
+
+export default function (this: { websocket: WebSocketOps; core: CoreOps; internal: any }) {
+  const ops = this.websocket
+  const core = this.core
+  const internal = this.internal
+  const encoder = new TextEncoder()
+
+  class WebSocket extends EventTarget {
+    #url: string
+    #protocol = ""
+    #extensions = ""
+    #readyState = CONNECTING
+    #binaryType: "blob" | "arraybuffer" = "arraybuffer"
+    #bufferedAmount = 0
+    #rid: number | null = null
+    #cancelRid: number | null = null
+    // sends and the closing handshake go out in order
+    #sending = Promise.resolve()
+    onopen: ((event: Event) => void) | null = null
+    onmessage: ((event: MessageEvent) => void) | null = null
+    onerror: ((event: Event) => void) | null = null
+    onclose: ((event: CloseEvent) => void) | null = null
+
+    constructor(url: string | { toString(): string }, protocols: string | string[] = [], key?: symbol) {
+      super()
+      const parsed = ops.parseUrl(String(url))
+      if (parsed === null) {
+        throw new DOMException(`Invalid WebSocket url: ${url}`, "SyntaxError")
+      }
+      this.#url = parsed
+      if (key === serverSide) {
+        return
+      }
+
+      const list = typeof protocols === "string" ? [protocols] : [...protocols].map(String)
+      if (new Set(list).size !== list.length || !list.every((protocol) => PROTOCOL.test(protocol))) {
+        throw new DOMException("Invalid or duplicate WebSocket protocol", "SyntaxError")
+      }
+      this.#cancelRid = core.cancelHandle()
+      try {
+        ops
+          .connect({ url: parsed, protocols: list, cancelRid: this.#cancelRid })
+          .then((connected) => this[opened](connected.rid, connected.protocol, connected.extensions))
+          .catch(() => this[failed]())
+      } catch (err) {
+        this.#release()
+        throw err
+      }
+    }
+
+    get url() {
+      return this.#url
+    }
+    get readyState() {
+      return this.#readyState
+    }
+    get protocol() {
+      return this.#protocol
+    }
+    get extensions() {
+      return this.#extensions
+    }
+    get bufferedAmount() {
+      return this.#bufferedAmount
+    }
+    get binaryType() {
+      return this.#binaryType
+    }
+    set binaryType(value) {
+      if (value === "blob" || value === "arraybuffer") {
+        this.#binaryType = value
+      }
+    }
+
+    send(data: string | ArrayBuffer | ArrayBufferView | { size?: number; arrayBuffer(): Promise<ArrayBuffer> }) {
+      if (this.#readyState === CONNECTING) {
+        throw new DOMException("The WebSocket is still connecting", "InvalidStateError")
+      }
+      let payload: string | Uint8Array | Promise<Uint8Array>
+      let size: number
+      if (typeof data === "object" && (ArrayBuffer.isView(data) || data instanceof ArrayBuffer)) {
+        // copied, the caller may reuse the buffer before it is sent
+        payload = internal.toBytes(data).slice()
+        size = payload.length
+      } else if (internal.form.isBlobLike(data)) {
+        payload = data.arrayBuffer().then((buffer: ArrayBuffer) => new Uint8Array(buffer))
+        size = data.size ?? 0
+      } else {
+        payload = String(data)
+        size = encoder.encode(payload).length
+      }
+      this.#bufferedAmount += size
+      // messages after close are dropped, they only count towards `bufferedAmount`
+      if (this.#readyState !== OPEN) {
+        return
+      }
+
+      const rid = this.#rid!
+      this.#sending = this.#sending
+        .then(async () => ops.send(rid, await payload))
+        // a broken connection surfaces through the close event
+        .catch(() => {})
+        .finally(() => {
+          this.#bufferedAmount -= size
+        })
+    }
+
+    close(code?: number, reason?: string) {
+      if (code !== undefined && code !== 1000 && (!Number.isInteger(code) || code < 3000 || code > 4999)) {
+        throw new DOMException(`Invalid close code ${code}`, "InvalidAccessError")
+      }
+      if (reason !== undefined && encoder.encode(String(reason)).length > 123) {
+        throw new DOMException("The close reason is longer than 123 bytes", "SyntaxError")
+      }
+      if (this.#readyState === CLOSING || this.#readyState === CLOSED) {
+        return
+      }
+      if (this.#readyState === CONNECTING) {
+        // aborts the handshake, which fails the connection
+        this.#readyState = CLOSING
+        this.#release()
+        return
+      }
+
+      this.#readyState = CLOSING
+      const rid = this.#rid!
+      this.#sending = this.#sending
+        .then(() => ops.close(rid, code ?? null, String(reason ?? "")))
+        .catch(() => {})
+    }
+
+    #dispatch(event: Event) {
+      ;(this as any)[`on${event.type}`]?.call(this, event)
+      this.dispatchEvent(event)
+    }
+
+    #release() {
+      if (this.#cancelRid !== null) {
+        core.close(this.#cancelRid)
+        this.#cancelRid = null
+      }
+    }
+
+    #finish(wasClean: boolean, code: number, reason: string) {
+      this.#readyState = CLOSED
+      if (this.#rid !== null) {
+        core.close(this.#rid)
+        this.#rid = null
+      }
+      if (!wasClean) {
+        this.#dispatch(new Event("error"))
+      }
+      this.#dispatch(new CloseEvent("close", { wasClean, code, reason }))
+    }
+
+    async #receive(rid: number) {
+      const origin = this.#url.match(/^wss?:\/\/[^/?#]+/)![0]
+      for (;;) {
+        let event: WebSocketEvent
+        try {
+          event = await ops.next(rid)
+        } catch (err) {
+          event = { kind: "error", message: String(err) }
+        }
+        switch (event.kind) {
+          case "text":
+            this.#dispatch(new MessageEvent("message", { data: event.data, origin }))
+            break
+          case "binary": {
+            const bytes = event.data
+            const data =
+              this.#binaryType === "blob" && typeof (globalThis as any).Blob === "function"
+                ? new (globalThis as any).Blob([bytes])
+                : bytes.buffer.slice(bytes.byteOffset, bytes.byteOffset + bytes.byteLength)
+            this.#dispatch(new MessageEvent("message", { data, origin }))
+            break
+          }
+          case "close":
+            // 1006 is reserved for a connection that dropped without a close frame
+            return this.#finish(event.code !== 1006, event.code, event.reason)
+          case "error":
+            return this.#finish(false, 1006, "")
+        }
+      }
+    }
+
+    [opened](rid: number, protocol: string, extensions: string) {
+      this.#release()
+      if (this.#readyState !== CONNECTING) {
+        core.close(rid)
+        return this[failed]()
+      }
+      this.#rid = rid
+      this.#protocol = protocol
+      this.#extensions = extensions
+      this.#readyState = OPEN
+      this.#dispatch(new Event("open"))
+      this.#receive(rid)
+    }
+
+    [failed]() {
+      this.#release()
+      this.#finish(false, 1006, "")
+    }
+  }
+
+  for (const [name, value] of Object.entries(READY_STATES)) {
+    Object.defineProperty(WebSocket, name, { value, enumerable: true })
+    Object.defineProperty(WebSocket.prototype, name, { value, enumerable: true })
+  }
+
+  internal.websocket = {
+    acceptKey: (key: string) => ops.acceptKey(key),
+    // a server socket stays connecting until the 101 response went out
+    accept: (url: string) => new WebSocket(url, [], serverSide),
+    open: (socket: WebSocket, rid: number, protocol: string) => socket[opened](rid, protocol, ""),
+    fail: (socket: WebSocket) => socket[failed](),
+  }
+  Object.assign(globalThis, { MessageEvent, CloseEvent, WebSocket })
+}
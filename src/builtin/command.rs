@@ -0,0 +1,336 @@
+use std::{cell::Cell, collections::HashMap, process::ExitStatus, rc::Rc};
+#[cfg(unix)]
+use std::{io, os::unix::process::ExitStatusExt};
+
+use libc::c_int;
+use serde::{Deserialize, Serialize};
+use serde_v8::{JsBuffer, ToJsBuffer};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::{Child, ChildStdin, Command},
+    sync::Mutex,
+};
+
+use super::{resources, signal};
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{CancelHandle, OpError, OpResult, Resource, ResourceId, Runtime},
+};
+
+/// the most bytes a read of a child's output hands to JS
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Stdio {
+    Inherit,
+    Piped,
+    Null,
+}
+
+impl From<Stdio> for std::process::Stdio {
+    fn from(stdio: Stdio) -> Self {
+        match stdio {
+            Stdio::Inherit => Self::inherit(),
+            Stdio::Piped => Self::piped(),
+            Stdio::Null => Self::null(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SpawnArgs {
+    cmd: String,
+    args: Vec<String>,
+    cwd: Option<String>,
+    /// added to the environment of the runtime
+    env: HashMap<String, String>,
+    stdin: Stdio,
+    stdout: Stdio,
+    stderr: Stdio,
+}
+
+/// the child and the resource ids of its piped streams
+#[derive(Debug, Serialize)]
+struct Spawned {
+    rid: ResourceId,
+    pid: Option<u32>,
+    stdin: Option<ResourceId>,
+    stdout: Option<ResourceId>,
+    stderr: Option<ResourceId>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommandStatus {
+    success: bool,
+    code: i32,
+    /// the signal that killed the child
+    signal: Option<&'static str>,
+}
+
+impl From<ExitStatus> for CommandStatus {
+    fn from(status: ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = status.signal();
+        #[cfg(not(unix))]
+        let signal = None;
+        Self {
+            success: status.success(),
+            // like a shell, a killed child exits with 128 plus the signal number
+            code: status
+                .code()
+                .or(signal.map(|signal| 128 + signal))
+                .unwrap_or(1),
+            signal: signal.and_then(signal::signal_name),
+        }
+    }
+}
+
+/// a spawned process, reaped by `wait`
+struct ChildProcess {
+    child: Mutex<Child>,
+    pid: Option<u32>,
+    /// set once the child is reaped, its pid may belong to another process afterwards
+    exited: Cell<bool>,
+}
+
+impl Resource for ChildProcess {
+    fn name(&self) -> &'static str {
+        "child"
+    }
+}
+
+/// the piped stdout or stderr of a child, closing it ends a pending `read`
+struct ChildReader {
+    name: &'static str,
+    stream: Mutex<Box<dyn AsyncRead + Unpin>>,
+    cancel: CancelHandle,
+}
+
+impl ChildReader {
+    fn new(name: &'static str, stream: impl AsyncRead + Unpin + 'static) -> Self {
+        Self {
+            name,
+            stream: Mutex::new(Box::new(stream)),
+            cancel: CancelHandle::default(),
+        }
+    }
+}
+
+impl Resource for ChildReader {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+/// the piped stdin of a child, it reads the end of its input once this is closed
+struct ChildWriter {
+    stream: Mutex<ChildStdin>,
+    cancel: CancelHandle,
+}
+
+impl Resource for ChildWriter {
+    fn name(&self) -> &'static str {
+        "childStdin"
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+fn spawn(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: SpawnArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    if !permissions::check(scope, PermissionName::Run, &args.cmd) {
+        return;
+    }
+
+    let mut command = Command::new(&args.cmd);
+    command
+        .args(&args.args)
+        .envs(&args.env)
+        .stdin(args.stdin)
+        .stdout(args.stdout)
+        .stderr(args.stderr);
+    if let Some(cwd) = &args.cwd {
+        command.current_dir(cwd);
+    }
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => {
+            let OpError { class, message } = err.into();
+            return OpError::new(class, format!("{message}, spawn '{}'", args.cmd)).throw(scope);
+        }
+    };
+
+    let spawned = {
+        let state_rc = Runtime::state(scope);
+        let mut state = state_rc.borrow_mut();
+        let resources = &mut state.resources;
+        let stdin = child.stdin.take().map(|stdin| {
+            resources.add(ChildWriter {
+                stream: Mutex::new(stdin),
+                cancel: CancelHandle::default(),
+            })
+        });
+        let stdout = child
+            .stdout
+            .take()
+            .map(|stdout| resources.add(ChildReader::new("childStdout", stdout)));
+        let stderr = child
+            .stderr
+            .take()
+            .map(|stderr| resources.add(ChildReader::new("childStderr", stderr)));
+        let pid = child.id();
+        let rid = resources.add(ChildProcess {
+            child: Mutex::new(child),
+            pid,
+            exited: Cell::new(false),
+        });
+        Spawned {
+            rid,
+            pid,
+            stdin,
+            stdout,
+            stderr,
+        }
+    };
+    match serde_v8::to_v8(scope, spawned) {
+        Ok(spawned) => rv.set(spawned),
+        Err(err) => OpError::from(err).throw(scope),
+    }
+}
+
+/// resolves to the status of the child once it exits
+fn wait(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let child = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<ChildProcess>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let child = child.ok_or_else(|| OpError::bad_resource(rid))?;
+        let status = child.child.lock().await.wait().await?;
+        child.exited.set(true);
+        Ok(CommandStatus::from(status))
+    });
+    rv.set(promise.into());
+}
+
+/// send a signal by name, e.g. `SIGTERM`
+fn kill(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let signal = args.get(1).to_rust_string_lossy(scope);
+    let child = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<ChildProcess>(rid);
+    let killed = child
+        .ok_or_else(|| OpError::bad_resource(rid))
+        .and_then(|child| {
+            let signal = signal::signal_number(&signal)?;
+            match child.pid {
+                Some(pid) if !child.exited.get() => send_signal(pid, signal),
+                _ => Err(OpError::type_error("the child process has already exited")),
+            }
+        });
+    if let Err(err) = killed {
+        err.throw(scope);
+    }
+}
+
+#[cfg(unix)]
+fn send_signal(pid: u32, signal: c_int) -> OpResult<()> {
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == -1 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// not reached, there are no signal numbers on other platforms
+#[cfg(not(unix))]
+fn send_signal(_pid: u32, _signal: c_int) -> OpResult<()> {
+    Err(OpError::not_supported("kill"))
+}
+
+/// resolves to the next chunk of a child's output, `null` at its end
+fn read(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let reader = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<ChildReader>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let reader = reader.ok_or_else(|| OpError::bad_resource(rid))?;
+        let mut buf = vec![0; CHUNK_SIZE];
+        let read = reader
+            .cancel
+            .run(async { reader.stream.lock().await.read(&mut buf).await })
+            .await
+            .ok_or_else(|| OpError::new("Interrupted", "the pipe was closed"))??;
+        buf.truncate(read);
+        Ok((read > 0).then(|| ToJsBuffer::from(buf)))
+    });
+    rv.set(promise.into());
+}
+
+/// resolves once all of the data is written to a child's stdin
+fn write(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let buf: JsBuffer = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(buf) => buf,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let writer = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<ChildWriter>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let writer = writer.ok_or_else(|| OpError::bad_resource(rid))?;
+        writer
+            .cancel
+            .run(async {
+                let mut stream = writer.stream.lock().await;
+                stream.write_all(&buf).await?;
+                stream.flush().await
+            })
+            .await
+            .ok_or_else(|| OpError::new("Interrupted", "the pipe was closed"))??;
+        Ok(())
+    });
+    rv.set(promise.into());
+}
+
+/// `command` ops behind `Edon.Command`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "spawn", spawn);
+    Runtime::set_func(scope, obj, "wait", wait);
+    Runtime::set_func(scope, obj, "kill", kill);
+    Runtime::set_func(scope, obj, "read", read);
+    Runtime::set_func(scope, obj, "write", write);
+    obj
+}
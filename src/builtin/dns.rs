@@ -0,0 +1,169 @@
+use std::net::{IpAddr, SocketAddr};
+
+use hickory_resolver::{
+    config::{NameServerConfigGroup, ResolverConfig, ResolverOpts},
+    error::{ResolveError, ResolveErrorKind},
+    proto::rr::{RData, RecordType},
+    TokioAsyncResolver,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{OpError, OpResult, Runtime},
+};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+enum RecordKind {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+    Srv,
+    Ns,
+}
+
+impl From<RecordKind> for RecordType {
+    fn from(kind: RecordKind) -> Self {
+        match kind {
+            RecordKind::A => RecordType::A,
+            RecordKind::Aaaa => RecordType::AAAA,
+            RecordKind::Cname => RecordType::CNAME,
+            RecordKind::Mx => RecordType::MX,
+            RecordKind::Txt => RecordType::TXT,
+            RecordKind::Srv => RecordType::SRV,
+            RecordKind::Ns => RecordType::NS,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NameServer {
+    ip_addr: IpAddr,
+    port: u16,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResolveArgs {
+    name: String,
+    record_type: RecordKind,
+    name_server: Option<NameServer>,
+}
+
+/// one answer, addresses and names are strings, a TXT record is the list of its strings
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum DnsRecord {
+    Name(String),
+    Txt(Vec<String>),
+    Mx {
+        preference: u16,
+        exchange: String,
+    },
+    Srv {
+        priority: u16,
+        weight: u16,
+        port: u16,
+        target: String,
+    },
+}
+
+impl DnsRecord {
+    fn from_rdata(rdata: &RData) -> Option<Self> {
+        let record = match rdata {
+            RData::A(addr) => Self::Name(addr.to_string()),
+            RData::AAAA(addr) => Self::Name(addr.to_string()),
+            RData::CNAME(name) => Self::Name(name.to_string()),
+            RData::NS(name) => Self::Name(name.to_string()),
+            RData::MX(mx) => Self::Mx {
+                preference: mx.preference(),
+                exchange: mx.exchange().to_string(),
+            },
+            RData::TXT(txt) => Self::Txt(
+                txt.txt_data()
+                    .iter()
+                    .map(|data| String::from_utf8_lossy(data).into_owned())
+                    .collect(),
+            ),
+            RData::SRV(srv) => Self::Srv {
+                priority: srv.priority(),
+                weight: srv.weight(),
+                port: srv.port(),
+                target: srv.target().to_string(),
+            },
+            _ => return None,
+        };
+        Some(record)
+    }
+}
+
+fn resolve_error(name: &str, err: ResolveError) -> OpError {
+    match err.kind() {
+        ResolveErrorKind::NoRecordsFound { .. } => {
+            OpError::new("NotFound", format!("no records found for {name}"))
+        }
+        ResolveErrorKind::Timeout => {
+            OpError::new("TimedOut", format!("resolving {name} timed out"))
+        }
+        _ => OpError::new("Error", format!("resolving {name}: {err}")),
+    }
+}
+
+fn resolver(name_server: Option<NameServer>) -> OpResult<TokioAsyncResolver> {
+    let Some(NameServer { ip_addr, port }) = name_server else {
+        return TokioAsyncResolver::tokio_from_system_conf()
+            .map_err(|err| OpError::new("Error", format!("no system resolver: {err}")));
+    };
+    let servers = NameServerConfigGroup::from_ips_clear(&[ip_addr], port, true);
+    let config = ResolverConfig::from_parts(None, vec![], servers);
+    Ok(TokioAsyncResolver::tokio(config, ResolverOpts::default()))
+}
+
+async fn lookup(args: ResolveArgs) -> OpResult<Vec<DnsRecord>> {
+    let record_type = args.record_type.into();
+    let resolver = resolver(args.name_server)?;
+    let lookup = resolver
+        .lookup(args.name.as_str(), record_type)
+        .await
+        .map_err(|err| resolve_error(&args.name, err))?;
+    // answers may carry the CNAME chain that led to the records asked for
+    Ok(lookup
+        .record_iter()
+        .filter(|record| record.record_type() == record_type)
+        .filter_map(|record| record.data().and_then(DnsRecord::from_rdata))
+        .collect())
+}
+
+/// resolve `{ name, recordType, nameServer }`, a custom name server is gated by net access to it
+fn resolve(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: ResolveArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    if !permissions::check(scope, PermissionName::Net, &args.name) {
+        return;
+    }
+    if let Some(server) = &args.name_server {
+        let resource = SocketAddr::new(server.ip_addr, server.port).to_string();
+        if !permissions::check(scope, PermissionName::Net, &resource) {
+            return;
+        }
+    }
+    let promise = Runtime::promise(scope, lookup(args));
+    rv.set(promise.into());
+}
+
+/// `dns` ops behind `Edon.resolveDns`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "resolve", resolve);
+    obj
+}
@@ -1,78 +1,591 @@
-use std::path::Path;
+use std::{
+    fs::{self, Metadata, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
 
-use crate::runtime::EdonRuntime;
+use serde::{Deserialize, Serialize};
+use serde_v8::{StringOrBuffer, ToJsBuffer};
 
-fn read(
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{OpError, OpResult, Runtime},
+};
+
+/// an io error that names the call and the path it failed on, thrown as its `Edon.errors` class
+fn fs_error<'a>(call: &'static str, path: &'a Path) -> impl FnOnce(io::Error) -> OpError + 'a {
+    move |err| {
+        let OpError { class, message } = err.into();
+        OpError::new(class, format!("{message}, {call} '{}'", path.display()))
+    }
+}
+
+/// run a file system call on the calling thread for the `*Sync` ops, on tokio's blocking pool
+/// for the others, which then return a promise
+fn run<T, F>(scope: &mut v8::HandleScope, mut rv: v8::ReturnValue, sync: bool, call: F)
+where
+    T: Serialize + Send + 'static,
+    F: FnOnce() -> OpResult<T> + Send + 'static,
+{
+    if sync {
+        let value = call().and_then(|value| serde_v8::to_v8(scope, value).map_err(OpError::from));
+        match value {
+            Ok(value) => rv.set(value),
+            Err(err) => err.throw(scope),
+        }
+        return;
+    }
+    let promise = Runtime::promise(scope, async move {
+        tokio::task::spawn_blocking(call)
+            .await
+            .map_err(|err| OpError::new("Error", err.to_string()))?
+    });
+    rv.set(promise.into());
+}
+
+fn arg<T: for<'de> Deserialize<'de>>(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Option<T> {
+    match serde_v8::from_v8(scope, value) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            OpError::from(err).throw(scope);
+            None
+        }
+    }
+}
+
+/// a path argument the permission `name` is checked for
+fn path_arg(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+    name: PermissionName,
+) -> Option<PathBuf> {
+    let path: String = arg(scope, value)?;
+    permissions::check(scope, name, &path).then(|| PathBuf::from(path))
+}
+
+/// what `stat` and `lstat` resolve to, times are milliseconds since the epoch
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileInfo {
+    is_file: bool,
+    is_directory: bool,
+    is_symlink: bool,
+    size: u64,
+    mtime: Option<f64>,
+    atime: Option<f64>,
+    birthtime: Option<f64>,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    dev: u64,
+    ino: u64,
+    nlink: u64,
+}
+
+fn millis(time: io::Result<SystemTime>) -> Option<f64> {
+    let since = time.ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some(since.as_millis() as f64)
+}
+
+impl From<Metadata> for FileInfo {
+    fn from(metadata: Metadata) -> Self {
+        let file_type = metadata.file_type();
+        #[cfg(unix)]
+        let (mode, uid, gid, dev, ino, nlink) = (
+            metadata.mode(),
+            metadata.uid(),
+            metadata.gid(),
+            metadata.dev(),
+            metadata.ino(),
+            metadata.nlink(),
+        );
+        // other platforms have no unix metadata, a read only file has no write bits
+        #[cfg(not(unix))]
+        let (mode, uid, gid, dev, ino, nlink) = match metadata.permissions().readonly() {
+            true => (0o444, 0, 0, 0, 0, 1),
+            false => (0o666, 0, 0, 0, 0, 1),
+        };
+        Self {
+            is_file: file_type.is_file(),
+            is_directory: file_type.is_dir(),
+            is_symlink: file_type.is_symlink(),
+            size: metadata.len(),
+            mtime: millis(metadata.modified()),
+            atime: millis(metadata.accessed()),
+            birthtime: millis(metadata.created()),
+            mode,
+            uid,
+            gid,
+            dev,
+            ino,
+            nlink,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DirEntry {
+    name: String,
+    is_file: bool,
+    is_directory: bool,
+    is_symlink: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteFileOptions {
+    append: bool,
+    create: bool,
+    create_new: bool,
+    /// unix permission bits, ignored on other platforms
+    #[cfg_attr(not(unix), allow(dead_code))]
+    mode: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MkdirOptions {
+    recursive: bool,
+    /// unix permission bits, ignored on other platforms
+    #[cfg_attr(not(unix), allow(dead_code))]
+    mode: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TempOptions {
+    dir: Option<String>,
+    prefix: String,
+    suffix: String,
+}
+
+fn read_file<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Read) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || {
+        let data = fs::read(&path).map_err(fs_error("readFile", &path))?;
+        Ok(ToJsBuffer::from(data))
+    });
+}
+
+/// invalid UTF-8 is an `InvalidData` error rather than replaced
+fn read_text_file<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Read) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || {
+        fs::read_to_string(&path).map_err(fs_error("readTextFile", &path))
+    });
+}
+
+/// write a string or bytes to `path`, `options` are normalized by bootstrap/fs.ts
+fn write_file<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Write) else {
+        return;
+    };
+    let Some(data) = arg::<StringOrBuffer>(scope, args.get(1)) else {
+        return;
+    };
+    let Some(options) = arg::<WriteFileOptions>(scope, args.get(2)) else {
+        return;
+    };
+    // copied, the buffer belongs to the isolate
+    let data = data.to_vec();
+    run(scope, rv, SYNC, move || {
+        let mut open = OpenOptions::new();
+        open.write(true)
+            .append(options.append)
+            .truncate(!options.append)
+            .create(options.create)
+            .create_new(options.create_new);
+        #[cfg(unix)]
+        if let Some(mode) = options.mode {
+            open.mode(mode);
+        }
+        let write = || {
+            let mut file = open.open(&path)?;
+            file.write_all(&data)?;
+            // the umask applies on creation, existing files keep their mode otherwise
+            #[cfg(unix)]
+            if let Some(mode) = options.mode {
+                file.set_permissions(fs::Permissions::from_mode(mode))?;
+            }
+            Ok(())
+        };
+        write().map_err(fs_error("writeFile", &path))
+    });
+}
+
+fn stat<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Read) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || {
+        let metadata = fs::metadata(&path).map_err(fs_error("stat", &path))?;
+        Ok(FileInfo::from(metadata))
+    });
+}
+
+/// `stat` of a symlink itself rather than its target
+fn lstat<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Read) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || {
+        let metadata = fs::symlink_metadata(&path).map_err(fs_error("lstat", &path))?;
+        Ok(FileInfo::from(metadata))
+    });
+}
+
+fn read_dir<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Read) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || {
+        let read = || {
+            let mut entries = vec![];
+            for entry in fs::read_dir(&path)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                entries.push(DirEntry {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    is_file: file_type.is_file(),
+                    is_directory: file_type.is_dir(),
+                    is_symlink: file_type.is_symlink(),
+                });
+            }
+            Ok(entries)
+        };
+        read().map_err(fs_error("readDir", &path))
+    });
+}
+
+fn mkdir<const SYNC: bool>(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
-    mut _rv: v8::ReturnValue,
+    rv: v8::ReturnValue,
 ) {
-    if let Ok(v) = v8::Local::<v8::String>::try_from(args.get(0)) {
-        let p = v.to_rust_string_lossy(scope);
-        let path = Path::new(&p);
-        let content = fs::read(path).unwrap();
-        let content = String::from_utf8(content).unwrap();
-        if let Ok(f) = v8::Local::<v8::Function>::try_from(args.get(1)) {
-            let undefined = v8::undefined(scope).into();
-            let content = v8::String::new(scope, &content).unwrap();
-            f.call(scope, undefined, &[content.into()]).unwrap();
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Write) else {
+        return;
+    };
+    let Some(options) = arg::<MkdirOptions>(scope, args.get(1)) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(options.recursive);
+        #[cfg(unix)]
+        if let Some(mode) = options.mode {
+            builder.mode(mode);
         }
-        // _rv.set(v8::String::new(scope, &content).unwrap().into());
+        builder.create(&path).map_err(fs_error("mkdir", &path))
+    });
+}
+
+/// a file, symlink or empty directory, or a whole tree when `recursive`
+fn remove<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Write) else {
+        return;
+    };
+    let recursive = args.get(1).boolean_value(scope);
+    run(scope, rv, SYNC, move || {
+        let remove = || {
+            let metadata = fs::symlink_metadata(&path)?;
+            match (metadata.is_dir(), recursive) {
+                (true, true) => fs::remove_dir_all(&path),
+                (true, false) => fs::remove_dir(&path),
+                (false, _) => fs::remove_file(&path),
+            }
+        };
+        remove().map_err(fs_error("remove", &path))
+    });
+}
+
+/// moving a file makes it readable wherever it lands, so both paths need read and write access
+fn rename<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(from) = path_arg(scope, args.get(0), PermissionName::Read) else {
+        return;
+    };
+    let Some(to) = path_arg(scope, args.get(1), PermissionName::Read) else {
+        return;
+    };
+    if !permissions::check(scope, PermissionName::Write, &from.to_string_lossy())
+        || !permissions::check(scope, PermissionName::Write, &to.to_string_lossy())
+    {
+        return;
     }
+    run(scope, rv, SYNC, move || {
+        fs::rename(&from, &to).map_err(fs_error("rename", &from))
+    });
+}
+
+fn copy_file<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(from) = path_arg(scope, args.get(0), PermissionName::Read) else {
+        return;
+    };
+    let Some(to) = path_arg(scope, args.get(1), PermissionName::Write) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || {
+        fs::copy(&from, &to)
+            .map(|_| ())
+            .map_err(fs_error("copyFile", &from))
+    });
 }
-fn read_sync(
+
+/// create `path` pointing at `target`, which does not need to exist
+///
+/// a link can point anywhere, so this needs full read and write access
+fn symlink<const SYNC: bool>(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
-    mut _rv: v8::ReturnValue,
+    rv: v8::ReturnValue,
 ) {
-    if let Ok(v) = v8::Local::<v8::String>::try_from(args.get(0)) {
-        let p = v.to_rust_string_lossy(scope);
-        let path = Path::new(&p);
-        let content = fs::read(path).unwrap();
-        let content = String::from_utf8(content).unwrap();
-        _rv.set(v8::String::new(scope, &content).unwrap().into());
+    if !permissions::check_all(scope, PermissionName::Read)
+        || !permissions::check_all(scope, PermissionName::Write)
+    {
+        return;
     }
+    let Some(target) = arg::<String>(scope, args.get(0)) else {
+        return;
+    };
+    let Some(path) = path_arg(scope, args.get(1), PermissionName::Write) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || create_symlink(&target, &path));
+}
+
+#[cfg(unix)]
+fn create_symlink(target: &str, path: &Path) -> OpResult<()> {
+    std::os::unix::fs::symlink(target, path).map_err(fs_error("symlink", path))
+}
+
+#[cfg(not(unix))]
+fn create_symlink(_target: &str, _path: &Path) -> OpResult<()> {
+    Err(OpError::not_supported("symlink"))
+}
+
+/// the canonical path, which is checked too so that links do not reveal locations that can
+/// not be read, against a snapshot of the permissions that never prompts
+fn real_path<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Read) else {
+        return;
+    };
+    let mut permissions = Runtime::state(scope).borrow().permissions.clone();
+    permissions.prompt = false;
+    run(scope, rv, SYNC, move || {
+        let real = fs::canonicalize(&path).map_err(fs_error("realPath", &path))?;
+        let real = real.to_string_lossy().into_owned();
+        permissions
+            .check(PermissionName::Read, &real)
+            .map_err(|message| OpError::new("PermissionDenied", message))?;
+        Ok(real)
+    });
 }
 
-fn read_promise(
+fn chmod<const SYNC: bool>(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
-    mut _rv: v8::ReturnValue,
+    rv: v8::ReturnValue,
 ) {
-    if let Ok(v) = v8::Local::<v8::String>::try_from(args.get(0)) {
-        let p = v.to_rust_string_lossy(scope);
-        let path = Path::new(&p);
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Write) else {
+        return;
+    };
+    let Some(mode) = arg::<u32>(scope, args.get(1)) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || set_mode(&path, mode));
+}
 
-        let promise_resolver = v8::PromiseResolver::new(scope).unwrap();
-        let promise = promise_resolver.get_promise(scope);
-        _rv.set(promise.into());
-        // tokio::spawn(async move {
-        let content = fs::read(path);
-        if let Ok(content) = content {
-            let content = String::from_utf8(content).unwrap();
-            let content = v8::String::new(scope, &content).unwrap();
-            promise_resolver.resolve(scope, content.into());
-        } else {
-            let error = v8::String::new(scope, "").unwrap();
-            promise_resolver.reject(scope, error.into());
-        }
-        // });
-        // let handler = tokio::spawn(tokio::fs::read(path));
-        // tokio::join!(handler);
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> OpResult<()> {
+    fs::set_permissions(path, fs::Permissions::from_mode(mode)).map_err(fs_error("chmod", path))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> OpResult<()> {
+    Err(OpError::not_supported("chmod"))
+}
+
+/// cut or zero-extend a file to `len` bytes
+fn truncate<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let Some(path) = path_arg(scope, args.get(0), PermissionName::Write) else {
+        return;
+    };
+    let Some(len) = arg::<u64>(scope, args.get(1)) else {
+        return;
+    };
+    run(scope, rv, SYNC, move || {
+        let truncate = || OpenOptions::new().write(true).open(&path)?.set_len(len);
+        truncate().map_err(fs_error("truncate", &path))
+    });
+}
 
-        // let content = fs::read(path).unwrap();
-        // let content = String::from_utf8(content).unwrap();
+/// a name unlikely to exist yet, creating it retries on `AlreadyExists`
+fn temp_name(options: &TempOptions, dir: &Path) -> PathBuf {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let unique = format!("{:x}{nanos:08x}{count:x}", process::id());
+    dir.join(format!("{}{unique}{}", options.prefix, options.suffix))
+}
 
-        // promise_resolver.resolve(scope, value);
+fn make_temp(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+    rv: v8::ReturnValue,
+    sync: bool,
+    directory: bool,
+) {
+    let Some(options) = arg::<TempOptions>(scope, value) else {
+        return;
+    };
+    let dir = match &options.dir {
+        Some(dir) => PathBuf::from(dir),
+        None => std::env::temp_dir(),
+    };
+    if !permissions::check(scope, PermissionName::Write, &dir.to_string_lossy()) {
+        return;
     }
+    let call = if directory {
+        "makeTempDir"
+    } else {
+        "makeTempFile"
+    };
+    run(scope, rv, sync, move || loop {
+        let path = temp_name(&options, &dir);
+        let created = match directory {
+            true => {
+                #[cfg_attr(not(unix), allow(unused_mut))]
+                let mut builder = fs::DirBuilder::new();
+                #[cfg(unix)]
+                builder.mode(0o700);
+                builder.create(&path)
+            }
+            false => {
+                let mut open = OpenOptions::new();
+                open.write(true).create_new(true);
+                #[cfg(unix)]
+                open.mode(0o600);
+                open.open(&path).map(|_| ())
+            }
+        };
+        match created {
+            Ok(()) => return Ok(path.to_string_lossy().into_owned()),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(fs_error(call, &dir)(err)),
+        }
+    });
+}
+
+fn make_temp_dir<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    make_temp(scope, args.get(0), rv, SYNC, true);
+}
+
+fn make_temp_file<const SYNC: bool>(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    make_temp(scope, args.get(0), rv, SYNC, false);
 }
 
+/// `fs` ops behind the file system functions of `Edon`, each with a `*Sync` variant
 pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
     let obj = v8::Object::new(scope);
-    EdonRuntime::set_func(scope, obj, "read", read);
-    EdonRuntime::set_func(scope, obj, "readSync", read_sync);
-    EdonRuntime::set_func(scope, obj, "readPromise", read_promise);
-    return obj;
+    Runtime::set_func(scope, obj, "readFile", read_file::<false>);
+    Runtime::set_func(scope, obj, "readFileSync", read_file::<true>);
+    Runtime::set_func(scope, obj, "readTextFile", read_text_file::<false>);
+    Runtime::set_func(scope, obj, "readTextFileSync", read_text_file::<true>);
+    Runtime::set_func(scope, obj, "writeFile", write_file::<false>);
+    Runtime::set_func(scope, obj, "writeFileSync", write_file::<true>);
+    Runtime::set_func(scope, obj, "stat", stat::<false>);
+    Runtime::set_func(scope, obj, "statSync", stat::<true>);
+    Runtime::set_func(scope, obj, "lstat", lstat::<false>);
+    Runtime::set_func(scope, obj, "lstatSync", lstat::<true>);
+    Runtime::set_func(scope, obj, "readDir", read_dir::<false>);
+    Runtime::set_func(scope, obj, "readDirSync", read_dir::<true>);
+    Runtime::set_func(scope, obj, "mkdir", mkdir::<false>);
+    Runtime::set_func(scope, obj, "mkdirSync", mkdir::<true>);
+    Runtime::set_func(scope, obj, "remove", remove::<false>);
+    Runtime::set_func(scope, obj, "removeSync", remove::<true>);
+    Runtime::set_func(scope, obj, "rename", rename::<false>);
+    Runtime::set_func(scope, obj, "renameSync", rename::<true>);
+    Runtime::set_func(scope, obj, "copyFile", copy_file::<false>);
+    Runtime::set_func(scope, obj, "copyFileSync", copy_file::<true>);
+    Runtime::set_func(scope, obj, "symlink", symlink::<false>);
+    Runtime::set_func(scope, obj, "symlinkSync", symlink::<true>);
+    Runtime::set_func(scope, obj, "realPath", real_path::<false>);
+    Runtime::set_func(scope, obj, "realPathSync", real_path::<true>);
+    Runtime::set_func(scope, obj, "chmod", chmod::<false>);
+    Runtime::set_func(scope, obj, "chmodSync", chmod::<true>);
+    Runtime::set_func(scope, obj, "truncate", truncate::<false>);
+    Runtime::set_func(scope, obj, "truncateSync", truncate::<true>);
+    Runtime::set_func(scope, obj, "makeTempDir", make_temp_dir::<false>);
+    Runtime::set_func(scope, obj, "makeTempDirSync", make_temp_dir::<true>);
+    Runtime::set_func(scope, obj, "makeTempFile", make_temp_file::<false>);
+    Runtime::set_func(scope, obj, "makeTempFileSync", make_temp_file::<true>);
+    obj
 }
@@ -0,0 +1,55 @@
+use serde_v8::{JsBuffer, ToJsBuffer};
+
+use crate::runtime::{OpError, Runtime};
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+fn encode(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    // lone surrogates become U+FFFD, as for a USVString
+    let input = args.get(0).to_rust_string_lossy(scope);
+    match serde_v8::to_v8(scope, ToJsBuffer::from(input.into_bytes())) {
+        Ok(bytes) => rv.set(bytes),
+        Err(err) => OpError::from(err).throw(scope),
+    }
+}
+
+fn decode(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let input: JsBuffer = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(input) => input,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let fatal = args.get(1).boolean_value(scope);
+    let ignore_bom = args.get(2).boolean_value(scope);
+
+    let mut bytes: &[u8] = &input;
+    if !ignore_bom && bytes.starts_with(BOM) {
+        bytes = &bytes[BOM.len()..];
+    }
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text.into(),
+        Err(_) if fatal => {
+            return OpError::type_error("The encoded data is not valid.").throw(scope);
+        }
+        Err(_) => String::from_utf8_lossy(bytes),
+    };
+    match v8::String::new(scope, &text) {
+        Some(text) => rv.set(text.into()),
+        None => OpError::range_error("decoded string is too long").throw(scope),
+    }
+}
+
+/// utf-8 only, behind `TextEncoder` and `TextDecoder`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "encode", encode);
+    Runtime::set_func(scope, obj, "decode", decode);
+    obj
+}
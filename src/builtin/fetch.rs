@@ -1,88 +1,298 @@
-use crate::runtime::AsynchronousKind;
-use std::{str::FromStr, sync::Arc, task::Poll};
-
-use core::pin::Pin;
-use reqwest::{self, Client, Method};
-
-use crate::runtime::Runtime;
-
-#[derive(Debug, Default)]
-struct FetchOption {
-    // A BodyInit object or null to set request's body.
-    // body: Option<BodyInit>,
-    // A string indicating how the request will interact with the browser's cache to set request's cache.
-    // cache: Option<RequestCache>,
-    // A string indicating whether credentials will be sent with the request always, never, or only when sent to a same-origin URL. Sets request's credentials.
-    // credentials: Option<RequestCredentials>,
-    // A Headers object, an object literal, or an array of two-item arrays to set request's headers.
-    // headers: Option<HeadersInit>,
-    // A cryptographic hash of the resource to be fetched by request. Sets request's integrity.
-    // integrity: Option<String>,
-    // A boolean to set request's keepalive.
-    // keepalive: Option<bool>,
-    // A string to set request's method.
-    method: Method,
-    // A string to indicate whether the request will use CORS, or will be restricted to same-origin URLs. Sets request's mode.
-    // mode: Option<RequestMode>,
-    // A string indicating whether request follows redirects, results in an error upon encountering a redirect, or returns the redirect (in an opaque fashion). Sets request's redirect.
-    // redirect: Option<RequestRedirect>,
-    // A string whose value is a same-origin URL, "about:client", or the empty string, to set request's referrer.
-    // referrer: Option<String>,
-    // A referrer policy to set request's referrerPolicy.
-    // referrer_policy: Option<ReferrerPolicy>,
-    // An AbortSignal to set request's signal.
-    // signal: Option<AbortSignal>,
+use std::{rc::Rc, sync::OnceLock};
+
+use reqwest::{
+    header::{self, HeaderName, HeaderValue},
+    redirect::Policy,
+    Client, Method, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use serde_v8::{JsBuffer, ToJsBuffer};
+use tokio::sync::Mutex;
+
+use super::resources;
+use crate::{
+    permissions::{self, PermissionName, Permissions},
+    runtime::{settle, CancelHandle, OpError, OpResult, Resource, ResourceId, Runtime},
+};
+
+/// statuses whose response never has a body
+const NULL_BODY_STATUS: [u16; 5] = [101, 103, 204, 205, 304];
+
+/// redirects followed before a request fails
+const MAX_REDIRECTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Redirect {
+    Follow,
+    Error,
+    Manual,
 }
 
-pub fn fetch(
-    scope: &mut v8::HandleScope,
-    args: v8::FunctionCallbackArguments,
-    mut rv: v8::ReturnValue,
-) {
-    let state_rc = Runtime::state(scope);
+/// a request as sent by `fetch` in bootstrap/web/fetch.ts, the body is already buffered
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchArgs {
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<JsBuffer>,
+    redirect: Redirect,
+    cancel_rid: Option<ResourceId>,
+}
 
-    let url = args.get(0);
-    let options = args.get(1);
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchResponse {
+    status: u16,
+    status_text: String,
+    url: String,
+    redirected: bool,
+    headers: Vec<(String, String)>,
+    body_rid: Option<ResourceId>,
+}
 
-    let init = if options.is_object() {
-        let obj = v8::Local::<v8::Object>::try_from(options).unwrap();
+/// the body of a response, read chunk by chunk until it is closed
+struct FetchBody {
+    response: Mutex<reqwest::Response>,
+    cancel: CancelHandle,
+}
 
-        let mut init = FetchOption::default();
+impl Resource for FetchBody {
+    fn name(&self) -> &'static str {
+        "fetchResponseBody"
+    }
 
-        let method_key = v8::String::new(scope, "method").unwrap();
-        init.method = if let Some(method) = obj.get(scope, method_key.into()) {
-            Method::from_str(method.to_rust_string_lossy(scope).as_str()).expect("invalida method")
-        } else {
-            Method::GET
-        };
-        init
-    } else {
-        FetchOption::default()
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+fn fetch_error(err: reqwest::Error) -> OpError {
+    OpError::type_error(format!("fetch failed: {:#}", anyhow::Error::from(err)))
+}
+
+/// clients are shared so that connections are pooled, one per redirect policy
+///
+/// redirects are followed by [`execute`] instead of the client, so that every
+/// location is checked against the net permission
+fn client(redirect: Redirect) -> OpResult<Client> {
+    static ERROR: OnceLock<Client> = OnceLock::new();
+    static MANUAL: OnceLock<Client> = OnceLock::new();
+
+    let (cell, policy) = match redirect {
+        Redirect::Error => (
+            &ERROR,
+            Policy::custom(|attempt| attempt.error("unexpected redirect")),
+        ),
+        Redirect::Follow | Redirect::Manual => (&MANUAL, Policy::none()),
     };
-    let client = reqwest::Client::new();
-    let builder = client.request(init.method, url.to_rust_string_lossy(scope));
+    if let Some(client) = cell.get() {
+        return Ok(client.clone());
+    }
+    let client = Client::builder()
+        .redirect(policy)
+        .build()
+        .map_err(fetch_error)?;
+    Ok(cell.get_or_init(|| client).clone())
+}
 
-    let resolver = v8::PromiseResolver::new(scope).unwrap();
+fn request(args: FetchArgs) -> OpResult<(Client, reqwest::Request)> {
+    let method = Method::from_bytes(args.method.as_bytes())
+        .map_err(|_| OpError::type_error(format!("invalid method `{}`", args.method)))?;
+    let client = client(args.redirect)?;
+    let mut builder = client.request(method, &args.url);
+    for (name, value) in args.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| OpError::type_error(format!("invalid header name `{name}`")))?;
+        let value = HeaderValue::from_str(&value)
+            .map_err(|_| OpError::type_error(format!("invalid header value `{value}`")))?;
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = args.body {
+        builder = builder.body(body.to_vec());
+    }
+    let request = builder.build().map_err(fetch_error)?;
+    Ok((client, request))
+}
+
+/// the request that follows the redirect `response` to `request`, if it is one
+fn redirect(
+    mut request: reqwest::Request,
+    response: &reqwest::Response,
+) -> OpResult<Option<reqwest::Request>> {
+    let status = response.status();
+    if !matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308) {
+        return Ok(None);
+    }
+    let Some(location) = response.headers().get(header::LOCATION) else {
+        return Ok(None);
+    };
+    let url = location
+        .to_str()
+        .ok()
+        .and_then(|location| response.url().join(location).ok())
+        .ok_or_else(|| OpError::type_error("fetch failed: invalid redirect location"))?;
 
-    // let resolver = Arc::new(resolver);
+    // a 303, or a 301 and 302 to a POST, turns into a GET without a body
+    let method = request.method().clone();
+    if (status == StatusCode::SEE_OTHER && method != Method::GET && method != Method::HEAD)
+        || (matches!(status.as_u16(), 301 | 302) && method == Method::POST)
+    {
+        *request.method_mut() = Method::GET;
+        *request.body_mut() = None;
+        for name in [
+            header::CONTENT_TYPE,
+            header::CONTENT_LENGTH,
+            header::CONTENT_ENCODING,
+            header::CONTENT_LANGUAGE,
+            header::CONTENT_LOCATION,
+        ] {
+            request.headers_mut().remove(name);
+        }
+    }
+    // credentials are not sent to another origin
+    if url.origin() != request.url().origin() {
+        for name in [
+            header::AUTHORIZATION,
+            header::COOKIE,
+            header::PROXY_AUTHORIZATION,
+        ] {
+            request.headers_mut().remove(name);
+        }
+    }
+    *request.url_mut() = url;
+    Ok(Some(request))
+}
 
-    let state = state_rc.borrow_mut();
-    // let move_resolver = resolver.clone();
-    // state.pending_ops.push(Pin::new(Poll::Ready(AsynchronousKind::Callback(async move {
-    //     let builder = builder.build().unwrap();
+/// send `request`, following redirects when `follow` is set
+///
+/// every location is checked against `permissions`, a snapshot taken when the request
+/// was sent that never prompts, the runtime state can not be borrowed while ops are polled
+async fn execute(
+    client: Client,
+    mut request: reqwest::Request,
+    follow: bool,
+    mut permissions: Permissions,
+) -> OpResult<reqwest::Response> {
+    let mut redirects = 0;
+    loop {
+        let next = if follow { request.try_clone() } else { None };
+        let response = client.execute(request).await.map_err(fetch_error)?;
+        let next = match next {
+            Some(next) => redirect(next, &response)?,
+            None => None,
+        };
+        let Some(next) = next else {
+            return Ok(response);
+        };
+        redirects += 1;
+        if redirects > MAX_REDIRECTS {
+            return Err(OpError::type_error("fetch failed: too many redirects"));
+        }
+        permissions
+            .check(PermissionName::Net, next.url().as_str())
+            .map_err(|message| OpError::new("PermissionDenied", message))?;
+        request = next;
+    }
+}
 
-    //     let response = Client::execute(&client, builder);
+fn send(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let args: FetchArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    if !permissions::check(scope, PermissionName::Net, &args.url) {
+        return;
+    }
 
-    //     let status = response.status();
-    //     let value = v8::ObjectTemplate::new(scope);
-    //     let status_key = v8::String::new(scope, "status").unwrap();
-    //     let status_value = v8::String::new(scope, status.as_str()).unwrap();
-    //     value.set(status_key.into(), status_value.into());
+    let cancel = match args.cancel_rid {
+        Some(rid) => match Runtime::state(scope)
+            .borrow()
+            .resources
+            .get::<CancelHandle>(rid)
+        {
+            Some(cancel) => Some(cancel),
+            None => return OpError::bad_resource(rid).throw(scope),
+        },
+        None => None,
+    };
+    let head = args.method == "HEAD";
+    let follow = matches!(args.redirect, Redirect::Follow);
+    let (client, request) = match request(args) {
+        Ok(request) => request,
+        Err(err) => return err.throw(scope),
+    };
+    let url = request.url().clone();
+    let mut permissions = Runtime::state(scope).borrow().permissions.clone();
+    permissions.prompt = false;
+    let future = execute(client, request, follow, permissions);
 
-    //     let value = value.new_instance(scope).unwrap();
-    //     move_resolver.resolve(scope, value.into());
+    let promise = Runtime::promise_with(scope, async move {
+        let response = match cancel {
+            Some(cancel) => cancel
+                .run(future)
+                .await
+                .ok_or_else(|| OpError::abort("the request was aborted"))
+                .and_then(|response| response),
+            None => future.await,
+        };
+        settle(move |scope| {
+            let response = response.map_err(|err| err.to_v8(scope))?;
+            let status = response.status();
+            let mut fetched = FetchResponse {
+                status: status.as_u16(),
+                status_text: status.canonical_reason().unwrap_or_default().to_string(),
+                url: response.url().to_string(),
+                redirected: follow && *response.url() != url,
+                headers: response
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                        (name.to_string(), value)
+                    })
+                    .collect(),
+                body_rid: None,
+            };
+            if !head && !NULL_BODY_STATUS.contains(&fetched.status) {
+                let body = FetchBody {
+                    response: Mutex::new(response),
+                    cancel: CancelHandle::default(),
+                };
+                fetched.body_rid = Some(Runtime::state(scope).borrow_mut().resources.add(body));
+            }
+            serde_v8::to_v8(scope, fetched).map_err(|err| OpError::from(err).to_v8(scope))
+        })
+    });
+    rv.set(promise.into());
+}
 
-    // }))));
+fn read(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let body = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<FetchBody>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let body = body.ok_or_else(|| OpError::bad_resource(rid))?;
+        let chunk = body
+            .cancel
+            .run(async { body.response.lock().await.chunk().await })
+            .await
+            .ok_or_else(|| OpError::abort("the response body was closed"))?
+            .map_err(fetch_error)?;
+        Ok(chunk.map(|chunk| ToJsBuffer::from(chunk.to_vec())))
+    });
+    rv.set(promise.into());
+}
 
-    rv.set(resolver.into());
+/// `fetch` ops, `send` resolves once the response head arrives and `read` pulls the body
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "send", send);
+    Runtime::set_func(scope, obj, "read", read);
+    obj
 }
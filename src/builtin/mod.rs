@@ -1,6 +1,19 @@
+pub(crate) mod command;
 pub(crate) mod console;
+pub(crate) mod dns;
+pub(crate) mod edon_fs;
+pub(crate) mod encoding;
 pub(crate) mod fetch;
 pub(crate) mod modules;
+pub(crate) mod net;
+pub(crate) mod permissions;
+pub(crate) mod process;
+pub(crate) mod resources;
+pub(crate) mod serve;
 pub(crate) mod set_timeout;
-// pub(crate) use edon_fs;
+pub(crate) mod signal;
+pub(crate) mod tls;
+pub(crate) mod url;
+pub(crate) mod watcher;
+pub(crate) mod websocket;
 // pub(crate) use modules::native_module_inject;
@@ -0,0 +1,600 @@
+#[cfg(unix)]
+use std::{cell::Cell, path::PathBuf};
+use std::{net::SocketAddr, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+use serde_v8::JsBuffer;
+#[cfg(unix)]
+use tokio::net::{unix, UnixListener, UnixStream};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf},
+    net::{TcpListener, TcpStream, UdpSocket},
+    sync::Mutex,
+};
+
+use super::{resources, tls::TlsInfo};
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{settle, CancelHandle, OpError, Resource, ResourceId, Runtime, Settle},
+};
+
+/// an address as seen from JS, `{ transport: "tcp" | "udp", hostname, port }` or
+/// `{ transport: "unix", path }`
+#[derive(Debug, Serialize)]
+#[serde(tag = "transport", rename_all = "lowercase")]
+pub(crate) enum NetAddr {
+    Tcp {
+        hostname: String,
+        port: u16,
+    },
+    Udp {
+        hostname: String,
+        port: u16,
+    },
+    #[cfg(unix)]
+    Unix {
+        path: Option<String>,
+    },
+}
+
+impl NetAddr {
+    fn udp(addr: SocketAddr) -> Self {
+        Self::Udp {
+            hostname: addr.ip().to_string(),
+            port: addr.port(),
+        }
+    }
+}
+
+impl From<SocketAddr> for NetAddr {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Tcp {
+            hostname: addr.ip().to_string(),
+            port: addr.port(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<unix::SocketAddr> for NetAddr {
+    fn from(addr: unix::SocketAddr) -> Self {
+        Self::Unix {
+            path: addr
+                .as_pathname()
+                .map(|path| path.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    /// with the socket file it created, removed once the listener is closed
+    #[cfg(unix)]
+    Unix(UnixListener, Cell<Option<PathBuf>>),
+}
+
+/// a listening socket, closing it ends a pending `accept`
+struct NetListener {
+    listener: Listener,
+    cancel: CancelHandle,
+}
+
+impl Resource for NetListener {
+    fn name(&self) -> &'static str {
+        match self.listener {
+            Listener::Tcp(_) => "tcpListener",
+            #[cfg(unix)]
+            Listener::Unix(..) => "unixListener",
+        }
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+        self.unlink();
+    }
+}
+
+impl NetListener {
+    /// remove the socket file of a unix listener, only once so that a socket bound to
+    /// the same path after `close` is kept
+    fn unlink(&self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = &self.listener {
+            if let Some(path) = path.take() {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+impl Drop for NetListener {
+    fn drop(&mut self) {
+        self.unlink();
+    }
+}
+
+/// any stream a [`Connection`] can be made of
+pub(crate) trait NetStream: AsyncRead + AsyncWrite + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> NetStream for T {}
+
+pub(crate) type BoxedStream = Box<dyn NetStream>;
+
+/// a connected stream, its halves are read and written independently
+pub(crate) struct Connection {
+    name: &'static str,
+    reader: Mutex<ReadHalf<BoxedStream>>,
+    pub(crate) writer: Mutex<WriteHalf<BoxedStream>>,
+    /// the handshake of a tls stream
+    pub(crate) tls: Option<Rc<TlsInfo>>,
+    cancel: CancelHandle,
+}
+
+impl Connection {
+    pub(crate) fn new(name: &'static str, stream: impl NetStream + 'static) -> Self {
+        let (reader, writer) = tokio::io::split(Box::new(stream) as BoxedStream);
+        Self {
+            name,
+            reader: Mutex::new(reader),
+            writer: Mutex::new(writer),
+            tls: None,
+            cancel: CancelHandle::default(),
+        }
+    }
+
+    /// the stream back, e.g. to start tls on it
+    pub(crate) fn into_stream(self) -> BoxedStream {
+        self.reader.into_inner().unsplit(self.writer.into_inner())
+    }
+}
+
+impl Resource for Connection {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+/// a bound udp socket, closing it ends a pending `receiveDatagram`
+struct DatagramSocket {
+    socket: UdpSocket,
+    cancel: CancelHandle,
+}
+
+impl Resource for DatagramSocket {
+    fn name(&self) -> &'static str {
+        "udpSocket"
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TcpArgs {
+    hostname: String,
+    port: u16,
+}
+
+#[cfg(unix)]
+#[derive(Debug, Deserialize)]
+struct UnixArgs {
+    path: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Connected {
+    rid: ResourceId,
+    local_addr: NetAddr,
+    remote_addr: NetAddr,
+}
+
+/// what a `listen` op returns, `{ rid, addr }`
+#[derive(Debug, Serialize)]
+pub(crate) struct Listening {
+    pub(crate) rid: ResourceId,
+    pub(crate) addr: NetAddr,
+}
+
+pub(crate) fn tcp_connected(stream: TcpStream) -> std::io::Result<(Connection, NetAddr, NetAddr)> {
+    let (local, remote) = tcp_addrs(&stream)?;
+    Ok((Connection::new("tcpStream", stream), local, remote))
+}
+
+pub(crate) fn tcp_addrs(stream: &TcpStream) -> std::io::Result<(NetAddr, NetAddr)> {
+    stream.set_nodelay(true)?;
+    Ok((stream.local_addr()?.into(), stream.peer_addr()?.into()))
+}
+
+#[cfg(unix)]
+fn unix_connected(stream: UnixStream) -> std::io::Result<(Connection, NetAddr, NetAddr)> {
+    let local = stream.local_addr()?.into();
+    let remote = stream.peer_addr()?.into();
+    Ok((Connection::new("unixStream", stream), local, remote))
+}
+
+/// resolve a promise with `{ rid, localAddr, remoteAddr }`, the connection is added on settle
+pub(crate) fn settle_connected(
+    connected: std::io::Result<(Connection, NetAddr, NetAddr)>,
+) -> Settle {
+    settle(move |scope| {
+        let (connection, local_addr, remote_addr) =
+            connected.map_err(|err| OpError::from(err).to_v8(scope))?;
+        let connected = Connected {
+            rid: Runtime::state(scope).borrow_mut().resources.add(connection),
+            local_addr,
+            remote_addr,
+        };
+        serde_v8::to_v8(scope, connected).map_err(|err| OpError::from(err).to_v8(scope))
+    })
+}
+
+fn connect(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: TcpArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let resource = format!("{}:{}", args.hostname, args.port);
+    if !permissions::check(scope, PermissionName::Net, &resource) {
+        return;
+    }
+    let promise = Runtime::promise_with(scope, async move {
+        let stream = TcpStream::connect((args.hostname.as_str(), args.port)).await;
+        settle_connected(stream.and_then(tcp_connected))
+    });
+    rv.set(promise.into());
+}
+
+/// unix sockets live on the file system, they are gated by read access to their path
+#[cfg(unix)]
+fn connect_unix(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: UnixArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    if !permissions::check(scope, PermissionName::Read, &args.path) {
+        return;
+    }
+    let promise = Runtime::promise_with(scope, async move {
+        let stream = UnixStream::connect(&args.path).await;
+        settle_connected(stream.and_then(unix_connected))
+    });
+    rv.set(promise.into());
+}
+
+fn add_listener(
+    scope: &mut v8::HandleScope,
+    mut rv: v8::ReturnValue,
+    listener: std::io::Result<(Listener, NetAddr)>,
+) {
+    let (listener, addr) = match listener {
+        Ok(listener) => listener,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let rid = Runtime::state(scope)
+        .borrow_mut()
+        .resources
+        .add(NetListener {
+            listener,
+            cancel: CancelHandle::default(),
+        });
+    match serde_v8::to_v8(scope, Listening { rid, addr }) {
+        Ok(listening) => rv.set(listening),
+        Err(err) => OpError::from(err).throw(scope),
+    }
+}
+
+/// bind right away so that `addr` is known and errors throw synchronously
+fn listen(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, rv: v8::ReturnValue) {
+    let args: TcpArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let resource = format!("{}:{}", args.hostname, args.port);
+    if !permissions::check(scope, PermissionName::Net, &resource) {
+        return;
+    }
+    let listener = std::net::TcpListener::bind((args.hostname.as_str(), args.port))
+        .and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            TcpListener::from_std(listener)
+        })
+        .and_then(|listener| {
+            let addr = listener.local_addr()?.into();
+            Ok((Listener::Tcp(listener), addr))
+        });
+    add_listener(scope, rv, listener);
+}
+
+#[cfg(unix)]
+fn listen_unix(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+) {
+    let args: UnixArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    if !permissions::check(scope, PermissionName::Read, &args.path)
+        || !permissions::check(scope, PermissionName::Write, &args.path)
+    {
+        return;
+    }
+    let listener = UnixListener::bind(&args.path).and_then(|listener| {
+        let addr = listener.local_addr()?.into();
+        let path = Cell::new(Some(PathBuf::from(&args.path)));
+        Ok((Listener::Unix(listener, path), addr))
+    });
+    add_listener(scope, rv, listener);
+}
+
+#[cfg(not(unix))]
+fn connect_unix(scope: &mut v8::HandleScope, _: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+    OpError::not_supported("unix socket").throw(scope);
+}
+
+#[cfg(not(unix))]
+fn listen_unix(scope: &mut v8::HandleScope, _: v8::FunctionCallbackArguments, _: v8::ReturnValue) {
+    OpError::not_supported("unix socket").throw(scope);
+}
+
+/// the next connection, `null` once the listener is closed
+fn accept(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let Some(listener) = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<NetListener>(rid)
+    else {
+        return OpError::bad_resource(rid).throw(scope);
+    };
+    let promise = Runtime::promise_with(scope, async move {
+        let accepted = listener
+            .cancel
+            .run(async {
+                match &listener.listener {
+                    Listener::Tcp(tcp) => tcp
+                        .accept()
+                        .await
+                        .and_then(|(stream, _)| tcp_connected(stream)),
+                    #[cfg(unix)]
+                    Listener::Unix(unix, _) => unix
+                        .accept()
+                        .await
+                        .and_then(|(stream, _)| unix_connected(stream)),
+                }
+            })
+            .await;
+        match accepted {
+            Some(accepted) => settle_connected(accepted),
+            None => settle(|scope| Ok(v8::null(scope).into())),
+        }
+    });
+    rv.set(promise.into());
+}
+
+/// read into the given buffer, resolves to the number of bytes read or `null` at the end
+fn read(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let mut buf: JsBuffer = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(buf) => buf,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let connection = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<Connection>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let connection = connection.ok_or_else(|| OpError::bad_resource(rid))?;
+        let read = connection
+            .cancel
+            .run(async { connection.reader.lock().await.read(&mut buf).await })
+            .await
+            .ok_or_else(|| OpError::new("Interrupted", "the connection was closed"))??;
+        Ok((read > 0 || buf.is_empty()).then_some(read))
+    });
+    rv.set(promise.into());
+}
+
+/// resolves to the number of bytes written, which may be less than the buffer
+fn write(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let buf: JsBuffer = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(buf) => buf,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let connection = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<Connection>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let connection = connection.ok_or_else(|| OpError::bad_resource(rid))?;
+        let written = connection
+            .cancel
+            .run(async {
+                let mut writer = connection.writer.lock().await;
+                let written = writer.write(&buf).await?;
+                writer.flush().await?;
+                Ok::<_, std::io::Error>(written)
+            })
+            .await
+            .ok_or_else(|| OpError::new("Interrupted", "the connection was closed"))??;
+        Ok(written)
+    });
+    rv.set(promise.into());
+}
+
+/// shut down the writing half, the peer reads the end of the stream
+fn close_write(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let connection = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<Connection>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let connection = connection.ok_or_else(|| OpError::bad_resource(rid))?;
+        connection.writer.lock().await.shutdown().await?;
+        Ok(())
+    });
+    rv.set(promise.into());
+}
+
+fn listen_datagram(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: TcpArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let resource = format!("{}:{}", args.hostname, args.port);
+    if !permissions::check(scope, PermissionName::Net, &resource) {
+        return;
+    }
+    let socket = std::net::UdpSocket::bind((args.hostname.as_str(), args.port))
+        .and_then(|socket| {
+            socket.set_nonblocking(true)?;
+            UdpSocket::from_std(socket)
+        })
+        .and_then(|socket| {
+            let addr = NetAddr::udp(socket.local_addr()?);
+            Ok((socket, addr))
+        });
+    let (socket, addr) = match socket {
+        Ok(socket) => socket,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let rid = Runtime::state(scope)
+        .borrow_mut()
+        .resources
+        .add(DatagramSocket {
+            socket,
+            cancel: CancelHandle::default(),
+        });
+    match serde_v8::to_v8(scope, Listening { rid, addr }) {
+        Ok(listening) => rv.set(listening),
+        Err(err) => OpError::from(err).throw(scope),
+    }
+}
+
+/// send one datagram, every destination is checked against the net permission
+fn send_datagram(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let to: TcpArgs = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(to) => to,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let data: JsBuffer = match serde_v8::from_v8(scope, args.get(2)) {
+        Ok(data) => data,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let resource = format!("{}:{}", to.hostname, to.port);
+    if !permissions::check(scope, PermissionName::Net, &resource) {
+        return;
+    }
+    let socket = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<DatagramSocket>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let socket = socket.ok_or_else(|| OpError::bad_resource(rid))?;
+        let sent = socket
+            .cancel
+            .run(
+                socket
+                    .socket
+                    .send_to(&data, (to.hostname.as_str(), to.port)),
+            )
+            .await
+            .ok_or_else(|| OpError::new("Interrupted", "the socket was closed"))??;
+        Ok(sent)
+    });
+    rv.set(promise.into());
+}
+
+/// receive one datagram into the given buffer, resolves to `[length, remoteAddr]`
+fn receive_datagram(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let mut buf: JsBuffer = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(buf) => buf,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let socket = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<DatagramSocket>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let socket = socket.ok_or_else(|| OpError::bad_resource(rid))?;
+        let (received, from) = socket
+            .cancel
+            .run(socket.socket.recv_from(&mut buf))
+            .await
+            .ok_or_else(|| OpError::new("Interrupted", "the socket was closed"))??;
+        Ok((received, NetAddr::udp(from)))
+    });
+    rv.set(promise.into());
+}
+
+/// `net` ops behind `Edon.connect`, `Edon.listen` and `Edon.listenDatagram`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "connect", connect);
+    Runtime::set_func(scope, obj, "connectUnix", connect_unix);
+    Runtime::set_func(scope, obj, "listen", listen);
+    Runtime::set_func(scope, obj, "listenUnix", listen_unix);
+    Runtime::set_func(scope, obj, "accept", accept);
+    Runtime::set_func(scope, obj, "read", read);
+    Runtime::set_func(scope, obj, "write", write);
+    Runtime::set_func(scope, obj, "closeWrite", close_write);
+    Runtime::set_func(scope, obj, "listenDatagram", listen_datagram);
+    Runtime::set_func(scope, obj, "sendDatagram", send_datagram);
+    Runtime::set_func(scope, obj, "receiveDatagram", receive_datagram);
+    obj
+}
@@ -0,0 +1,84 @@
+use crate::{
+    permissions::{PermissionName, PermissionState, Permissions},
+    runtime::{OpError, Runtime},
+};
+
+/// `{ name: "read" | "write", path?: string }`, `{ name: "net", host?: string }`,
+/// `{ name: "env", variable?: string }` or `{ name: "run", command?: string }`
+fn descriptor(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Option<(PermissionName, Option<String>)> {
+    let Ok(obj) = v8::Local::<v8::Object>::try_from(value) else {
+        OpError::type_error("permission descriptor must be an object").throw(scope);
+        return None;
+    };
+    let key = v8::String::new(scope, "name").unwrap();
+    let name = obj.get(scope, key.into())?.to_rust_string_lossy(scope);
+    let name = match name.parse::<PermissionName>() {
+        Ok(name) => name,
+        Err(err) => {
+            OpError::type_error(err).throw(scope);
+            return None;
+        }
+    };
+    let key = v8::String::new(scope, name.descriptor_key()).unwrap();
+    let resource = obj.get(scope, key.into())?;
+    let resource = match resource.is_null_or_undefined() {
+        true => None,
+        false => Some(resource.to_rust_string_lossy(scope)),
+    };
+    Some((name, resource))
+}
+
+/// resolve a promise with a `{ state }` status
+fn resolve_status(scope: &mut v8::HandleScope, mut rv: v8::ReturnValue, state: PermissionState) {
+    let status = v8::Object::new(scope);
+    let key = v8::String::new(scope, "state").unwrap();
+    let value = v8::String::new(scope, &state.to_string()).unwrap();
+    status.set(scope, key.into(), value.into());
+
+    let resolver = v8::PromiseResolver::new(scope).unwrap();
+    resolver.resolve(scope, status.into());
+    rv.set(resolver.get_promise(scope).into());
+}
+
+fn permission_op(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    rv: v8::ReturnValue,
+    op: fn(&mut Permissions, PermissionName, Option<&str>) -> PermissionState,
+) {
+    let Some((name, resource)) = descriptor(scope, args.get(0)) else {
+        return;
+    };
+    let state = {
+        let state_rc = Runtime::state(scope);
+        let mut state = state_rc.borrow_mut();
+        op(&mut state.permissions, name, resource.as_deref())
+    };
+    resolve_status(scope, rv, state);
+}
+
+fn query(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, rv: v8::ReturnValue) {
+    permission_op(scope, args, rv, |permissions, name, resource| {
+        permissions.query(name, resource)
+    });
+}
+
+fn request(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, rv: v8::ReturnValue) {
+    permission_op(scope, args, rv, Permissions::request);
+}
+
+fn revoke(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, rv: v8::ReturnValue) {
+    permission_op(scope, args, rv, Permissions::revoke);
+}
+
+/// `Edon.permissions`, every method resolves to `{ state: "granted" | "prompt" | "denied" }`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "query", query);
+    Runtime::set_func(scope, obj, "request", request);
+    Runtime::set_func(scope, obj, "revoke", revoke);
+    obj
+}
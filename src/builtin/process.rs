@@ -0,0 +1,152 @@
+use std::{env, io::Write};
+
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{OpError, Runtime},
+};
+
+fn env_key(scope: &mut v8::HandleScope, key: v8::Local<v8::Value>) -> Option<String> {
+    if !key.is_string() {
+        OpError::type_error("environment variable name must be a string").throw(scope);
+        return None;
+    }
+    let key = key.to_rust_string_lossy(scope);
+    if key.is_empty() || key.contains(['=', '\0']) {
+        OpError::type_error(format!("invalid environment variable name `{key}`")).throw(scope);
+        return None;
+    }
+    Some(key)
+}
+
+fn env_get(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(key) = env_key(scope, args.get(0)) else {
+        return;
+    };
+    if !permissions::check(scope, PermissionName::Env, &key) {
+        return;
+    }
+    match env::var(key) {
+        Ok(value) => rv.set(v8::String::new(scope, &value).unwrap().into()),
+        Err(_) => rv.set_undefined(),
+    }
+}
+
+fn env_set(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _rv: v8::ReturnValue,
+) {
+    let Some(key) = env_key(scope, args.get(0)) else {
+        return;
+    };
+    if !permissions::check(scope, PermissionName::Env, &key) {
+        return;
+    }
+    let value = args.get(1).to_rust_string_lossy(scope);
+    if value.contains('\0') {
+        OpError::type_error("environment variable value must not contain NUL").throw(scope);
+        return;
+    }
+    env::set_var(key, value);
+}
+
+fn env_delete(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _rv: v8::ReturnValue,
+) {
+    let Some(key) = env_key(scope, args.get(0)) else {
+        return;
+    };
+    if !permissions::check(scope, PermissionName::Env, &key) {
+        return;
+    }
+    env::remove_var(key);
+}
+
+fn env_to_object(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    if !permissions::check_all(scope, PermissionName::Env) {
+        return;
+    }
+    let obj = v8::Object::new(scope);
+    for (key, value) in env::vars_os() {
+        let key = v8::String::new(scope, &key.to_string_lossy()).unwrap();
+        let value = v8::String::new(scope, &value.to_string_lossy()).unwrap();
+        obj.set(scope, key.into(), value.into());
+    }
+    rv.set(obj.into());
+}
+
+fn exit(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue) {
+    let code = if args.get(0).is_undefined() {
+        Runtime::state(scope).borrow().exit_code
+    } else {
+        args.get(0).int32_value(scope).unwrap_or_default()
+    };
+    let _ = std::io::stdout().flush();
+    std::process::exit(code);
+}
+
+fn cwd(scope: &mut v8::HandleScope, _args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    match env::current_dir() {
+        Ok(dir) => rv.set(
+            v8::String::new(scope, &dir.to_string_lossy())
+                .unwrap()
+                .into(),
+        ),
+        Err(err) => {
+            let message = v8::String::new(scope, &err.to_string()).unwrap();
+            let exception = v8::Exception::error(scope, message);
+            scope.throw_exception(exception);
+        }
+    }
+}
+
+/// path of the running edon binary, e.g. to run edon again as a subprocess
+fn exec_path(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let path = match env::current_exe() {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    if !permissions::check(scope, PermissionName::Read, &path) {
+        return;
+    }
+    rv.set(v8::String::new(scope, &path).unwrap().into());
+}
+
+/// `Edon` namespace, `Edon.args` is filled in when the runtime bootstraps
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+
+    let env = v8::Object::new(scope);
+    Runtime::set_func(scope, env, "get", env_get);
+    Runtime::set_func(scope, env, "set", env_set);
+    Runtime::set_func(scope, env, "delete", env_delete);
+    Runtime::set_func(scope, env, "toObject", env_to_object);
+    Runtime::set_obj(scope, obj, "env", env);
+
+    let permissions = super::permissions::init(scope);
+    Runtime::set_obj(scope, obj, "permissions", permissions);
+
+    Runtime::set_func(scope, obj, "exit", exit);
+    Runtime::set_func(scope, obj, "cwd", cwd);
+    Runtime::set_func(scope, obj, "execPath", exec_path);
+
+    let pid_key = v8::String::new(scope, "pid").unwrap();
+    let pid = v8::Integer::new_from_unsigned(scope, std::process::id());
+    obj.set(scope, pid_key.into(), pid.into());
+
+    obj
+}
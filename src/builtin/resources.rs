@@ -0,0 +1,40 @@
+use crate::runtime::{CancelHandle, OpError, ResourceId, Runtime};
+
+/// a resource id argument, throws a `TypeError` for anything else
+pub fn rid(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Option<ResourceId> {
+    if !value.is_uint32() {
+        OpError::type_error("resource id must be an unsigned integer").throw(scope);
+        return None;
+    }
+    value.uint32_value(scope)
+}
+
+fn close(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue) {
+    let Some(rid) = rid(scope, args.get(0)) else {
+        return;
+    };
+    let closed = Runtime::state(scope).borrow_mut().resources.close(rid);
+    if !closed {
+        OpError::bad_resource(rid).throw(scope);
+    }
+}
+
+fn cancel_handle(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let rid = Runtime::state(scope)
+        .borrow_mut()
+        .resources
+        .add(CancelHandle::default());
+    rv.set_uint32(rid);
+}
+
+/// `core` ops, shared by every module that hands out resource ids
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "close", close);
+    Runtime::set_func(scope, obj, "cancelHandle", cancel_handle);
+    obj
+}
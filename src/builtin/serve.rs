@@ -0,0 +1,496 @@
+use std::{cell::RefCell, convert::Infallible, net::SocketAddr, rc::Rc};
+
+use bytes::Bytes;
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, Empty, Full, StreamBody};
+use hyper::{
+    body::{Body, Frame, Incoming},
+    header::{HeaderName, HeaderValue, HOST},
+    service::service_fn,
+    upgrade::OnUpgrade,
+    Request, Response, StatusCode,
+};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use serde::{Deserialize, Serialize};
+use serde_v8::{JsBuffer, ToJsBuffer};
+use tokio::{
+    net::TcpListener,
+    sync::{mpsc, oneshot, watch, Mutex},
+};
+use tokio_tungstenite::{tungstenite::protocol::Role, WebSocketStream};
+
+use super::{
+    net::{Listening, NetAddr},
+    resources,
+    websocket::WebSocket,
+};
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{settle, CancelHandle, OpError, Resource, ResourceId, Runtime},
+};
+
+type ResponseBody = UnsyncBoxBody<Bytes, Infallible>;
+
+/// a request handed from a connection task to the JS handler
+struct Pending {
+    request: Request<Incoming>,
+    respond: oneshot::Sender<Response<ResponseBody>>,
+    local: SocketAddr,
+    remote: SocketAddr,
+}
+
+/// requests arrive until the server shuts down and every connection is done
+struct HttpServer {
+    requests: Mutex<mpsc::Receiver<Pending>>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Resource for HttpServer {
+    fn name(&self) -> &'static str {
+        "httpServer"
+    }
+
+    fn close(self: Rc<Self>) {
+        let _ = self.shutdown.send(true);
+    }
+}
+
+/// a request being handled, its body is read and its response sent from JS
+struct HttpRequest {
+    body: Mutex<Incoming>,
+    respond: RefCell<Option<oneshot::Sender<Response<ResponseBody>>>>,
+    upgrade: RefCell<Option<OnUpgrade>>,
+    cancel: CancelHandle,
+}
+
+impl Resource for HttpRequest {
+    fn name(&self) -> &'static str {
+        "httpRequest"
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+/// the body of a streamed response, closing it ends the body
+struct HttpResponseBody {
+    sender: mpsc::Sender<Bytes>,
+}
+
+impl Resource for HttpResponseBody {
+    fn name(&self) -> &'static str {
+        "httpResponseBody"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenArgs {
+    hostname: String,
+    port: u16,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NextRequest {
+    rid: ResourceId,
+    method: String,
+    url: String,
+    headers: Vec<(String, String)>,
+    has_body: bool,
+    remote_addr: NetAddr,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RespondArgs {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Option<JsBuffer>,
+    stream: bool,
+}
+
+fn status(status: StatusCode) -> Response<ResponseBody> {
+    let mut response = Response::new(Empty::new().boxed_unsync());
+    *response.status_mut() = status;
+    response
+}
+
+/// serve one connection, on shutdown in-flight requests finish before it closes
+async fn connection(
+    stream: tokio::net::TcpStream,
+    local: SocketAddr,
+    remote: SocketAddr,
+    requests: mpsc::Sender<Pending>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let service = service_fn(move |request| {
+        let requests = requests.clone();
+        async move {
+            let (respond, response) = oneshot::channel();
+            let pending = Pending {
+                request,
+                respond,
+                local,
+                remote,
+            };
+            if requests.send(pending).await.is_err() {
+                return Ok::<_, Infallible>(status(StatusCode::SERVICE_UNAVAILABLE));
+            }
+            // the handler threw or the runtime went away without a response
+            Ok(response
+                .await
+                .unwrap_or_else(|_| status(StatusCode::INTERNAL_SERVER_ERROR)))
+        }
+    });
+    let builder = auto::Builder::new(TokioExecutor::new());
+    let conn = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+    tokio::pin!(conn);
+    // the watch guard is not `Send`, it must not live across the await below
+    let shutdown = async move {
+        let _ = shutdown.wait_for(|shutdown| *shutdown).await;
+    };
+    tokio::select! {
+        _ = conn.as_mut() => {}
+        _ = shutdown => {
+            conn.as_mut().graceful_shutdown();
+            let _ = conn.await;
+        }
+    }
+}
+
+async fn accept(
+    listener: TcpListener,
+    requests: mpsc::Sender<Pending>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let Ok(local) = listener.local_addr() else {
+        return;
+    };
+    loop {
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = shutdown.wait_for(|shutdown| *shutdown) => break,
+        };
+        // e.g. the connection was reset before it was accepted
+        let Ok((stream, remote)) = accepted else {
+            continue;
+        };
+        tokio::spawn(connection(
+            stream,
+            local,
+            remote,
+            requests.clone(),
+            shutdown.clone(),
+        ));
+    }
+}
+
+fn listen(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: ListenArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let resource = format!("{}:{}", args.hostname, args.port);
+    if !permissions::check(scope, PermissionName::Net, &resource) {
+        return;
+    }
+
+    // bind right away so that `addr` is known and errors throw synchronously
+    let listener =
+        std::net::TcpListener::bind((args.hostname.as_str(), args.port)).and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            TcpListener::from_std(listener)
+        });
+    let listener = match listener {
+        Ok(listener) => listener,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let addr = match listener.local_addr() {
+        Ok(addr) => addr,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+
+    let (sender, receiver) = mpsc::channel(128);
+    let (shutdown, watcher) = watch::channel(false);
+    tokio::spawn(accept(listener, sender, watcher));
+
+    let rid = Runtime::state(scope)
+        .borrow_mut()
+        .resources
+        .add(HttpServer {
+            requests: Mutex::new(receiver),
+            shutdown,
+        });
+    let listening = Listening {
+        rid,
+        addr: addr.into(),
+    };
+    match serde_v8::to_v8(scope, listening) {
+        Ok(listening) => rv.set(listening),
+        Err(err) => OpError::from(err).throw(scope),
+    }
+}
+
+/// the next request, `null` once the server is shut down and drained
+fn next(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let Some(server) = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<HttpServer>(rid)
+    else {
+        return OpError::bad_resource(rid).throw(scope);
+    };
+    let promise = Runtime::promise_with(scope, async move {
+        let pending = server.requests.lock().await.recv().await;
+        settle(move |scope| {
+            let Some(mut pending) = pending else {
+                return Ok(v8::null(scope).into());
+            };
+            let upgrade = hyper::upgrade::on(&mut pending.request);
+            let (parts, body) = pending.request.into_parts();
+            let url = match parts.uri.scheme() {
+                // http/2 requests carry the full url
+                Some(_) => parts.uri.to_string(),
+                None => {
+                    let host = parts
+                        .headers
+                        .get(HOST)
+                        .and_then(|host| host.to_str().ok())
+                        .map(str::to_string)
+                        .unwrap_or_else(|| pending.local.to_string());
+                    let path = parts.uri.path_and_query().map_or("/", |path| path.as_str());
+                    format!("http://{host}{path}")
+                }
+            };
+            let has_body = !body.is_end_stream();
+            let request = HttpRequest {
+                body: Mutex::new(body),
+                respond: RefCell::new(Some(pending.respond)),
+                upgrade: RefCell::new(Some(upgrade)),
+                cancel: CancelHandle::default(),
+            };
+            let next = NextRequest {
+                rid: Runtime::state(scope).borrow_mut().resources.add(request),
+                method: parts.method.to_string(),
+                url,
+                headers: parts
+                    .headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                        (name.to_string(), value)
+                    })
+                    .collect(),
+                has_body,
+                remote_addr: pending.remote.into(),
+            };
+            serde_v8::to_v8(scope, next).map_err(|err| OpError::from(err).to_v8(scope))
+        })
+    });
+    rv.set(promise.into());
+}
+
+/// stop accepting connections, `next` resolves to `null` once in-flight requests are done
+fn shutdown(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    _rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    match Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<HttpServer>(rid)
+    {
+        Some(server) => {
+            let _ = server.shutdown.send(true);
+        }
+        None => OpError::bad_resource(rid).throw(scope),
+    }
+}
+
+fn read(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let request = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<HttpRequest>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let request = request.ok_or_else(|| OpError::bad_resource(rid))?;
+        let chunk = request
+            .cancel
+            .run(async {
+                let mut body = request.body.lock().await;
+                // trailers are skipped
+                while let Some(frame) = body.frame().await {
+                    if let Ok(data) = frame?.into_data() {
+                        return Ok(Some(data));
+                    }
+                }
+                Ok::<_, hyper::Error>(None)
+            })
+            .await
+            .ok_or_else(|| OpError::abort("the request was closed"))?
+            .map_err(|err| OpError::new("Http", err.to_string()))?;
+        Ok(chunk.map(|chunk| ToJsBuffer::from(chunk.to_vec())))
+    });
+    rv.set(promise.into());
+}
+
+fn response(
+    args: RespondArgs,
+) -> Result<(Response<ResponseBody>, Option<HttpResponseBody>), OpError> {
+    let (body, writer) = match (args.body, args.stream) {
+        (_, true) => {
+            let (sender, receiver) = mpsc::channel::<Bytes>(16);
+            let stream = futures::stream::unfold(receiver, |mut receiver| async move {
+                let chunk = receiver.recv().await?;
+                Some((Ok(Frame::data(chunk)), receiver))
+            });
+            (
+                StreamBody::new(stream).boxed_unsync(),
+                Some(HttpResponseBody { sender }),
+            )
+        }
+        (Some(body), false) => (Full::new(Bytes::from(body.to_vec())).boxed_unsync(), None),
+        (None, false) => (Empty::new().boxed_unsync(), None),
+    };
+
+    let mut response = Response::new(body);
+    *response.status_mut() = StatusCode::from_u16(args.status)
+        .map_err(|_| OpError::range_error(format!("invalid status {}", args.status)))?;
+    let headers = response.headers_mut();
+    for (name, value) in args.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| OpError::type_error(format!("invalid header name `{name}`")))?;
+        let value = HeaderValue::from_str(&value)
+            .map_err(|_| OpError::type_error(format!("invalid header value `{value}`")))?;
+        headers.append(name, value);
+    }
+    Ok((response, writer))
+}
+
+/// send the response head, a streamed body is written through the returned resource id
+fn respond(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let args: RespondArgs = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let state_rc = Runtime::state(scope);
+    let Some(request) = state_rc.borrow().resources.get::<HttpRequest>(rid) else {
+        return OpError::bad_resource(rid).throw(scope);
+    };
+    let Some(sender) = request.respond.borrow_mut().take() else {
+        return OpError::new("Http", "the response was already sent").throw(scope);
+    };
+    let (response, writer) = match response(args) {
+        Ok(response) => response,
+        Err(err) => return err.throw(scope),
+    };
+    // the client may be gone already, there is nobody left to tell
+    let _ = sender.send(response);
+    match writer {
+        Some(writer) => rv.set_uint32(state_rc.borrow_mut().resources.add(writer)),
+        None => rv.set_null(),
+    }
+}
+
+fn write(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let chunk: JsBuffer = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(chunk) => chunk,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let chunk = Bytes::from(chunk.to_vec());
+    let body = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<HttpResponseBody>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let body = body.ok_or_else(|| OpError::bad_resource(rid))?;
+        body.sender
+            .send(chunk)
+            .await
+            .map_err(|_| OpError::new("BrokenPipe", "the connection was closed"))
+    });
+    rv.set(promise.into());
+}
+
+/// take over the connection once a `101` response was sent, resolves to a websocket resource id
+fn upgrade(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let Some(request) = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<HttpRequest>(rid)
+    else {
+        return OpError::bad_resource(rid).throw(scope);
+    };
+    let Some(upgrade) = request.upgrade.borrow_mut().take() else {
+        return OpError::new("Http", "the connection was already upgraded").throw(scope);
+    };
+    let promise = Runtime::promise_with(scope, async move {
+        let stream = match upgrade.await {
+            Ok(upgraded) => {
+                let io = TokioIo::new(upgraded);
+                Ok(WebSocketStream::from_raw_socket(io, Role::Server, None).await)
+            }
+            Err(err) => Err(OpError::new("Http", format!("upgrade failed: {err}"))),
+        };
+        settle(move |scope| {
+            let stream = stream.map_err(|err| err.to_v8(scope))?;
+            let rid = Runtime::state(scope)
+                .borrow_mut()
+                .resources
+                .add(WebSocket::new(stream));
+            Ok(v8::Integer::new_from_unsigned(scope, rid).into())
+        })
+    });
+    rv.set(promise.into());
+}
+
+/// `serve` ops behind `Edon.serve`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "listen", listen);
+    Runtime::set_func(scope, obj, "next", next);
+    Runtime::set_func(scope, obj, "shutdown", shutdown);
+    Runtime::set_func(scope, obj, "read", read);
+    Runtime::set_func(scope, obj, "respond", respond);
+    Runtime::set_func(scope, obj, "write", write);
+    Runtime::set_func(scope, obj, "upgrade", upgrade);
+    obj
+}
@@ -0,0 +1,245 @@
+#[cfg(unix)]
+use std::{cell::Cell, collections::BTreeMap, ptr, rc::Rc};
+
+use libc::c_int;
+#[cfg(unix)]
+use tokio::{
+    signal::unix::{self, Signal, SignalKind},
+    sync::Mutex,
+};
+
+#[cfg(unix)]
+use super::resources;
+#[cfg(unix)]
+use crate::runtime::{CancelHandle, Resource};
+use crate::runtime::{OpError, Runtime};
+
+/// the signals scripts can name, e.g. in `ChildProcess.kill`
+#[cfg(unix)]
+const SIGNALS: &[(&str, c_int)] = &[
+    ("SIGHUP", libc::SIGHUP),
+    ("SIGINT", libc::SIGINT),
+    ("SIGQUIT", libc::SIGQUIT),
+    ("SIGILL", libc::SIGILL),
+    ("SIGTRAP", libc::SIGTRAP),
+    ("SIGABRT", libc::SIGABRT),
+    ("SIGBUS", libc::SIGBUS),
+    ("SIGFPE", libc::SIGFPE),
+    ("SIGKILL", libc::SIGKILL),
+    ("SIGUSR1", libc::SIGUSR1),
+    ("SIGSEGV", libc::SIGSEGV),
+    ("SIGUSR2", libc::SIGUSR2),
+    ("SIGPIPE", libc::SIGPIPE),
+    ("SIGALRM", libc::SIGALRM),
+    ("SIGTERM", libc::SIGTERM),
+    ("SIGCHLD", libc::SIGCHLD),
+    ("SIGCONT", libc::SIGCONT),
+    ("SIGSTOP", libc::SIGSTOP),
+    ("SIGTSTP", libc::SIGTSTP),
+    ("SIGTTIN", libc::SIGTTIN),
+    ("SIGTTOU", libc::SIGTTOU),
+    ("SIGURG", libc::SIGURG),
+    ("SIGXCPU", libc::SIGXCPU),
+    ("SIGXFSZ", libc::SIGXFSZ),
+    ("SIGVTALRM", libc::SIGVTALRM),
+    ("SIGPROF", libc::SIGPROF),
+    ("SIGWINCH", libc::SIGWINCH),
+    ("SIGIO", libc::SIGIO),
+    ("SIGSYS", libc::SIGSYS),
+];
+
+/// signals a process can not handle, or only when it is already broken
+#[cfg(unix)]
+const FORBIDDEN: &[c_int] = &[
+    libc::SIGKILL,
+    libc::SIGSTOP,
+    libc::SIGILL,
+    libc::SIGFPE,
+    libc::SIGSEGV,
+];
+
+/// the number of a signal name, a `TypeError` for names that are not in the list
+#[cfg(unix)]
+pub(crate) fn signal_number(name: &str) -> Result<c_int, OpError> {
+    SIGNALS
+        .iter()
+        .find(|(signal, _)| *signal == name)
+        .map(|(_, number)| *number)
+        .ok_or_else(|| OpError::type_error(format!("unknown signal `{name}`")))
+}
+
+#[cfg(unix)]
+pub(crate) fn signal_name(number: c_int) -> Option<&'static str> {
+    SIGNALS
+        .iter()
+        .find(|(_, signal)| *signal == number)
+        .map(|(name, _)| *name)
+}
+
+/// signals only exist on unix
+#[cfg(not(unix))]
+pub(crate) fn signal_number(name: &str) -> Result<c_int, OpError> {
+    Err(OpError::not_supported(&format!("signal `{name}`")))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn signal_name(_number: c_int) -> Option<&'static str> {
+    None
+}
+
+/// the listeners of a signal across every runtime of the process
+#[cfg(unix)]
+#[derive(Default)]
+struct Bound {
+    listeners: usize,
+    /// the handler of tokio, put aside while the signal has its default action
+    parked: Option<libc::sigaction>,
+}
+
+#[cfg(unix)]
+static BOUND: std::sync::Mutex<BTreeMap<c_int, Bound>> = std::sync::Mutex::new(BTreeMap::new());
+
+/// count a listener, bringing back the handler of tokio if the default action was restored
+#[cfg(unix)]
+fn acquire(number: c_int) {
+    let mut bound = BOUND.lock().unwrap();
+    let bound = bound.entry(number).or_default();
+    if let Some(action) = bound.parked.take() {
+        unsafe { libc::sigaction(number, &action, ptr::null_mut()) };
+    }
+    bound.listeners += 1;
+}
+
+/// tokio never uninstalls a handler, without listeners it would swallow e.g. `SIGINT`, so
+/// the last one gone restores the default action
+#[cfg(unix)]
+fn release(number: c_int) {
+    let mut bound = BOUND.lock().unwrap();
+    let Some(bound) = bound.get_mut(&number) else {
+        return;
+    };
+    bound.listeners -= 1;
+    if bound.listeners > 0 {
+        return;
+    }
+    unsafe {
+        let mut default: libc::sigaction = std::mem::zeroed();
+        default.sa_sigaction = libc::SIG_DFL;
+        libc::sigemptyset(&mut default.sa_mask);
+        let mut action: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(number, &default, &mut action) == 0 {
+            bound.parked = Some(action);
+        }
+    }
+}
+
+/// a signal bound by `Edon.addSignalListener`, closing it ends a pending `poll`
+///
+/// the default action of the signal, e.g. exiting on `SIGINT`, comes back once every
+/// listener of the process is closed or dropped with its runtime
+#[cfg(unix)]
+struct SignalListener {
+    number: c_int,
+    released: Cell<bool>,
+    signal: Mutex<Signal>,
+    cancel: CancelHandle,
+}
+
+#[cfg(unix)]
+impl SignalListener {
+    /// right away on close, a pending `poll` keeps the listener until the next tick
+    fn release(&self) {
+        if !self.released.replace(true) {
+            release(self.number);
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SignalListener {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(unix)]
+impl Resource for SignalListener {
+    fn name(&self) -> &'static str {
+        "signal"
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+        self.release();
+    }
+}
+
+#[cfg(unix)]
+fn bind(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let name = args.get(0).to_rust_string_lossy(scope);
+    let number = match signal_number(&name) {
+        Ok(number) => number,
+        Err(err) => return err.throw(scope),
+    };
+    if FORBIDDEN.contains(&number) {
+        return OpError::type_error(format!("signal `{name}` can not be listened to")).throw(scope);
+    }
+    acquire(number);
+    let signal = match unix::signal(SignalKind::from_raw(number)) {
+        Ok(signal) => signal,
+        Err(err) => {
+            release(number);
+            return OpError::from(err).throw(scope);
+        }
+    };
+    let rid = Runtime::state(scope)
+        .borrow_mut()
+        .resources
+        .add(SignalListener {
+            number,
+            released: Cell::new(false),
+            signal: Mutex::new(signal),
+            cancel: CancelHandle::default(),
+        });
+    rv.set_uint32(rid);
+}
+
+#[cfg(not(unix))]
+fn bind(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue) {
+    let name = args.get(0).to_rust_string_lossy(scope);
+    if let Err(err) = signal_number(&name) {
+        err.throw(scope);
+    }
+}
+
+/// resolves to `true` once the signal arrives, `false` when the listener was closed, a
+/// pending poll keeps the event loop alive
+#[cfg(unix)]
+fn poll(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let listener = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<SignalListener>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let listener = listener.ok_or_else(|| OpError::bad_resource(rid))?;
+        let received = listener
+            .cancel
+            .run(async { listener.signal.lock().await.recv().await })
+            .await;
+        Ok(matches!(received, Some(Some(()))))
+    });
+    rv.set(promise.into());
+}
+
+/// `signal` ops behind `Edon.addSignalListener`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "bind", bind);
+    // `bind` throws on other platforms, nothing is ever polled
+    #[cfg(unix)]
+    Runtime::set_func(scope, obj, "poll", poll);
+    obj
+}
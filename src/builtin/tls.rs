@@ -0,0 +1,483 @@
+use std::{
+    cell::RefCell,
+    future::Future,
+    io,
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::{TcpListener, TcpStream},
+};
+use tokio_rustls::{
+    rustls::{
+        crypto::{ring, CryptoProvider},
+        pki_types::{CertificateDer, PrivateKeyDer, ServerName},
+        ClientConfig, RootCertStore, ServerConfig,
+    },
+    Accept, TlsAcceptor, TlsConnector, TlsStream,
+};
+
+use super::{
+    net::{self, BoxedStream, Connection, Listening},
+    resources,
+};
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{settle, CancelHandle, OpError, OpResult, Resource, Runtime},
+};
+
+/// the outcome of a handshake, the negotiated ALPN protocol or why it failed
+#[derive(Debug, Default)]
+pub(crate) struct TlsInfo {
+    result: RefCell<Option<Result<Option<String>, String>>>,
+}
+
+enum State {
+    Accept(Accept<BoxedStream>),
+    Ready(TlsStream<BoxedStream>),
+    Failed,
+}
+
+/// a tls stream, an accepted one finishes its handshake on first use so that a
+/// slow client does not hold up the listener
+struct Tls {
+    state: State,
+    info: Rc<TlsInfo>,
+}
+
+impl Tls {
+    fn ready(stream: TlsStream<BoxedStream>) -> (Self, Rc<TlsInfo>) {
+        let info = Rc::new(TlsInfo::default());
+        *info.result.borrow_mut() = Some(Ok(alpn_protocol(&stream)));
+        let tls = Self {
+            state: State::Ready(stream),
+            info: info.clone(),
+        };
+        (tls, info)
+    }
+
+    fn accept(accept: Accept<BoxedStream>) -> (Self, Rc<TlsInfo>) {
+        let info = Rc::new(TlsInfo::default());
+        let tls = Self {
+            state: State::Accept(accept),
+            info: info.clone(),
+        };
+        (tls, info)
+    }
+
+    fn poll_handshake(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<io::Result<&mut TlsStream<BoxedStream>>> {
+        if let State::Accept(accept) = &mut self.state {
+            let accepted = ready!(Pin::new(accept).poll(cx));
+            let (state, result) = match accepted {
+                Ok(stream) => {
+                    let stream = TlsStream::from(stream);
+                    let alpn = alpn_protocol(&stream);
+                    (State::Ready(stream), Ok(alpn))
+                }
+                Err(err) => (State::Failed, Err(err.to_string())),
+            };
+            self.state = state;
+            *self.info.result.borrow_mut() = Some(result);
+        }
+        match &mut self.state {
+            State::Ready(stream) => Poll::Ready(Ok(stream)),
+            _ => Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "the tls handshake failed",
+            ))),
+        }
+    }
+}
+
+impl AsyncRead for Tls {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut ReadBuf,
+    ) -> Poll<io::Result<()>> {
+        let stream = ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Tls {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let stream = ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let stream = ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let stream = ready!(self.get_mut().poll_handshake(cx))?;
+        Pin::new(stream).poll_shutdown(cx)
+    }
+}
+
+fn alpn_protocol(stream: &TlsStream<BoxedStream>) -> Option<String> {
+    let (_, state) = stream.get_ref();
+    state
+        .alpn_protocol()
+        .map(|protocol| String::from_utf8_lossy(protocol).into_owned())
+}
+
+fn tls_connection(tls: Tls, info: Rc<TlsInfo>) -> Connection {
+    let mut connection = Connection::new("tlsStream", tls);
+    connection.tls = Some(info);
+    connection
+}
+
+/// a listening socket handing out tls connections
+struct TlsListener {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    cancel: CancelHandle,
+}
+
+impl Resource for TlsListener {
+    fn name(&self) -> &'static str {
+        "tlsListener"
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ClientOptions {
+    /// PEM encoded certificates trusted next to the bundled Mozilla roots
+    ca_certs: Vec<String>,
+    /// PEM encoded client certificate chain and private key
+    cert: Option<String>,
+    key: Option<String>,
+    alpn_protocols: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConnectTlsArgs {
+    hostname: String,
+    port: u16,
+    /// the name sent for SNI and verified against the certificate, the hostname by default
+    server_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListenTlsArgs {
+    hostname: String,
+    port: u16,
+    cert: String,
+    key: String,
+    alpn_protocols: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HandshakeInfo {
+    alpn_protocol: Option<String>,
+}
+
+fn invalid_data(message: impl Into<String>) -> OpError {
+    OpError::new("InvalidData", message)
+}
+
+fn certs(pem: &str) -> OpResult<Vec<CertificateDer<'static>>> {
+    let certs = rustls_pemfile::certs(&mut pem.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| invalid_data(format!("invalid certificate: {err}")))?;
+    if certs.is_empty() {
+        return Err(invalid_data("no certificate found in PEM"));
+    }
+    Ok(certs)
+}
+
+fn private_key(pem: &str) -> OpResult<PrivateKeyDer<'static>> {
+    rustls_pemfile::private_key(&mut pem.as_bytes())
+        .map_err(|err| invalid_data(format!("invalid private key: {err}")))?
+        .ok_or_else(|| invalid_data("no private key found in PEM"))
+}
+
+fn alpn(protocols: Vec<String>) -> Vec<Vec<u8>> {
+    protocols.into_iter().map(String::into_bytes).collect()
+}
+
+/// ring is used for the crypto so that no system OpenSSL is needed
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+fn client_config(options: ClientOptions) -> OpResult<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    for pem in &options.ca_certs {
+        for cert in certs(pem)? {
+            roots
+                .add(cert)
+                .map_err(|err| invalid_data(format!("invalid CA certificate: {err}")))?;
+        }
+    }
+    let builder = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| OpError::type_error(err.to_string()))?
+        .with_root_certificates(roots);
+    let mut config = match (options.cert, options.key) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(certs(&cert)?, private_key(&key)?)
+            .map_err(|err| invalid_data(err.to_string()))?,
+        (None, None) => builder.with_no_client_auth(),
+        _ => {
+            return Err(OpError::type_error(
+                "a client certificate needs both `cert` and `key`",
+            ))
+        }
+    };
+    config.alpn_protocols = alpn(options.alpn_protocols);
+    Ok(config)
+}
+
+fn server_config(args: &ListenTlsArgs) -> OpResult<ServerConfig> {
+    let mut config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| OpError::type_error(err.to_string()))?
+        .with_no_client_auth()
+        .with_single_cert(certs(&args.cert)?, private_key(&args.key)?)
+        .map_err(|err| invalid_data(err.to_string()))?;
+    config.alpn_protocols = alpn(args.alpn_protocols.clone());
+    Ok(config)
+}
+
+fn server_name(name: &str) -> OpResult<ServerName<'static>> {
+    ServerName::try_from(name.to_string())
+        .map_err(|_| OpError::type_error(format!("invalid server name `{name}`")))
+}
+
+fn connector(options: ClientOptions, name: &str) -> OpResult<(TlsConnector, ServerName<'static>)> {
+    let config = client_config(options)?;
+    Ok((TlsConnector::from(Arc::new(config)), server_name(name)?))
+}
+
+fn connect_tls(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let connect: ConnectTlsArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(connect) => connect,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let options: ClientOptions = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(options) => options,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let resource = format!("{}:{}", connect.hostname, connect.port);
+    if !permissions::check(scope, PermissionName::Net, &resource) {
+        return;
+    }
+    let name = connect.server_name.as_deref().unwrap_or(&connect.hostname);
+    let (connector, name) = match connector(options, name) {
+        Ok(connector) => connector,
+        Err(err) => return err.throw(scope),
+    };
+
+    // the handshake is done up front, certificate errors reject the promise
+    let promise = Runtime::promise_with(scope, async move {
+        let connected = async {
+            let stream = TcpStream::connect((connect.hostname.as_str(), connect.port)).await?;
+            let (local, remote) = net::tcp_addrs(&stream)?;
+            let stream = connector
+                .connect(name, Box::new(stream) as BoxedStream)
+                .await?;
+            let (tls, info) = Tls::ready(TlsStream::from(stream));
+            Ok((tls_connection(tls, info), local, remote))
+        };
+        net::settle_connected(connected.await)
+    });
+    rv.set(promise.into());
+}
+
+/// upgrade a connection to tls, the plain connection's resource id is gone afterwards
+fn start_tls(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let hostname = args.get(1).to_rust_string_lossy(scope);
+    let options: ClientOptions = match serde_v8::from_v8(scope, args.get(2)) {
+        Ok(options) => options,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let (connector, name) = match connector(options, &hostname) {
+        Ok(connector) => connector,
+        Err(err) => return err.throw(scope),
+    };
+    let state_rc = Runtime::state(scope);
+    match state_rc.borrow().resources.get::<Connection>(rid) {
+        Some(connection) if connection.tls.is_some() => {
+            return OpError::type_error("the connection already uses tls").throw(scope)
+        }
+        Some(_) => {}
+        None => return OpError::bad_resource(rid).throw(scope),
+    }
+    let Some(connection) = state_rc.borrow_mut().resources.take::<Connection>(rid) else {
+        return OpError::bad_resource(rid).throw(scope);
+    };
+    let connection = match Rc::try_unwrap(connection) {
+        Ok(connection) => connection,
+        Err(connection) => {
+            // a read or write is still pending, the connection stays usable
+            state_rc.borrow_mut().resources.add_rc(connection);
+            return OpError::new("Busy", "the connection has pending reads or writes").throw(scope);
+        }
+    };
+
+    let promise = Runtime::promise_with(scope, async move {
+        let connected = connector.connect(name, connection.into_stream()).await;
+        settle(move |scope| {
+            let stream = connected.map_err(|err| OpError::from(err).to_v8(scope))?;
+            let (tls, info) = Tls::ready(TlsStream::from(stream));
+            let rid = Runtime::state(scope)
+                .borrow_mut()
+                .resources
+                .add(tls_connection(tls, info));
+            Ok(v8::Integer::new_from_unsigned(scope, rid).into())
+        })
+    });
+    rv.set(promise.into());
+}
+
+fn listen_tls(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: ListenTlsArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let resource = format!("{}:{}", args.hostname, args.port);
+    if !permissions::check(scope, PermissionName::Net, &resource) {
+        return;
+    }
+    let acceptor = match server_config(&args) {
+        Ok(config) => TlsAcceptor::from(Arc::new(config)),
+        Err(err) => return err.throw(scope),
+    };
+    let listener = std::net::TcpListener::bind((args.hostname.as_str(), args.port))
+        .and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            TcpListener::from_std(listener)
+        })
+        .and_then(|listener| {
+            let addr = listener.local_addr()?.into();
+            Ok((listener, addr))
+        });
+    let (listener, addr) = match listener {
+        Ok(listener) => listener,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let rid = Runtime::state(scope)
+        .borrow_mut()
+        .resources
+        .add(TlsListener {
+            listener,
+            acceptor,
+            cancel: CancelHandle::default(),
+        });
+    match serde_v8::to_v8(scope, Listening { rid, addr }) {
+        Ok(listening) => rv.set(listening),
+        Err(err) => OpError::from(err).throw(scope),
+    }
+}
+
+/// the next connection, `null` once the listener is closed, the handshake runs on first use
+fn accept_tls(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let Some(listener) = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<TlsListener>(rid)
+    else {
+        return OpError::bad_resource(rid).throw(scope);
+    };
+    let promise = Runtime::promise_with(scope, async move {
+        let accepted = listener.cancel.run(listener.listener.accept()).await;
+        let Some(accepted) = accepted else {
+            return settle(|scope| Ok(v8::null(scope).into()));
+        };
+        let accepted = accepted.and_then(|(stream, _)| {
+            let (local, remote) = net::tcp_addrs(&stream)?;
+            let accept = listener.acceptor.accept(Box::new(stream) as BoxedStream);
+            let (tls, info) = Tls::accept(accept);
+            Ok((tls_connection(tls, info), local, remote))
+        });
+        net::settle_connected(accepted)
+    });
+    rv.set(promise.into());
+}
+
+/// finish the handshake of a tls connection, resolves to `{ alpnProtocol }`
+fn handshake(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let Some(connection) = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<Connection>(rid)
+    else {
+        return OpError::bad_resource(rid).throw(scope);
+    };
+    let Some(info) = connection.tls.clone() else {
+        return OpError::type_error("not a tls connection").throw(scope);
+    };
+    let promise = Runtime::promise(scope, async move {
+        if info.result.borrow().is_none() {
+            // flushing drives the handshake of a stream that was not used yet
+            let _ = connection.writer.lock().await.flush().await;
+        }
+        match info.result.borrow().clone() {
+            Some(Ok(alpn_protocol)) => Ok(HandshakeInfo { alpn_protocol }),
+            Some(Err(message)) => Err(invalid_data(message)),
+            None => Err(invalid_data("the tls handshake did not finish")),
+        }
+    });
+    rv.set(promise.into());
+}
+
+/// `tls` ops behind `Edon.connectTls`, `Edon.listenTls` and `Edon.startTls`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "connectTls", connect_tls);
+    Runtime::set_func(scope, obj, "startTls", start_tls);
+    Runtime::set_func(scope, obj, "listenTls", listen_tls);
+    Runtime::set_func(scope, obj, "acceptTls", accept_tls);
+    Runtime::set_func(scope, obj, "handshake", handshake);
+    obj
+}
@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use url::{quirks, Url};
+
+use crate::runtime::{OpError, Runtime};
+
+/// a parsed url, as returned by the getters of `URL`
+#[derive(Debug, Serialize)]
+struct UrlParts<'a> {
+    href: &'a str,
+    origin: String,
+    protocol: &'a str,
+    username: &'a str,
+    password: &'a str,
+    host: &'a str,
+    hostname: &'a str,
+    port: &'a str,
+    pathname: &'a str,
+    search: &'a str,
+    hash: &'a str,
+}
+
+impl<'a> From<&'a Url> for UrlParts<'a> {
+    fn from(url: &'a Url) -> Self {
+        Self {
+            href: quirks::href(url),
+            origin: quirks::origin(url),
+            protocol: quirks::protocol(url),
+            username: quirks::username(url),
+            password: quirks::password(url),
+            host: quirks::host(url),
+            hostname: quirks::hostname(url),
+            port: quirks::port(url),
+            pathname: quirks::pathname(url),
+            search: quirks::search(url),
+            hash: quirks::hash(url),
+        }
+    }
+}
+
+/// the setters of `URL`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Part {
+    Href,
+    Protocol,
+    Username,
+    Password,
+    Host,
+    Hostname,
+    Port,
+    Pathname,
+    Search,
+    Hash,
+}
+
+fn invalid(href: &str) -> OpError {
+    OpError::type_error(format!("Invalid URL: '{href}'"))
+}
+
+fn set_parts(scope: &mut v8::HandleScope, mut rv: v8::ReturnValue, url: &Url) {
+    match serde_v8::to_v8(scope, UrlParts::from(url)) {
+        Ok(parts) => rv.set(parts),
+        Err(err) => OpError::from(err).throw(scope),
+    }
+}
+
+/// parse `href`, relative to `base` unless it is null
+fn parse(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, rv: v8::ReturnValue) {
+    let href = args.get(0).to_rust_string_lossy(scope);
+    let base = args.get(1);
+    let url = if base.is_null_or_undefined() {
+        Url::parse(&href)
+    } else {
+        let base = base.to_rust_string_lossy(scope);
+        Url::parse(&base).and_then(|base| base.join(&href))
+    };
+    match url {
+        Ok(url) => set_parts(scope, rv, &url),
+        Err(_) => invalid(&href).throw(scope),
+    }
+}
+
+/// set one part of the url `href`, returns the parts of the new url
+fn set(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, rv: v8::ReturnValue) {
+    let href = args.get(0).to_rust_string_lossy(scope);
+    let part: Part = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(part) => part,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let value = args.get(2).to_rust_string_lossy(scope);
+    let Ok(mut url) = Url::parse(&href) else {
+        return invalid(&href).throw(scope);
+    };
+    // like the setters of `URL`, a value the part does not accept is ignored
+    let _ = match part {
+        Part::Href => {
+            if quirks::set_href(&mut url, &value).is_err() {
+                return invalid(&value).throw(scope);
+            }
+            Ok(())
+        }
+        Part::Protocol => quirks::set_protocol(&mut url, &value),
+        Part::Username => quirks::set_username(&mut url, &value),
+        Part::Password => quirks::set_password(&mut url, &value),
+        Part::Host => quirks::set_host(&mut url, &value),
+        Part::Hostname => quirks::set_hostname(&mut url, &value),
+        Part::Port => quirks::set_port(&mut url, &value),
+        Part::Pathname => {
+            quirks::set_pathname(&mut url, &value);
+            Ok(())
+        }
+        Part::Search => {
+            quirks::set_search(&mut url, &value);
+            Ok(())
+        }
+        Part::Hash => {
+            quirks::set_hash(&mut url, &value);
+            Ok(())
+        }
+    };
+    set_parts(scope, rv, &url);
+}
+
+/// WHATWG url parsing behind `URL`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "parse", parse);
+    Runtime::set_func(scope, obj, "set", set);
+    obj
+}
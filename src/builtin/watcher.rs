@@ -0,0 +1,213 @@
+use std::{collections::VecDeque, rc::Rc, time::Duration};
+
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{mpsc, Mutex},
+    time::{self, Instant},
+};
+
+use super::resources;
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{CancelHandle, OpError, Resource, Runtime},
+};
+
+/// how long a change waits for the ones that follow it, an editor saving a file
+/// touches it several times in a row
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// a change as seen from JS, `kind` is `create`, `modify`, `remove` or `rename`
+#[derive(Debug, PartialEq, Serialize)]
+struct FsEvent {
+    kind: &'static str,
+    paths: Vec<String>,
+    /// the rename halves of one move share it with the rename that has both paths
+    #[serde(skip)]
+    tracker: Option<usize>,
+}
+
+impl FsEvent {
+    /// `None` for accesses and changes of unknown kind
+    fn from_event(event: Event) -> Option<Self> {
+        let kind = match event.kind {
+            EventKind::Create(_) => "create",
+            EventKind::Modify(ModifyKind::Name(_)) => "rename",
+            EventKind::Modify(_) => "modify",
+            EventKind::Remove(_) => "remove",
+            EventKind::Access(_) | EventKind::Any | EventKind::Other => return None,
+        };
+        let tracker = match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::From | RenameMode::To)) => {
+                event.tracker()
+            }
+            _ => None,
+        };
+        Some(Self {
+            kind,
+            tracker,
+            paths: event
+                .paths
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+        })
+    }
+}
+
+struct WatchState {
+    events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+    /// debounced events not handed out yet
+    pending: VecDeque<FsEvent>,
+}
+
+impl WatchState {
+    /// queue an event unless the same one is already queued, a rename with both paths
+    /// replaces its halves
+    fn push(&mut self, event: Event) {
+        let both = matches!(
+            event.kind,
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both))
+        );
+        let tracker = event.tracker();
+        let Some(event) = FsEvent::from_event(event) else {
+            return;
+        };
+        if both && tracker.is_some() {
+            self.pending
+                .retain(|pending| pending.kind != "rename" || pending.tracker != tracker);
+        }
+        if !self.pending.contains(&event) {
+            self.pending.push_back(event);
+        }
+    }
+}
+
+/// a watcher of files and directories, closing it ends the iteration
+struct FsWatcher {
+    // dropping it stops the watch
+    _watcher: RecommendedWatcher,
+    state: Mutex<WatchState>,
+    cancel: CancelHandle,
+}
+
+impl Resource for FsWatcher {
+    fn name(&self) -> &'static str {
+        "fsWatcher"
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct WatchArgs {
+    paths: Vec<String>,
+    recursive: bool,
+}
+
+fn watch_error(err: notify::Error) -> OpError {
+    match err.kind {
+        notify::ErrorKind::Io(err) => err.into(),
+        notify::ErrorKind::PathNotFound => {
+            OpError::new("NotFound", format!("watch failed: {:?}", err.paths))
+        }
+        _ => OpError::new("Error", format!("watch failed: {err}")),
+    }
+}
+
+/// start watching every path right away, so that changes made before the first `next` count
+fn watch(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: WatchArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    for path in &args.paths {
+        if !permissions::check(scope, PermissionName::Read, path) {
+            return;
+        }
+    }
+
+    let (sender, events) = mpsc::unbounded_channel();
+    // called on the watcher's own thread
+    let handler = move |event| {
+        let _ = sender.send(event);
+    };
+    let mode = match args.recursive {
+        true => RecursiveMode::Recursive,
+        false => RecursiveMode::NonRecursive,
+    };
+    let watcher = notify::recommended_watcher(handler).and_then(|mut watcher| {
+        for path in &args.paths {
+            watcher.watch(path.as_ref(), mode)?;
+        }
+        Ok(watcher)
+    });
+    let watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(err) => return watch_error(err).throw(scope),
+    };
+
+    let rid = Runtime::state(scope).borrow_mut().resources.add(FsWatcher {
+        _watcher: watcher,
+        state: Mutex::new(WatchState {
+            events,
+            pending: VecDeque::new(),
+        }),
+        cancel: CancelHandle::default(),
+    });
+    rv.set(v8::Integer::new_from_unsigned(scope, rid).into());
+}
+
+/// the next change, `null` once the watcher is closed
+fn next(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let watcher = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<FsWatcher>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let watcher = watcher.ok_or_else(|| OpError::bad_resource(rid))?;
+        let event = watcher
+            .cancel
+            .run(async {
+                let mut state = watcher.state.lock().await;
+                loop {
+                    if let Some(event) = state.pending.pop_front() {
+                        return Ok(Some(event));
+                    }
+                    let Some(event) = state.events.recv().await else {
+                        return Ok(None);
+                    };
+                    state.push(event.map_err(watch_error)?);
+                    let deadline = Instant::now() + DEBOUNCE;
+                    while let Ok(Some(event)) =
+                        time::timeout_at(deadline, state.events.recv()).await
+                    {
+                        state.push(event.map_err(watch_error)?);
+                    }
+                }
+            })
+            .await;
+        event.unwrap_or(Ok(None))
+    });
+    rv.set(promise.into());
+}
+
+/// `watcher` ops behind `Edon.watchFs`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "watch", watch);
+    Runtime::set_func(scope, obj, "next", next);
+    obj
+}
@@ -0,0 +1,315 @@
+use std::{borrow::Cow, pin::Pin, rc::Rc};
+
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_v8::{StringOrBuffer, ToJsBuffer};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::Mutex,
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        self,
+        client::IntoClientRequest,
+        handshake::derive_accept_key,
+        http::HeaderValue,
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message,
+    },
+    WebSocketStream,
+};
+
+use super::resources;
+use crate::{
+    permissions::{self, PermissionName},
+    runtime::{settle, CancelHandle, OpError, Resource, ResourceId, Runtime},
+};
+
+type MessageSink = Pin<Box<dyn Sink<Message, Error = tungstenite::Error>>>;
+type MessageStream = Pin<Box<dyn Stream<Item = Result<Message, tungstenite::Error>>>>;
+
+/// an open websocket, client or server side, messages are sent and received independently
+pub(crate) struct WebSocket {
+    sink: Mutex<MessageSink>,
+    stream: Mutex<MessageStream>,
+    cancel: CancelHandle,
+}
+
+impl WebSocket {
+    pub(crate) fn new<S>(stream: WebSocketStream<S>) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + 'static,
+    {
+        let (sink, stream) = stream.split();
+        Self {
+            sink: Mutex::new(Box::pin(sink)),
+            stream: Mutex::new(Box::pin(stream)),
+            cancel: CancelHandle::default(),
+        }
+    }
+}
+
+impl Resource for WebSocket {
+    fn name(&self) -> &'static str {
+        "webSocket"
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel.cancel();
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConnectArgs {
+    url: String,
+    protocols: Vec<String>,
+    cancel_rid: ResourceId,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Connected {
+    rid: ResourceId,
+    protocol: String,
+    extensions: String,
+}
+
+/// what `next` resolves to, pings are answered and pongs dropped on the way
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum WebSocketEvent {
+    Text { data: String },
+    Binary { data: ToJsBuffer },
+    Close { code: u16, reason: String },
+    Error { message: String },
+}
+
+fn websocket_error(err: tungstenite::Error) -> OpError {
+    OpError::new("Http", format!("websocket failed: {err}"))
+}
+
+/// a `ws:` or `wss:` url, `http:` and `https:` are mapped to them, `null` if invalid
+fn parse_url(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let url = args.get(0).to_rust_string_lossy(scope);
+    let Ok(mut url) = url::Url::parse(&url) else {
+        return rv.set_null();
+    };
+    let scheme = match url.scheme() {
+        "ws" | "http" => "ws",
+        "wss" | "https" => "wss",
+        _ => return rv.set_null(),
+    };
+    if url.fragment().is_some() || url.set_scheme(scheme).is_err() {
+        return rv.set_null();
+    }
+    let url = v8::String::new(scope, url.as_str()).unwrap();
+    rv.set(url.into());
+}
+
+fn accept_key(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let key = args.get(0).to_rust_string_lossy(scope);
+    let accept = v8::String::new(scope, &derive_accept_key(key.as_bytes())).unwrap();
+    rv.set(accept.into());
+}
+
+fn connect(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let args: ConnectArgs = match serde_v8::from_v8(scope, args.get(0)) {
+        Ok(args) => args,
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    if !permissions::check(scope, PermissionName::Net, &args.url) {
+        return;
+    }
+    let Some(cancel) = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<CancelHandle>(args.cancel_rid)
+    else {
+        return OpError::bad_resource(args.cancel_rid).throw(scope);
+    };
+
+    let mut request = match args.url.as_str().into_client_request() {
+        Ok(request) => request,
+        Err(err) => return websocket_error(err).throw(scope),
+    };
+    if !args.protocols.is_empty() {
+        let Ok(protocols) = HeaderValue::from_str(&args.protocols.join(", ")) else {
+            return OpError::type_error("invalid websocket protocol").throw(scope);
+        };
+        request
+            .headers_mut()
+            .insert("sec-websocket-protocol", protocols);
+    }
+
+    let promise = Runtime::promise_with(scope, async move {
+        let connected = cancel
+            .run(tokio_tungstenite::connect_async(request))
+            .await
+            .ok_or_else(|| OpError::abort("the connection was aborted"))
+            .and_then(|connected| connected.map_err(websocket_error));
+        settle(move |scope| {
+            let (stream, response) = connected.map_err(|err| err.to_v8(scope))?;
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let connected = Connected {
+                rid: Runtime::state(scope)
+                    .borrow_mut()
+                    .resources
+                    .add(WebSocket::new(stream)),
+                protocol: header("sec-websocket-protocol"),
+                extensions: header("sec-websocket-extensions"),
+            };
+            serde_v8::to_v8(scope, connected).map_err(|err| OpError::from(err).to_v8(scope))
+        })
+    });
+    rv.set(promise.into());
+}
+
+fn send(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let message = match serde_v8::from_v8(scope, args.get(1)) {
+        Ok(StringOrBuffer::String(text)) => Message::Text(text),
+        Ok(StringOrBuffer::Buffer(bytes)) => Message::Binary(bytes.to_vec()),
+        Err(err) => return OpError::from(err).throw(scope),
+    };
+    let socket = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<WebSocket>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let socket = socket.ok_or_else(|| OpError::bad_resource(rid))?;
+        socket
+            .cancel
+            .run(async { socket.sink.lock().await.send(message).await })
+            .await
+            .ok_or_else(|| OpError::abort("the websocket was closed"))?
+            .map_err(websocket_error)
+    });
+    rv.set(promise.into());
+}
+
+/// start the closing handshake, the close event arrives through `next`
+fn close(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut rv: v8::ReturnValue,
+) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let code = args.get(1);
+    let frame = if code.is_null_or_undefined() {
+        None
+    } else {
+        Some(CloseFrame {
+            code: CloseCode::from(code.uint32_value(scope).unwrap_or_default() as u16),
+            reason: Cow::Owned(args.get(2).to_rust_string_lossy(scope)),
+        })
+    };
+    let socket = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<WebSocket>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let socket = socket.ok_or_else(|| OpError::bad_resource(rid))?;
+        let closed = socket
+            .cancel
+            .run(async { socket.sink.lock().await.send(Message::Close(frame)).await })
+            .await;
+        match closed {
+            // the peer closed first, its close frame is already on the way
+            Some(Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed))
+            | Some(Ok(())) => Ok(()),
+            Some(Err(err)) => Err(websocket_error(err)),
+            None => Err(OpError::abort("the websocket was closed")),
+        }
+    });
+    rv.set(promise.into());
+}
+
+/// the next message, resolves to a close event once and then the socket is done
+fn next(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, mut rv: v8::ReturnValue) {
+    let Some(rid) = resources::rid(scope, args.get(0)) else {
+        return;
+    };
+    let socket = Runtime::state(scope)
+        .borrow()
+        .resources
+        .get::<WebSocket>(rid);
+    let promise = Runtime::promise(scope, async move {
+        let socket = socket.ok_or_else(|| OpError::bad_resource(rid))?;
+        let event = socket
+            .cancel
+            .run(async {
+                let mut stream = socket.stream.lock().await;
+                loop {
+                    // the pong for a ping is flushed by the next read
+                    break match stream.next().await {
+                        Some(Ok(Message::Text(data))) => WebSocketEvent::Text { data },
+                        Some(Ok(Message::Binary(data))) => WebSocketEvent::Binary {
+                            data: ToJsBuffer::from(data),
+                        },
+                        Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => {
+                            continue
+                        }
+                        Some(Ok(Message::Close(frame))) => match frame {
+                            Some(frame) => WebSocketEvent::Close {
+                                code: frame.code.into(),
+                                reason: frame.reason.into_owned(),
+                            },
+                            None => WebSocketEvent::Close {
+                                code: CloseCode::Status.into(),
+                                reason: String::new(),
+                            },
+                        },
+                        Some(Err(tungstenite::Error::ConnectionClosed)) | None => {
+                            WebSocketEvent::Close {
+                                code: CloseCode::Abnormal.into(),
+                                reason: String::new(),
+                            }
+                        }
+                        Some(Err(err)) => WebSocketEvent::Error {
+                            message: err.to_string(),
+                        },
+                    };
+                }
+            })
+            .await
+            .ok_or_else(|| OpError::abort("the websocket was closed"))?;
+        Ok(event)
+    });
+    rv.set(promise.into());
+}
+
+/// `websocket` ops behind the `WebSocket` global and `Edon.upgradeWebSocket`
+pub fn init<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+    let obj = v8::Object::new(scope);
+    Runtime::set_func(scope, obj, "parseUrl", parse_url);
+    Runtime::set_func(scope, obj, "acceptKey", accept_key);
+    Runtime::set_func(scope, obj, "connect", connect);
+    Runtime::set_func(scope, obj, "send", send);
+    Runtime::set_func(scope, obj, "close", close);
+    Runtime::set_func(scope, obj, "next", next);
+    obj
+}
@@ -0,0 +1,189 @@
+use crate::graph::DependencyGraph;
+use anyhow::{anyhow, bail};
+use oxc_allocator::Allocator;
+use oxc_ast::{
+    ast::{ExportDefaultDeclarationKind, ImportDeclarationSpecifier, ModuleExportName, Statement},
+    syntax_directed_operations::BoundNames,
+};
+use oxc_span::{GetSpan, SourceType, Span};
+use std::collections::HashMap;
+
+const MODULES: &str = "__edon_modules";
+const DEFAULT: &str = "__edon_default";
+
+/// Bundle the module graph reachable from `entry` into a single ES module.
+///
+/// Every module is wrapped in an async function scope, imports are rewritten to
+/// reads from the already evaluated dependencies and exports are collected into
+/// a namespace object. Dynamic `import()` is left untouched.
+pub fn bundle(graph: &DependencyGraph, entry: &String) -> anyhow::Result<String> {
+    let mut order = vec![];
+    let mut index = HashMap::new();
+    sort(graph, entry, &mut order, &mut index, &mut vec![])?;
+
+    let mut chunks = vec![
+        format!("const {MODULES} = [];"),
+        "const __edon_star = (m) => Object.fromEntries(Object.entries(m).filter(([k]) => k !== \"default\"));".to_string(),
+    ];
+    for (id, filename) in order.iter().enumerate() {
+        let dep = graph
+            .get(filename)
+            .ok_or(anyhow!("module `{filename}` not found"))?;
+        let body = transform(&dep.source, filename, &|source: &str| {
+            index
+                .get(&graph.resolve(source, filename))
+                .copied()
+                .ok_or(anyhow!("module `{source}` not found from `{filename}`"))
+        })?;
+        chunks.push(format!(
+            "// {filename}\n{MODULES}[{id}] = await (async () => {{\n{body}\n}})();"
+        ));
+    }
+    Ok(chunks.join("\n"))
+}
+
+/// depth first post order, dependencies come before their importers
+fn sort(
+    graph: &DependencyGraph,
+    filename: &String,
+    order: &mut Vec<String>,
+    index: &mut HashMap<String, usize>,
+    stack: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    if index.contains_key(filename) {
+        return Ok(());
+    }
+    if stack.contains(filename) {
+        stack.push(filename.clone());
+        bail!("circular import is not supported: {}", stack.join(" -> "));
+    }
+    let dep = graph
+        .get(filename)
+        .ok_or(anyhow!("module `{filename}` not found"))?;
+
+    stack.push(filename.clone());
+    for source in &dep.deps {
+        sort(graph, &graph.resolve(source, filename), order, index, stack)?;
+    }
+    stack.pop();
+
+    index.insert(filename.clone(), order.len());
+    order.push(filename.clone());
+    Ok(())
+}
+
+fn transform(
+    code: &str,
+    filename: &str,
+    module_id: &dyn Fn(&str) -> anyhow::Result<usize>,
+) -> anyhow::Result<String> {
+    let allo = Allocator::default();
+    let source_type = SourceType::default().with_module(true);
+    let ret = oxc_parser::Parser::new(&allo, code, source_type).parse();
+    if !ret.errors.is_empty() {
+        bail!("parse `{filename}` failure");
+    }
+
+    let mut patches: Vec<(Span, String)> = vec![];
+    let mut exports: Vec<String> = vec![];
+
+    for stmt in &ret.program.body {
+        match stmt {
+            Statement::ImportDeclaration(decl) => {
+                let module = format!("{MODULES}[{}]", module_id(&decl.source.value)?);
+                let mut names = vec![];
+                let mut patch = String::new();
+                for specifier in decl.specifiers.iter().flatten() {
+                    match specifier {
+                        ImportDeclarationSpecifier::ImportSpecifier(s) => {
+                            names.push(format!("{}: {}", export_key(&s.imported), s.local.name))
+                        }
+                        ImportDeclarationSpecifier::ImportDefaultSpecifier(s) => {
+                            names.push(format!("\"default\": {}", s.local.name))
+                        }
+                        ImportDeclarationSpecifier::ImportNamespaceSpecifier(s) => {
+                            patch.push_str(&format!("const {} = {module};", s.local.name))
+                        }
+                    }
+                }
+                if !names.is_empty() {
+                    patch.push_str(&format!("const {{ {} }} = {module};", names.join(", ")));
+                }
+                patches.push((decl.span, patch));
+            }
+            Statement::ExportNamedDeclaration(decl) => {
+                if let Some(declaration) = &decl.declaration {
+                    declaration.bound_names(&mut |ident| {
+                        exports.push(format!("get \"{0}\"() {{ return {0}; }}", ident.name))
+                    });
+                    let span = declaration.span();
+                    patches.push((
+                        decl.span,
+                        code[span.start as usize..span.end as usize].into(),
+                    ));
+                    continue;
+                }
+                let from = match &decl.source {
+                    Some(source) => Some(format!("{MODULES}[{}]", module_id(&source.value)?)),
+                    None => None,
+                };
+                for specifier in &decl.specifiers {
+                    let exported = export_key(&specifier.exported);
+                    let local = match &from {
+                        Some(module) => format!("{module}[{}]", export_key(&specifier.local)),
+                        None => specifier.local.to_string(),
+                    };
+                    exports.push(format!("get {exported}() {{ return {local}; }}"));
+                }
+                patches.push((decl.span, String::new()));
+            }
+            Statement::ExportDefaultDeclaration(decl) => {
+                let span = decl.declaration.span();
+                let text = &code[span.start as usize..span.end as usize];
+                let name = match &decl.declaration {
+                    ExportDefaultDeclarationKind::FunctionDeclaration(f) => f.id.as_ref(),
+                    ExportDefaultDeclarationKind::ClassDeclaration(c) => c.id.as_ref(),
+                    _ => None,
+                };
+                let patch = match name {
+                    Some(ident) => {
+                        exports.push(format!("get \"default\"() {{ return {}; }}", ident.name));
+                        text.to_string()
+                    }
+                    None => {
+                        exports.push(format!("get \"default\"() {{ return {DEFAULT}; }}"));
+                        format!("const {DEFAULT} = {text};")
+                    }
+                };
+                patches.push((decl.span, patch));
+            }
+            Statement::ExportAllDeclaration(decl) => {
+                let module = format!("{MODULES}[{}]", module_id(&decl.source.value)?);
+                match &decl.exported {
+                    Some(name) => {
+                        exports.push(format!("get {}() {{ return {module}; }}", export_key(name)))
+                    }
+                    None => exports.push(format!("...__edon_star({module})")),
+                }
+                patches.push((decl.span, String::new()));
+            }
+            _ => {}
+        }
+    }
+
+    let mut body = code.to_string();
+    patches.sort_by_key(|(span, _)| span.start);
+    for (span, patch) in patches.iter().rev() {
+        body.replace_range(span.start as usize..span.end as usize, patch);
+    }
+    body.push_str(&format!("\nreturn {{ {} }};", exports.join(", ")));
+    Ok(body)
+}
+
+fn export_key(name: &ModuleExportName) -> String {
+    let name = match name {
+        ModuleExportName::Identifier(ident) => ident.name.as_str(),
+        ModuleExportName::StringLiteral(literal) => literal.value.as_str(),
+    };
+    serde_json::to_string(name).unwrap_or_default()
+}
@@ -0,0 +1,263 @@
+use crate::{
+    compile::{compile, ModuleDependency},
+    config::CompilerOptions,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+/// environment variable overriding the cache directory
+pub const EDON_DIR: &str = "EDON_DIR";
+
+pub fn hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// metadata of a downloaded module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteMeta {
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub source_hash: String,
+    pub fetched_at: i64,
+}
+
+/// compiler output of a source, addressed by the hash of the source
+#[derive(Debug, Serialize, Deserialize)]
+struct CompiledMeta {
+    version: String,
+    filename: String,
+    deps: Vec<String>,
+    async_deps: Vec<String>,
+    specifiers: Vec<String>,
+    map: Option<String>,
+}
+
+/**
+# Module cache
+
+```text
+$EDON_DIR/deps/<scheme>/<host>/<sha256(url)>        raw source
+$EDON_DIR/deps/<scheme>/<host>/<sha256(url)>.json   RemoteMeta
+$EDON_DIR/gen/<sha256(options, source)>.js         compiled source
+$EDON_DIR/gen/<sha256(options, source)>.json       CompiledMeta
+```
+*/
+#[derive(Debug, Clone)]
+pub struct ModuleCache {
+    root: PathBuf,
+}
+
+impl Default for ModuleCache {
+    fn default() -> Self {
+        Self::new(Self::default_root())
+    }
+}
+
+impl ModuleCache {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// `$EDON_DIR`, then `$XDG_CACHE_HOME/edon`, then `$HOME/.cache/edon`
+    pub fn default_root() -> PathBuf {
+        if let Some(dir) = env::var_os(EDON_DIR) {
+            return PathBuf::from(dir);
+        }
+        if let Some(dir) = env::var_os("XDG_CACHE_HOME") {
+            return PathBuf::from(dir).join("edon");
+        }
+        if let Some(home) = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+            return PathBuf::from(home).join(".cache").join("edon");
+        }
+        env::temp_dir().join("edon")
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn remote_path(&self, url: &str) -> PathBuf {
+        let mut path = self.root.join("deps");
+        if let Ok(url) = Url::parse(url) {
+            path.push(url.scheme());
+            let host = match (url.host_str(), url.port()) {
+                (Some(host), Some(port)) => format!("{host}_PORT{port}"),
+                (Some(host), None) => host.to_string(),
+                _ => "_".to_string(),
+            };
+            path.push(host);
+        }
+        path.push(hash(url.as_bytes()));
+        path
+    }
+
+    fn gen_path(&self, key_hash: &str) -> PathBuf {
+        self.root.join("gen").join(key_hash)
+    }
+
+    /// raw source and metadata of a downloaded module
+    pub async fn get_source(&self, url: &str) -> Option<(String, RemoteMeta)> {
+        let path = self.remote_path(url);
+        let meta = tokio::fs::read(path.with_extension("json")).await.ok()?;
+        let meta: RemoteMeta = serde_json::from_slice(&meta).ok()?;
+        let source = tokio::fs::read_to_string(&path).await.ok()?;
+        if hash(source.as_bytes()) != meta.source_hash {
+            return None;
+        }
+        Some((source, meta))
+    }
+
+    pub async fn set_source(
+        &self,
+        url: &str,
+        source: &str,
+        headers: HashMap<String, String>,
+    ) -> anyhow::Result<RemoteMeta> {
+        let path = self.remote_path(url);
+        let meta = RemoteMeta {
+            url: url.to_string(),
+            headers,
+            source_hash: hash(source.as_bytes()),
+            fetched_at: chrono::Local::now().timestamp_millis(),
+        };
+        write(&path, source.as_bytes()).await?;
+        write(
+            &path.with_extension("json"),
+            &serde_json::to_vec_pretty(&meta)?,
+        )
+        .await?;
+        Ok(meta)
+    }
+
+    /// compile a source, reusing the cached output when neither the source nor the options changed
+    pub async fn compile(
+        &self,
+        filename: &str,
+        source: &str,
+        options: &CompilerOptions,
+    ) -> anyhow::Result<ModuleDependency> {
+        let mut key = serde_json::to_vec(options)?;
+        key.extend_from_slice(source.as_bytes());
+        let path = self.gen_path(&hash(&key));
+
+        if let Some(dep) = self.get_compiled(&path, filename).await {
+            return Ok(dep);
+        }
+
+        let dep = compile(filename, source, options)?;
+        let meta = CompiledMeta {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            filename: filename.to_string(),
+            deps: dep.deps.clone(),
+            async_deps: dep.async_deps.clone(),
+            specifiers: dep.specifiers.clone(),
+            map: dep.map.clone(),
+        };
+        write(&path.with_extension("js"), dep.source.as_bytes()).await?;
+        write(&path.with_extension("json"), &serde_json::to_vec(&meta)?).await?;
+        Ok(dep)
+    }
+
+    async fn get_compiled(&self, path: &Path, filename: &str) -> Option<ModuleDependency> {
+        let meta = tokio::fs::read(path.with_extension("json")).await.ok()?;
+        let meta: CompiledMeta = serde_json::from_slice(&meta).ok()?;
+        // the compiler output depends on the edon version and the file name
+        if meta.version != env!("CARGO_PKG_VERSION") || meta.filename != filename {
+            return None;
+        }
+        let source = tokio::fs::read_to_string(path.with_extension("js"))
+            .await
+            .ok()?;
+        Some(ModuleDependency {
+            deps: meta.deps,
+            async_deps: meta.async_deps,
+            specifiers: meta.specifiers,
+            source,
+            map: meta.map,
+            filename: filename.to_string(),
+            is_main: false,
+        })
+    }
+}
+
+/// write to a temporary file first so that readers never see a partial file
+async fn write(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    tokio::fs::write(&tmp, data).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(name: &str) -> ModuleCache {
+        let root = env::temp_dir().join(format!("edon-cache-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        ModuleCache::new(root)
+    }
+
+    #[test]
+    fn remote_paths_are_grouped_by_host() {
+        let cache = ModuleCache::new("/cache");
+        let url = "https://example.com:8443/mod.ts";
+        assert_eq!(
+            cache.remote_path(url),
+            Path::new("/cache/deps/https/example.com_PORT8443").join(hash(url.as_bytes()))
+        );
+    }
+
+    #[tokio::test]
+    async fn stores_remote_sources() {
+        let cache = cache("source");
+        let url = "https://example.com/mod.ts";
+        assert!(cache.get_source(url).await.is_none());
+
+        let headers = HashMap::from([("content-type".to_string(), "text/typescript".to_string())]);
+        cache.set_source(url, "export {}", headers).await.unwrap();
+        let (source, meta) = cache.get_source(url).await.unwrap();
+        assert_eq!(source, "export {}");
+        assert_eq!(meta.url, url);
+        assert_eq!(meta.headers["content-type"], "text/typescript");
+
+        // a source edited on disk no longer matches its hash
+        std::fs::write(cache.remote_path(url), "export const edited = 1").unwrap();
+        assert!(cache.get_source(url).await.is_none());
+        std::fs::remove_dir_all(cache.root()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reuses_compiled_output() {
+        let cache = cache("compile");
+        let options = CompilerOptions::default();
+        let source = "import './dep.ts'\nexport const a: number = 1";
+        let dep = cache.compile("/mod.ts", source, &options).await.unwrap();
+        assert_eq!(dep.deps, vec!["./dep.ts".to_string()]);
+
+        let output = std::fs::read_dir(cache.root().join("gen"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "js"))
+            .unwrap();
+        std::fs::write(&output, "// cached").unwrap();
+        let cached = cache.compile("/mod.ts", source, &options).await.unwrap();
+        assert_eq!(cached.source, "// cached");
+        assert_eq!(cached.deps, dep.deps);
+
+        // the output depends on the file name
+        let other = cache.compile("/other.ts", source, &options).await.unwrap();
+        assert_ne!(other.source, "// cached");
+        std::fs::remove_dir_all(cache.root()).unwrap();
+    }
+}
@@ -0,0 +1,30 @@
+use super::{current_dir, BundleArgs};
+use crate::{
+    bundle::bundle,
+    graph::{resolve, DependencyGraph},
+};
+use colored::Colorize;
+
+pub async fn exec(args: BundleArgs) -> anyhow::Result<i32> {
+    let current_dir = current_dir()?;
+    let entry = resolve(&args.entry, &current_dir);
+    let graph = DependencyGraph::new(args.loader.loader(Some(&args.entry))?)
+        .load(&args.entry, &current_dir)
+        .await?;
+
+    let code = bundle(&graph, &entry)?;
+    match &args.output {
+        Some(output) => {
+            tokio::fs::write(output, &code).await?;
+            eprintln!(
+                "{} {} ({} modules, {} bytes)",
+                "Bundle".green(),
+                output,
+                graph.iter().count(),
+                code.len()
+            );
+        }
+        None => println!("{code}"),
+    }
+    Ok(0)
+}
@@ -0,0 +1,28 @@
+use super::{current_dir, CacheArgs};
+use crate::graph::DependencyGraph;
+use colored::Colorize;
+
+pub async fn exec(args: CacheArgs) -> anyhow::Result<i32> {
+    let current_dir = current_dir()?;
+
+    let loader = args
+        .loader
+        .loader(args.entries.first().map(String::as_str))?;
+
+    for entry in &args.entries {
+        let graph = DependencyGraph::new(loader.clone())
+            .load(entry, &current_dir)
+            .await?;
+        println!(
+            "{} {} ({} modules)",
+            "Cached".green(),
+            entry,
+            graph.iter().count()
+        );
+    }
+    println!("{} {}", "Cache dir".green(), loader.cache.root().display());
+    if let Some(lockfile) = &loader.lockfile {
+        println!("{} {}", "Lockfile".green(), lockfile.path().display());
+    }
+    Ok(0)
+}
@@ -0,0 +1,31 @@
+use super::{current_dir, EvalArgs};
+use crate::{graph::DependencyGraph, runtime::Runtime};
+use std::path::Path;
+
+/// virtual module name of the evaluated code, relative imports resolve from the current directory
+const EVAL_MODULE: &str = "$edon$eval.ts";
+
+pub async fn exec(args: EvalArgs) -> anyhow::Result<i32> {
+    let current_dir = current_dir()?;
+    let filename = Path::new(&current_dir)
+        .join(EVAL_MODULE)
+        .to_string_lossy()
+        .to_string();
+
+    let code = if args.print {
+        format!("console.log({})", args.code())
+    } else {
+        args.code().clone()
+    };
+
+    let graph = DependencyGraph::new(args.loader.loader(None)?)
+        .load_source(&filename, &code)
+        .await?;
+    let permissions = args.permissions.permissions(graph.config())?;
+
+    Runtime::from(graph)
+        .with_args(args.args())
+        .with_permissions(permissions)
+        .run(&filename)
+        .await
+}
@@ -0,0 +1,73 @@
+use super::{current_dir, InfoArgs};
+use crate::graph::{resolve, DependencyGraph};
+use colored::Colorize;
+use std::collections::HashSet;
+
+pub async fn exec(args: InfoArgs) -> anyhow::Result<i32> {
+    let current_dir = current_dir()?;
+    let entry = resolve(&args.entry, &current_dir);
+    let graph = DependencyGraph::new(args.loader.loader(Some(&args.entry))?)
+        .load(&args.entry, &current_dir)
+        .await?;
+
+    let size = graph.iter().map(|(_, dep)| dep.source.len()).sum::<usize>();
+    println!("{} {}", "entry:".bold(), entry);
+    println!(
+        "{} {} unique",
+        "dependencies:".bold(),
+        graph.iter().count() - 1
+    );
+    println!("{} {}", "size:".bold(), human_size(size));
+    println!();
+
+    let mut seen = HashSet::new();
+    print_tree(&graph, &entry, "", true, true, &mut seen);
+    Ok(0)
+}
+
+fn print_tree(
+    graph: &DependencyGraph,
+    filename: &String,
+    prefix: &str,
+    is_last: bool,
+    is_root: bool,
+    seen: &mut HashSet<String>,
+) {
+    let branch = match (is_root, is_last) {
+        (true, _) => "",
+        (false, true) => "└── ",
+        (false, false) => "├── ",
+    };
+    let Some(dep) = graph.get(filename) else {
+        println!("{prefix}{branch}{} {}", filename, "(missing)".red());
+        return;
+    };
+    if !seen.insert(filename.clone()) {
+        println!("{prefix}{branch}{} {}", filename, "*".color("gray"));
+        return;
+    }
+    println!(
+        "{prefix}{branch}{} {}",
+        filename,
+        format!("({})", human_size(dep.source.len())).color("gray")
+    );
+
+    let prefix = match (is_root, is_last) {
+        (true, _) => prefix.to_string(),
+        (false, true) => format!("{prefix}    "),
+        (false, false) => format!("{prefix}│   "),
+    };
+    for (index, source) in dep.deps.iter().enumerate() {
+        let url = graph.resolve(source, &dep.filename);
+        let is_last = index + 1 == dep.deps.len();
+        print_tree(graph, &url, &prefix, is_last, false, seen);
+    }
+}
+
+fn human_size(size: usize) -> String {
+    match size {
+        0..=1023 => format!("{size}B"),
+        1024..=1048575 => format!("{:.2}KB", size as f64 / 1024.0),
+        _ => format!("{:.2}MB", size as f64 / 1048576.0),
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{
+    cache::ModuleCache,
+    config::{Config, LockConfig},
+    graph::{resolve, Loader},
+    import_map::ImportMap,
+    lockfile::{Lockfile, LOCKFILE_NAME},
+    permissions::{PermissionName, Permissions},
+    repl::Repl,
+};
+use clap::{Args, Parser, Subcommand};
+use std::{
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+
+mod bundle;
+mod cache;
+mod eval;
+mod info;
+mod run;
+mod task;
+mod test;
+
+/**
+# Edon command line
+*/
+#[derive(Debug, Parser)]
+#[command(name = "edon", version, about = "A TypeScript runtime built on V8")]
+#[command(arg_required_else_help = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run a TypeScript or JavaScript program
+    Run(RunArgs),
+    /// Evaluate a piece of TypeScript code
+    Eval(EvalArgs),
+    /// Start an interactive read-eval-print loop
+    Repl(ReplArgs),
+    /// Run test files
+    Test(TestArgs),
+    /// Bundle a module and its dependencies into a single JavaScript file
+    Bundle(BundleArgs),
+    /// Show the dependency graph of a module
+    Info(InfoArgs),
+    /// Download and compile the dependencies of a module without running it
+    Cache(CacheArgs),
+    /// Run a task defined in the configuration file, or list the tasks
+    Task(TaskArgs),
+}
+
+/// options shared by every command that loads a module graph
+#[derive(Debug, Args)]
+pub struct LoaderArgs {
+    /// Download remote modules again instead of using the module cache
+    #[arg(short, long)]
+    pub reload: bool,
+    /// Do not load remote modules
+    #[arg(long)]
+    pub no_remote: bool,
+    /// Only load remote modules from the module cache, never from the network
+    #[arg(long, conflicts_with = "reload")]
+    pub cached_only: bool,
+    /// Module cache directory, defaults to `$EDON_DIR` or `~/.cache/edon`
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Load the configuration from a file instead of looking up `edon.json` from the entry
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Do not look up a configuration file
+    #[arg(long, conflicts_with = "config")]
+    pub no_config: bool,
+    /// Resolve module specifiers through an import map file, overrides the configuration
+    #[arg(long, value_name = "FILE")]
+    pub import_map: Option<PathBuf>,
+    /// Check remote modules against a lockfile, defaults to `edon.lock` next to the
+    /// configuration file or the entry
+    #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true, default_missing_value = "")]
+    pub lock: Option<String>,
+    /// Update the lockfile with the current hashes of remote modules
+    #[arg(long)]
+    pub lock_write: bool,
+    /// Do not read or write a lockfile
+    #[arg(long, conflicts_with_all = ["lock", "lock_write"])]
+    pub no_lock: bool,
+}
+
+impl LoaderArgs {
+    /// the configuration file given on the command line or the nearest one above `dir`
+    pub fn config(&self, dir: &Path) -> anyhow::Result<Option<Config>> {
+        match &self.config {
+            _ if self.no_config => Ok(None),
+            Some(path) => Config::load(path).map(Some),
+            None => Config::discover(dir),
+        }
+    }
+
+    /// command line flags take precedence over the configuration, the lockfile is
+    /// used when requested or when one exists next to the configuration or the entry
+    pub fn loader(&self, entry: Option<&str>) -> anyhow::Result<Loader> {
+        let current_dir = current_dir()?;
+        let entry_dir = match entry {
+            Some(entry) if !entry.starts_with("http://") && !entry.starts_with("https://") => {
+                let entry = resolve(entry, &current_dir);
+                Path::new(&entry)
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| PathBuf::from(&current_dir))
+            }
+            _ => PathBuf::from(&current_dir),
+        };
+        let config = self.config(&entry_dir)?.unwrap_or_default();
+        let dir = config.dir().map(Path::to_path_buf).unwrap_or(entry_dir);
+        let default_lock = dir.join(LOCKFILE_NAME);
+
+        let lockfile = match (&self.lock, &config.lock) {
+            _ if self.no_lock => None,
+            (Some(path), _) if !path.is_empty() => Some(PathBuf::from(path)),
+            (Some(_), _) => Some(default_lock),
+            (None, Some(LockConfig::Path(path))) => Some(config.join(path)),
+            (None, Some(LockConfig::Enabled(true))) => Some(default_lock),
+            (None, Some(LockConfig::Enabled(false))) if !self.lock_write => None,
+            (None, _) if self.lock_write || default_lock.exists() => Some(default_lock),
+            (None, _) => None,
+        };
+
+        let import_map = match &self.import_map {
+            Some(path) => Some(ImportMap::from_file(path)?),
+            None => config.import_map()?,
+        };
+
+        Ok(Loader {
+            cache: match &self.cache_dir {
+                Some(dir) => ModuleCache::new(dir),
+                None => ModuleCache::default(),
+            },
+            reload: self.reload,
+            no_remote: self.no_remote,
+            cached_only: self.cached_only,
+            lockfile: match lockfile {
+                Some(path) => Some(Lockfile::open(path, self.lock_write)?),
+                None => None,
+            },
+            import_map,
+            config,
+        })
+    }
+}
+
+/// permissions of the script, granted in addition to the ones of the configuration
+#[derive(Debug, Args)]
+pub struct PermissionArgs {
+    /// Allow reading files, everywhere or under the given paths
+    #[arg(long, value_name = "PATH", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    pub allow_read: Option<Vec<String>>,
+    /// Allow writing files, everywhere or under the given paths
+    #[arg(long, value_name = "PATH", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    pub allow_write: Option<Vec<String>>,
+    /// Allow network access, to every host or to the given `host[:port]`
+    #[arg(long, value_name = "HOST", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    pub allow_net: Option<Vec<String>>,
+    /// Allow environment access, to every variable or to the given names
+    #[arg(long, value_name = "NAME", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    pub allow_env: Option<Vec<String>>,
+    /// Allow running subprocesses, any program or the given ones
+    #[arg(long, value_name = "PROGRAM", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    pub allow_run: Option<Vec<String>>,
+    /// Allow everything
+    #[arg(short = 'A', long)]
+    pub allow_all: bool,
+    /// Deny permissions that were not granted instead of asking on the terminal
+    #[arg(long)]
+    pub no_prompt: bool,
+}
+
+impl PermissionArgs {
+    pub fn permissions(&self, config: &Config) -> anyhow::Result<Permissions> {
+        if self.allow_all {
+            return Ok(Permissions::allow_all());
+        }
+        let mut permissions = Permissions::from_config(config);
+        permissions.prompt = !self.no_prompt && io::stdin().is_terminal();
+        let current_dir = PathBuf::from(current_dir()?);
+        for (name, resources) in [
+            (PermissionName::Read, &self.allow_read),
+            (PermissionName::Write, &self.allow_write),
+            (PermissionName::Net, &self.allow_net),
+            (PermissionName::Env, &self.allow_env),
+            (PermissionName::Run, &self.allow_run),
+        ] {
+            if let Some(resources) = resources {
+                permissions.grant(name, resources, &current_dir);
+            }
+        }
+        Ok(permissions)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ReplArgs {
+    #[command(flatten)]
+    pub permissions: PermissionArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub loader: LoaderArgs,
+    #[command(flatten)]
+    pub permissions: PermissionArgs,
+    /// Restart whenever a local module of the program changes, or anything under the
+    /// given extra paths
+    #[arg(long, value_name = "PATH", num_args = 0.., value_delimiter = ',', require_equals = true)]
+    pub watch: Option<Vec<String>>,
+    /// Replace changed modules in the running program, modules opt in with
+    /// `import.meta.hot.accept()`, other changes restart it like `--watch`
+    #[arg(long)]
+    pub hot: bool,
+    /// Entry module, a local path or an http(s) url, followed by the arguments
+    /// passed to the script as `Edon.args`
+    #[arg(
+        required = true,
+        num_args = 1..,
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        value_name = "ENTRY"
+    )]
+    pub script: Vec<String>,
+}
+
+impl RunArgs {
+    pub fn entry(&self) -> &String {
+        &self.script[0]
+    }
+    pub fn args(&self) -> Vec<String> {
+        self.script[1..].to_vec()
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct EvalArgs {
+    #[command(flatten)]
+    pub loader: LoaderArgs,
+    #[command(flatten)]
+    pub permissions: PermissionArgs,
+    /// Print the value of the code with `console.log`
+    #[arg(short, long)]
+    pub print: bool,
+    /// Code to evaluate, followed by the arguments passed to it as `Edon.args`
+    #[arg(
+        required = true,
+        num_args = 1..,
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        value_name = "CODE"
+    )]
+    pub script: Vec<String>,
+}
+
+impl EvalArgs {
+    pub fn code(&self) -> &String {
+        &self.script[0]
+    }
+    pub fn args(&self) -> Vec<String> {
+        self.script[1..].to_vec()
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct TestArgs {
+    #[command(flatten)]
+    pub loader: LoaderArgs,
+    #[command(flatten)]
+    pub permissions: PermissionArgs,
+    /// Test files or directories to search, defaults to the current directory
+    pub paths: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct BundleArgs {
+    #[command(flatten)]
+    pub loader: LoaderArgs,
+    /// Entry module
+    pub entry: String,
+    /// Output file, the bundle is written to stdout when omitted
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    #[command(flatten)]
+    pub loader: LoaderArgs,
+    /// Module to inspect
+    pub entry: String,
+}
+
+#[derive(Debug, Args)]
+pub struct CacheArgs {
+    #[command(flatten)]
+    pub loader: LoaderArgs,
+    /// Modules to load
+    #[arg(required = true)]
+    pub entries: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct TaskArgs {
+    /// Configuration file, defaults to the nearest `edon.json` from the current directory
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Task name followed by the arguments appended to its command
+    #[arg(
+        num_args = 0..,
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        value_name = "TASK"
+    )]
+    pub task: Vec<String>,
+}
+
+impl TaskArgs {
+    pub fn name(&self) -> Option<&String> {
+        self.task.first()
+    }
+    pub fn args(&self) -> Vec<String> {
+        self.task.iter().skip(1).cloned().collect()
+    }
+}
+
+impl Cli {
+    /// run the command, returns the process exit code
+    pub async fn exec(self) -> anyhow::Result<i32> {
+        match self.command {
+            Command::Run(args) => run::exec(args).await,
+            Command::Eval(args) => eval::exec(args).await,
+            Command::Repl(args) => {
+                let current_dir = current_dir()?;
+                let config = Config::discover(Path::new(&current_dir))?.unwrap_or_default();
+                let permissions = args.permissions.permissions(&config)?;
+                Repl::new(&current_dir, config, permissions)?.start().await
+            }
+            Command::Test(args) => test::exec(args).await,
+            Command::Bundle(args) => bundle::exec(args).await,
+            Command::Info(args) => info::exec(args).await,
+            Command::Cache(args) => cache::exec(args).await,
+            Command::Task(args) => task::exec(args).await,
+        }
+    }
+}
+
+pub fn current_dir() -> anyhow::Result<String> {
+    Ok(std::env::current_dir()?.to_string_lossy().to_string())
+}
@@ -0,0 +1,168 @@
+use super::{current_dir, RunArgs};
+use crate::{
+    graph::{resolve, DependencyGraph},
+    runtime::{HotReload, Runtime},
+};
+use colored::Colorize;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+
+/// changes arriving this soon after the first one restart the program only once
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+pub async fn exec(args: RunArgs) -> anyhow::Result<i32> {
+    match (&args.watch, args.hot) {
+        (None, false) => run(&args, None).await,
+        (paths, hot) => watch(&args, paths.as_deref().unwrap_or_default(), hot).await,
+    }
+}
+
+/// how a watched run talks to the watcher
+struct Watch {
+    /// every local module the graph loads
+    modules: mpsc::UnboundedSender<PathBuf>,
+    /// set with `--hot`
+    hot: Option<HotReload>,
+}
+
+async fn run(args: &RunArgs, watch: Option<Watch>) -> anyhow::Result<i32> {
+    let current_dir = current_dir()?;
+    let entry = args.entry();
+
+    let mut graph = DependencyGraph::new(args.loader.loader(Some(entry))?);
+    let mut hot = None;
+    if let Some(watch) = watch {
+        graph = graph.with_watch(watch.modules);
+        hot = watch.hot;
+    }
+    let graph = graph.load(entry, &current_dir).await?;
+    let permissions = args.permissions.permissions(graph.config())?;
+
+    let mut runtime = Runtime::from(graph)
+        .with_args(args.args())
+        .with_permissions(permissions);
+    if let Some(hot) = hot {
+        runtime = runtime.with_hot(hot);
+    }
+    runtime.run(&resolve(entry, &current_dir)).await
+}
+
+/// what `--watch` restarts on, the local modules of the graph and the extra paths
+struct WatchedPaths {
+    files: HashSet<PathBuf>,
+    /// modules are watched through their directory, editors often replace a file on save
+    dirs: HashSet<PathBuf>,
+    extra: Vec<PathBuf>,
+}
+
+impl WatchedPaths {
+    fn add_module(&mut self, watcher: &mut impl Watcher, file: PathBuf) {
+        if let Some(dir) = file.parent() {
+            if !self.dirs.contains(dir) && watcher.watch(dir, RecursiveMode::NonRecursive).is_ok() {
+                self.dirs.insert(dir.to_path_buf());
+            }
+        }
+        self.files.insert(file);
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        self.files.contains(path) || self.extra.iter().any(|extra| path.starts_with(extra))
+    }
+}
+
+/// run the program, and once a watched file changes tear the runtime down and run it again,
+/// with `hot` changed modules go to the running program first
+async fn watch(args: &RunArgs, extra: &[String], hot: bool) -> anyhow::Result<i32> {
+    let current_dir = current_dir()?;
+    let (sender, mut changes) = mpsc::unbounded_channel();
+    let mut watcher =
+        notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                let _ = sender.send(event.paths);
+            }
+            _ => {}
+        })?;
+
+    let mut watched = WatchedPaths {
+        files: HashSet::new(),
+        dirs: HashSet::new(),
+        extra: extra
+            .iter()
+            .map(|path| PathBuf::from(resolve(path, &current_dir)))
+            .collect(),
+    };
+    for path in &watched.extra {
+        watcher.watch(path, RecursiveMode::Recursive)?;
+    }
+
+    println!("{} Process started", "Watcher".green());
+    loop {
+        // only the modules of the current run count, the graph may have changed
+        watched.files.clear();
+        let (modules, mut loaded) = mpsc::unbounded_channel();
+        let (updates, updated) = mpsc::unbounded_channel();
+        let (declines, mut declined) = mpsc::unbounded_channel();
+        let watch = Watch {
+            modules,
+            hot: hot.then(|| HotReload::new(updated, declines)),
+        };
+        let mut program = Box::pin(run(args, Some(watch)));
+        let mut finished = false;
+        let changed = loop {
+            tokio::select! {
+                result = &mut program, if !finished => {
+                    finished = true;
+                    match result {
+                        Ok(code) => println!(
+                            "{} Process finished with exit code {code}, restarting on file change",
+                            "Watcher".green()
+                        ),
+                        Err(err) => {
+                            eprintln!("{err:?}");
+                            println!("{} Process failed, restarting on file change", "Watcher".green());
+                        }
+                    }
+                }
+                // dynamic imports keep adding modules while the program runs
+                Some(file) = loaded.recv() => watched.add_module(&mut watcher, file),
+                Some(paths) = changes.recv() => {
+                    let Some(path) = paths.into_iter().find(|path| watched.matches(path)) else {
+                        continue;
+                    };
+                    if !hot || finished {
+                        break path;
+                    }
+                    // let the burst of a save settle before the modules are replaced
+                    tokio::time::sleep(DEBOUNCE).await;
+                    let mut changed = HashSet::from([path]);
+                    while let Ok(paths) = changes.try_recv() {
+                        changed.extend(paths.into_iter().filter(|path| watched.matches(path)));
+                    }
+                    // the extra paths are no modules, they always restart
+                    if let Some(path) = changed.iter().find(|path| !watched.files.contains(*path)) {
+                        break path.clone();
+                    }
+                    for path in changed {
+                        let _ = updates.send(path);
+                    }
+                }
+                Some(path) = declined.recv(), if hot && !finished => break path,
+            }
+        };
+        // the runtime and its isolate go away with the program future
+        drop(program);
+
+        tokio::time::sleep(DEBOUNCE).await;
+        while changes.try_recv().is_ok() {}
+        println!(
+            "{} File change detected: {}, restarting",
+            "Watcher".green(),
+            changed.display()
+        );
+    }
+}
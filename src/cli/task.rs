@@ -0,0 +1,179 @@
+use super::{current_dir, TaskArgs};
+use crate::config::Config;
+use anyhow::{anyhow, bail};
+use colored::Colorize;
+use std::path::Path;
+use tokio::process::Command;
+
+pub async fn exec(args: TaskArgs) -> anyhow::Result<i32> {
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::discover(Path::new(&current_dir()?))?.ok_or(anyhow!(
+            "no configuration file found, create an `edon.json` first"
+        ))?,
+    };
+    let root = config.dir().ok_or(anyhow!("invalid configuration path"))?;
+
+    let Some(name) = args.name() else {
+        list(&config);
+        return Ok(0);
+    };
+
+    let mut order = vec![];
+    sort(&config, name, &mut order, &mut vec![])?;
+
+    let extra = args.args();
+    for task in &order {
+        let mut command = config.tasks[task].command().to_string();
+        // only the requested task receives the extra arguments
+        if task == name {
+            for arg in &extra {
+                command.push(' ');
+                command.push_str(&quote(arg));
+            }
+        }
+        eprintln!("{} {} {}", "Task".green(), task, command);
+        let code = run(&command, root).await?;
+        if code != 0 {
+            return Ok(code);
+        }
+    }
+    Ok(0)
+}
+
+fn list(config: &Config) {
+    if config.tasks.is_empty() {
+        println!("No tasks in {}", config.path.display());
+        return;
+    }
+    println!("Available tasks:");
+    for (name, task) in &config.tasks {
+        println!("- {}", name.cyan());
+        if let Some(description) = task.description() {
+            println!("    {description}");
+        }
+        println!("    {}", task.command().color("gray"));
+    }
+}
+
+/// dependencies come before the tasks depending on them, each task runs once
+fn sort(
+    config: &Config,
+    name: &String,
+    order: &mut Vec<String>,
+    stack: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    if order.contains(name) {
+        return Ok(());
+    }
+    if stack.contains(name) {
+        stack.push(name.clone());
+        bail!("circular task dependency: {}", stack.join(" -> "));
+    }
+    let Some(task) = config.tasks.get(name) else {
+        match stack.last() {
+            Some(parent) => bail!("task `{name}` required by `{parent}` not found"),
+            None => {
+                let names = config.tasks.keys().cloned().collect::<Vec<_>>();
+                bail!("task `{name}` not found, available: {}", names.join(", "))
+            }
+        }
+    };
+
+    stack.push(name.clone());
+    for dep in task.dependencies() {
+        sort(config, dep, order, stack)?;
+    }
+    stack.pop();
+
+    order.push(name.clone());
+    Ok(())
+}
+
+async fn run(command: &str, cwd: &Path) -> anyhow::Result<i32> {
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.arg("/C").arg(command);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.arg("-c").arg(command);
+        process
+    };
+    let status = process
+        .current_dir(cwd)
+        .status()
+        .await
+        .map_err(|err| anyhow!("spawn `{command}` failure: {err}"))?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// quote an argument for the shell running the task
+fn quote(arg: &str) -> String {
+    if cfg!(windows) {
+        return format!("\"{}\"", arg.replace('"', "\\\""));
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(tasks: serde_json::Value) -> Config {
+        serde_json::from_value(serde_json::json!({ "tasks": tasks })).unwrap()
+    }
+
+    fn order(config: &Config, name: &str) -> anyhow::Result<Vec<String>> {
+        let mut order = vec![];
+        sort(config, &name.to_string(), &mut order, &mut vec![])?;
+        Ok(order)
+    }
+
+    #[test]
+    fn runs_dependencies_first_and_once() {
+        let config = config(serde_json::json!({
+            "build": { "command": "edon bundle main.ts dist/main.js", "dependencies": ["check", "fmt"] },
+            "check": { "command": "edon test", "dependencies": ["fmt"] },
+            "fmt": "edon fmt",
+        }));
+        assert_eq!(order(&config, "build").unwrap(), ["fmt", "check", "build"]);
+        assert_eq!(order(&config, "fmt").unwrap(), ["fmt"]);
+    }
+
+    #[test]
+    fn rejects_circular_dependencies() {
+        let config = config(serde_json::json!({
+            "a": { "command": "true", "dependencies": ["b"] },
+            "b": { "command": "true", "dependencies": ["a"] },
+        }));
+        let err = order(&config, "a").unwrap_err();
+        assert_eq!(err.to_string(), "circular task dependency: a -> b -> a");
+    }
+
+    #[test]
+    fn reports_missing_tasks() {
+        let config = config(serde_json::json!({
+            "start": { "command": "edon run main.ts", "dependencies": ["setup"] },
+            "test": "edon test",
+        }));
+        let err = order(&config, "deploy").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "task `deploy` not found, available: start, test"
+        );
+        let err = order(&config, "start").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "task `setup` required by `start` not found"
+        );
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn quotes_arguments_for_the_shell() {
+        assert_eq!(quote("main.ts"), "'main.ts'");
+        assert_eq!(quote("a b"), "'a b'");
+        assert_eq!(quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,101 @@
+use super::{current_dir, TestArgs};
+use crate::{
+    graph::{resolve, DependencyGraph},
+    runtime::Runtime,
+};
+use anyhow::bail;
+use colored::Colorize;
+use std::{fs, path::Path};
+
+const TEST_SUFFIXES: [&str; 8] = [
+    "_test.ts",
+    ".test.ts",
+    "_test.tsx",
+    ".test.tsx",
+    "_test.js",
+    ".test.js",
+    "_test.mjs",
+    ".test.mjs",
+];
+
+pub async fn exec(args: TestArgs) -> anyhow::Result<i32> {
+    let current_dir = current_dir()?;
+    let paths = if args.paths.is_empty() {
+        vec![current_dir.clone()]
+    } else {
+        args.paths
+    };
+
+    let mut files = vec![];
+    for path in &paths {
+        collect(Path::new(&resolve(path, &current_dir)), &mut files)?;
+    }
+    files.sort();
+
+    println!("running {} test files", files.len());
+    let mut failed = vec![];
+    for file in &files {
+        let result = match DependencyGraph::new(args.loader.loader(None)?)
+            .load(file, &current_dir)
+            .await
+        {
+            Ok(graph) => match args.permissions.permissions(graph.config()) {
+                Ok(permissions) => {
+                    Runtime::from(graph)
+                        .with_permissions(permissions)
+                        .run(file)
+                        .await
+                }
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(0) => println!("{file} ... {}", "ok".green()),
+            Ok(code) => {
+                println!("{file} ... {} (exit code {code})", "FAILED".red());
+                failed.push(file);
+            }
+            Err(err) => {
+                println!("{file} ... {}", "FAILED".red());
+                eprintln!("{err:?}");
+                failed.push(file);
+            }
+        }
+    }
+
+    let passed = files.len() - failed.len();
+    if failed.is_empty() {
+        println!("\n{} | {passed} passed; 0 failed", "ok".green());
+        return Ok(0);
+    }
+    println!(
+        "\n{} | {passed} passed; {} failed",
+        "FAILED".red(),
+        failed.len()
+    );
+    bail!("{} test files failed", failed.len())
+}
+
+fn collect(path: &Path, files: &mut Vec<String>) -> anyhow::Result<()> {
+    if path.is_file() {
+        files.push(path.to_string_lossy().to_string());
+        return Ok(());
+    }
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if name.starts_with('.') || name == "node_modules" || name == "target" {
+            continue;
+        }
+        if path.is_dir() {
+            collect(&path, files)?;
+        } else if TEST_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)) {
+            files.push(path.to_string_lossy().to_string());
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,5 @@
 use crate::{
     compile_oxc,
-    graph::resolve,
     runtime::{ModuleInstance, Runtime},
 };
 use anyhow::anyhow;
@@ -36,7 +35,7 @@ impl ModuleDependency {
         let state = graph_rc.borrow();
         let graph = state.table.borrow();
         for url in self.deps.iter() {
-            let url = resolve(url, &self.filename);
+            let url = graph.resolve(url, &self.filename);
             let dep = graph.get(&url).unwrap();
             dep.initialize(isolate)?
         }
@@ -109,7 +108,7 @@ impl ModuleDependency {
         for url in &self.deps {
             let graph = graph_rc.borrow();
             let table = graph.table.borrow();
-            let url = resolve(&url, &self.filename);
+            let url = table.resolve(url, &self.filename);
             let dep = table
                 .get(&url)
                 .ok_or(anyhow!("table get failure `{url}`"))?;
@@ -135,6 +134,11 @@ impl ModuleDependency {
         if result.is_promise() {
             let promise = v8::Local::<v8::Promise>::try_from(result).unwrap();
             if let v8::PromiseState::Rejected = promise.state() {
+                // reported through the returned error instead
+                Runtime::state(tc_scope)
+                    .borrow_mut()
+                    .rejections
+                    .remove(&promise.get_identity_hash());
                 let result = promise.result(tc_scope);
                 let stack = tc_scope.stack_trace();
                 return Err(anyhow!(
@@ -1,4 +1,4 @@
-use std::path::Path;
+use std::{borrow::Cow, path::Path};
 
 use anyhow::bail;
 use oxc_allocator::Allocator;
@@ -6,7 +6,10 @@ use oxc_ast::{AstKind, Trivias, Visit};
 use oxc_codegen::{Codegen, CodegenOptions};
 use oxc_transformer::{TransformOptions, Transformer};
 
-use crate::compile::ModuleDependency;
+use crate::{
+    compile::ModuleDependency,
+    config::{CompilerOptions, JsxRuntime},
+};
 
 #[derive(Debug, Default)]
 struct ImportParser {
@@ -61,7 +64,32 @@ impl<'a> oxc_ast::Visit<'a> for ImportParser {
     }
 }
 
-pub fn compile(file_name: &str, content: &str) -> anyhow::Result<ModuleDependency> {
+fn transform_options(options: &CompilerOptions) -> TransformOptions {
+    let mut transform_options = TransformOptions::default();
+    // the runtime enum is not exported by oxc, it can only be deserialized
+    let runtime = match options.jsx {
+        JsxRuntime::Classic => "classic",
+        JsxRuntime::Automatic | JsxRuntime::AutomaticDev => "automatic",
+    };
+    transform_options.react =
+        serde_json::from_value(serde_json::json!({ "runtime": runtime })).unwrap_or_default();
+    let react = &mut transform_options.react;
+    react.development = options.jsx == JsxRuntime::AutomaticDev;
+    react.pragma = Cow::Owned(options.jsx_factory.clone());
+    react.pragma_frag = Cow::Owned(options.jsx_fragment_factory.clone());
+    react.import_source = Cow::Owned(options.jsx_import_source.clone());
+
+    let typescript = &mut transform_options.typescript;
+    typescript.jsx_pragma = Cow::Owned(options.jsx_factory.clone());
+    typescript.jsx_pragma_frag = Cow::Owned(options.jsx_fragment_factory.clone());
+    transform_options
+}
+
+pub fn compile(
+    file_name: &str,
+    content: &str,
+    options: &CompilerOptions,
+) -> anyhow::Result<ModuleDependency> {
     let allo = Allocator::default();
     let source_type = oxc_span::SourceType::from_path(file_name).unwrap();
 
@@ -79,7 +107,7 @@ pub fn compile(file_name: &str, content: &str) -> anyhow::Result<ModuleDependenc
     import_parser.visit_program(&ret.program);
 
     let program = allo.alloc(ret.program);
-    let transform_options = TransformOptions::default();
+    let transform_options = transform_options(options);
 
     let trivias = Trivias::default();
     let transformer = Transformer::new(
@@ -0,0 +1,332 @@
+use crate::import_map::ImportMap;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+/// file names looked up in every directory from the entry up to the root
+pub const CONFIG_NAMES: [&str; 2] = ["edon.json", "edon.jsonc"];
+
+/**
+# Project configuration
+
+`edon.json` or `edon.jsonc` (json with comments and trailing commas), relative
+paths are resolved against the directory of the config file
+
+```jsonc
+{
+  "compilerOptions": { "jsx": "react", "jsxFactory": "h" },
+  "importMap": "./import_map.json",
+  "lock": "./edon.lock",
+  "permissions": { "read": ["./data"], "net": true },
+  "tasks": { "build": "edon bundle main.ts dist/main.js" },
+  "v8": { "threads": 4, "flags": ["--max-old-space-size=4096"] }
+}
+```
+*/
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    /// the config file itself, empty when no config file was found
+    #[serde(skip)]
+    pub path: PathBuf,
+    pub compiler_options: CompilerOptions,
+    /// path of an import map file
+    pub import_map: Option<String>,
+    /// inline import map, ignored when `importMap` is set
+    pub imports: Option<serde_json::Value>,
+    pub scopes: Option<serde_json::Value>,
+    pub lock: Option<LockConfig>,
+    pub permissions: PermissionsConfig,
+    pub tasks: BTreeMap<String, TaskConfig>,
+    pub v8: V8Options,
+}
+
+/// options of the TypeScript and JSX transform, named after `tsconfig.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CompilerOptions {
+    pub jsx: JsxRuntime,
+    pub jsx_factory: String,
+    pub jsx_fragment_factory: String,
+    pub jsx_import_source: String,
+}
+
+impl Default for CompilerOptions {
+    fn default() -> Self {
+        Self {
+            jsx: JsxRuntime::default(),
+            jsx_factory: "React.createElement".to_string(),
+            jsx_fragment_factory: "React.Fragment".to_string(),
+            jsx_import_source: "react".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum JsxRuntime {
+    /// `jsxFactory` calls
+    #[serde(rename = "react")]
+    Classic,
+    /// imports from `jsxImportSource`
+    #[default]
+    #[serde(rename = "react-jsx")]
+    Automatic,
+    #[serde(rename = "react-jsxdev")]
+    AutomaticDev,
+}
+
+/// `true` for `edon.lock` next to the config file, `false` to disable, or a path
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum LockConfig {
+    Enabled(bool),
+    Path(String),
+}
+
+/// permissions granted without command line flags
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PermissionsConfig {
+    pub all: bool,
+    pub read: PermissionConfig,
+    pub write: PermissionConfig,
+    pub net: PermissionConfig,
+    pub env: PermissionConfig,
+    pub run: PermissionConfig,
+}
+
+/// `true` grants everything, a list grants the listed paths, hosts or names
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PermissionConfig {
+    All(bool),
+    List(Vec<String>),
+}
+
+impl Default for PermissionConfig {
+    fn default() -> Self {
+        Self::All(false)
+    }
+}
+
+/// a command line, or a command with the tasks to run before it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TaskConfig {
+    Command(String),
+    Detailed(TaskDefinition),
+}
+
+impl TaskConfig {
+    pub fn command(&self) -> &str {
+        match self {
+            Self::Command(command) => command,
+            Self::Detailed(task) => &task.command,
+        }
+    }
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Self::Command(_) => None,
+            Self::Detailed(task) => task.description.as_deref(),
+        }
+    }
+    pub fn dependencies(&self) -> &[String] {
+        match self {
+            Self::Command(_) => &[],
+            Self::Detailed(task) => &task.dependencies,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaskDefinition {
+    pub command: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+}
+
+/// options of the V8 platform, only the first runtime of a process applies them
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct V8Options {
+    /// worker threads of the platform
+    pub threads: u32,
+    pub flags: Vec<String>,
+}
+
+impl Default for V8Options {
+    fn default() -> Self {
+        Self {
+            threads: 2,
+            flags: vec![],
+        }
+    }
+}
+
+impl Config {
+    /// find the nearest config file from `dir` upwards
+    pub fn discover(dir: &Path) -> anyhow::Result<Option<Self>> {
+        for dir in dir.ancestors() {
+            for name in CONFIG_NAMES {
+                let path = dir.join(name);
+                if path.is_file() {
+                    return Self::load(&path).map(Some);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let path = fs::canonicalize(path)
+            .map_err(|err| anyhow!("read config file `{}` failure: {err}", path.display()))?;
+        let text = fs::read_to_string(&path)?;
+        let mut config: Config = serde_json::from_str(&strip_jsonc(&text))
+            .map_err(|err| anyhow!("invalid config file `{}`: {err}", path.display()))?;
+        config.path = path;
+        Ok(config)
+    }
+
+    /// directory of the config file, the project root
+    pub fn dir(&self) -> Option<&Path> {
+        self.path.parent()
+    }
+
+    /// resolve a path of the config file against its directory
+    pub fn join(&self, path: &str) -> PathBuf {
+        match self.dir() {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        }
+    }
+
+    pub fn import_map(&self) -> anyhow::Result<Option<ImportMap>> {
+        if let Some(path) = &self.import_map {
+            return ImportMap::from_file(&self.join(path)).map(Some);
+        }
+        if self.imports.is_none() && self.scopes.is_none() {
+            return Ok(None);
+        }
+        let base = Url::from_file_path(&self.path)
+            .map_err(|_| anyhow!("invalid config path `{}`", self.path.display()))?;
+        let mut value = serde_json::Map::new();
+        if let Some(imports) = &self.imports {
+            value.insert("imports".to_string(), imports.clone());
+        }
+        if let Some(scopes) = &self.scopes {
+            value.insert("scopes".to_string(), scopes.clone());
+        }
+        ImportMap::from_value(&serde_json::Value::Object(value), &base)
+            .map_err(|err| anyhow!("invalid import map in `{}`: {err}", self.path.display()))
+            .map(Some)
+    }
+}
+
+/// drop comments and trailing commas, keeping string literals untouched
+fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+                out.push(' ');
+            }
+            // comments are gone already, so a trailing comma is followed by whitespace only
+            '}' | ']' => {
+                let len = out.trim_end().len();
+                if out[..len].ends_with(',') {
+                    out.remove(len - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> serde_json::Value {
+        serde_json::from_str(&strip_jsonc(text)).unwrap()
+    }
+
+    #[test]
+    fn strips_comments() {
+        let value = parse(
+            r#"{
+                // line comment
+                "lock": "./edon.lock", /* block
+                comment */ "tasks": { "start": "edon run main.ts" /* inline */ }
+            }"#,
+        );
+        assert_eq!(
+            value,
+            serde_json::json!({ "lock": "./edon.lock", "tasks": { "start": "edon run main.ts" } })
+        );
+    }
+
+    #[test]
+    fn strips_trailing_commas() {
+        let value = parse(
+            r#"{
+                "permissions": { "read": ["./data", "./static",], },
+                "v8": { "flags": [
+                    "--max-old-space-size=4096", // comment after the last item
+                ] },
+            }"#,
+        );
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "permissions": { "read": ["./data", "./static"] },
+                "v8": { "flags": ["--max-old-space-size=4096"] }
+            })
+        );
+    }
+
+    #[test]
+    fn keeps_string_literals() {
+        let value = parse(r#"{ "url": "https://example.com/a,}", "quote": "say \"//\" ,]" }"#);
+        assert_eq!(
+            value,
+            serde_json::json!({ "url": "https://example.com/a,}", "quote": "say \"//\" ,]" })
+        );
+    }
+}
@@ -1,11 +1,24 @@
-use crate::compile::{compile, ModuleDependency};
+use crate::{
+    cache::ModuleCache,
+    compile::{compile, ModuleDependency},
+    config::Config,
+    import_map::ImportMap,
+    lockfile::Lockfile,
+};
+use anyhow::{bail, Context};
 use chrono::Local;
 use colored::Colorize;
 use queues::*;
 use regex::Regex;
 use relative_path::RelativePath;
 use reqwest::{self};
-use std::{collections::HashMap, fs};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    future::Future,
+    path::PathBuf,
+};
+use tokio::sync::mpsc;
 use url::Url;
 
 pub fn resolve(filename: &str, base: &String) -> String {
@@ -23,7 +36,11 @@ pub fn resolve(filename: &str, base: &String) -> String {
     }
     let relative_path = RelativePath::new(filename);
     let base = RelativePath::new(base);
-    let full_path = if fs::metadata(base.to_string()).unwrap().is_file() {
+    // a base that does not exist on disk is a virtual module, e.g. `edon eval`
+    let is_file = fs::metadata(base.to_string())
+        .map(|meta| meta.is_file())
+        .unwrap_or(true);
+    let full_path = if is_file {
         base.parent().unwrap().join_normalized(relative_path)
     } else {
         base.join_normalized(relative_path)
@@ -31,57 +48,298 @@ pub fn resolve(filename: &str, base: &String) -> String {
     format!("/{full_path}")
 }
 
-pub async fn load(filename: &String) -> anyhow::Result<ModuleDependency> {
-    let https = Regex::new(r#"https?://"#).unwrap();
-    if https.is_match(&filename) {
+/// how modules are fetched and compiled
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+    pub cache: ModuleCache,
+    /// ignore cached remote modules and download them again
+    pub reload: bool,
+    /// refuse to load any remote module
+    pub no_remote: bool,
+    /// only load remote modules that are already in the module cache
+    pub cached_only: bool,
+    pub lockfile: Option<Lockfile>,
+    pub import_map: Option<ImportMap>,
+    /// project configuration, the default one when no config file was found
+    pub config: Config,
+}
+
+impl Loader {
+    /// resolve a specifier through the import map, then relative to `base`
+    pub fn resolve(&self, source: &str, base: &String) -> String {
+        let mapped = self
+            .import_map
+            .as_ref()
+            .and_then(|map| map.resolve(source, base));
+        mapped.unwrap_or_else(|| resolve(source, base))
+    }
+    pub async fn load(&self, filename: &String) -> anyhow::Result<ModuleDependency> {
+        let https = Regex::new(r#"https?://"#).unwrap();
+        if https.is_match(filename) {
+            return self.load_remote(filename).await;
+        };
+        let data = tokio::fs::read(&filename).await?;
+        compile(
+            filename,
+            &String::from_utf8_lossy(&data),
+            &self.config.compiler_options,
+        )
+    }
+
+    async fn load_remote(&self, url: &String) -> anyhow::Result<ModuleDependency> {
+        if self.no_remote {
+            bail!("remote module `{url}` is not allowed with `--no-remote`");
+        }
+        if !self.reload || self.cached_only {
+            if let Some((source, _)) = self.cache.get_source(url).await {
+                self.verify(url, &source)?;
+                return self
+                    .cache
+                    .compile(url, &source, &self.config.compiler_options)
+                    .await;
+            }
+        }
+        if self.cached_only {
+            bail!(
+                "remote module `{url}` is not in the module cache `{}`, run `edon cache` first or remove `--cached-only`",
+                self.cache.root().display()
+            );
+        }
+
         let now = Local::now().timestamp_millis();
-        let data = reqwest::get(filename).await?;
-        let data = data.text().await?;
-        let data = compile(filename, &data)?;
-        println!(
-            "{} {}",
-            "Downland ".green(),
-            format!(
-                "{} cost {}ms",
-                filename,
-                Local::now().timestamp_millis() - now
-            ),
+        let response = reqwest::get(url).await?.error_for_status()?;
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(key, value)| {
+                let value = String::from_utf8_lossy(value.as_bytes()).to_string();
+                (key.to_string(), value)
+            })
+            .collect::<HashMap<_, _>>();
+        let source = response.text().await?;
+        self.verify(url, &source)?;
+        self.cache.set_source(url, &source, headers).await?;
+        eprintln!(
+            "{} {} cost {}ms",
+            "Download".green(),
+            url,
+            Local::now().timestamp_millis() - now
         );
-        return Ok(data);
-    };
-    let data = tokio::fs::read(&filename).await?;
-    compile(filename, &String::from_utf8_lossy(&data).to_string())
+        self.cache
+            .compile(url, &source, &self.config.compiler_options)
+            .await
+    }
+
+    fn verify(&self, url: &str, source: &str) -> anyhow::Result<()> {
+        match &self.lockfile {
+            Some(lockfile) => lockfile.check(url, source),
+            None => Ok(()),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct DependencyGraph(HashMap<String, ModuleDependency>);
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    modules: HashMap<String, ModuleDependency>,
+    loader: Loader,
+    /// receives every local file the graph loads, including dynamic imports, for `--watch`
+    watch: Option<mpsc::UnboundedSender<PathBuf>>,
+}
 
 impl DependencyGraph {
-    pub async fn from(entry: &String, base: &String) -> anyhow::Result<Self> {
-        let mut dep = DependencyGraph(HashMap::new());
-        dep.append(entry, base).await?;
-        Ok(dep)
-    }
-    pub async fn append(&mut self, source: &String, base: &String) -> anyhow::Result<()> {
-        //
-        let mut preload = queue![(source.clone(), base.clone())];
-
-        let table = &mut self.0;
-
-        while let Ok((source, base)) = preload.remove() {
-            let url = resolve(&source, &base);
-            let dep = load(&url).await?;
-            let base = dep.filename.clone();
-            for source in &dep.deps {
-                if table.get(source).is_none() {
-                    preload.add((source.clone(), base.clone())).unwrap();
+    pub fn new(loader: Loader) -> Self {
+        Self {
+            modules: HashMap::new(),
+            loader,
+            watch: None,
+        }
+    }
+    /// report local files to `sender` before they are loaded, so that a file that fails
+    /// to load is watched as well
+    pub fn with_watch(mut self, sender: mpsc::UnboundedSender<PathBuf>) -> Self {
+        self.watch = Some(sender);
+        self
+    }
+    /// load `entry` and its dependencies into the graph
+    pub async fn load(mut self, entry: &str, base: &str) -> anyhow::Result<Self> {
+        self.append(entry, base).await?;
+        Ok(self)
+    }
+    /// load an in-memory entry module instead of a file
+    pub async fn load_source(mut self, filename: &str, source: &str) -> anyhow::Result<Self> {
+        let dep = compile(filename, source, &self.loader.config.compiler_options)?;
+        let base = dep.filename.clone();
+        let deps = dep.deps.clone();
+        self.modules.insert(base.clone(), dep);
+        for source in &deps {
+            self.append(source, &base).await?;
+        }
+        Ok(self)
+    }
+    /// resolve a specifier through the import map, then relative to `base`
+    pub fn resolve(&self, source: &str, base: &String) -> String {
+        self.loader.resolve(source, base)
+    }
+    pub async fn append(&mut self, source: &str, base: &str) -> anyhow::Result<()> {
+        let modules = self.fetch(source, base).await?;
+        self.extend(modules);
+        Ok(())
+    }
+    /// load `source` and those of its dependencies that are not in the graph yet,
+    /// the future does not borrow the graph so it can run beside the runtime
+    pub fn fetch(
+        &self,
+        source: &str,
+        base: &str,
+    ) -> impl Future<Output = anyhow::Result<Vec<ModuleDependency>>> + 'static {
+        let loader = self.loader.clone();
+        let watch = self.watch.clone();
+        let mut known = self.modules.keys().cloned().collect::<HashSet<_>>();
+        let mut preload = queue![(source.to_string(), base.to_string())];
+
+        async move {
+            let mut modules = vec![];
+            while let Ok((source, base)) = preload.remove() {
+                let url = loader.resolve(&source, &base);
+                if !known.insert(url.clone()) {
+                    continue;
                 }
+                let remote = url.starts_with("http://") || url.starts_with("https://");
+                if let (Some(watch), false) = (&watch, remote) {
+                    let _ = watch.send(PathBuf::from(&url));
+                }
+                let dep = loader
+                    .load(&url)
+                    .await
+                    .with_context(|| format!("cannot load `{source}` imported from `{base}`"))?;
+                let base = dep.filename.clone();
+                for source in &dep.deps {
+                    if !known.contains(&loader.resolve(source, &base)) {
+                        preload.add((source.clone(), base.clone())).unwrap();
+                    }
+                }
+                modules.push(dep);
             }
-            table.insert(dep.filename.clone(), dep);
+            Ok(modules)
         }
-        Ok(())
+    }
+    /// add modules loaded by [`DependencyGraph::fetch`], keeping those already in the graph
+    pub fn extend(&mut self, modules: Vec<ModuleDependency>) {
+        for dep in modules {
+            self.modules.entry(dep.filename.clone()).or_insert(dep);
+        }
+    }
+    pub fn config(&self) -> &Config {
+        &self.loader.config
     }
     pub fn get(&self, source: &String) -> Option<&ModuleDependency> {
-        self.0.get(source)
+        self.modules.get(source)
+    }
+    /// swap in a recompiled module, returns the previous version
+    pub fn replace(&mut self, dep: ModuleDependency) -> Option<ModuleDependency> {
+        self.modules.insert(dep.filename.clone(), dep)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ModuleDependency)> {
+        self.modules.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("edon-graph-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn loader(dir: &Path) -> Loader {
+        Loader {
+            cache: ModuleCache::new(dir.join("cache")),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn no_remote_refuses_remote_modules() {
+        let dir = temp_dir("no-remote");
+        let loader = Loader {
+            no_remote: true,
+            ..loader(&dir)
+        };
+        let err = loader
+            .load(&"https://example.com/mod.ts".to_string())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("--no-remote"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn cached_only_loads_from_the_cache() {
+        let dir = temp_dir("cached-only");
+        let loader = Loader {
+            cached_only: true,
+            ..loader(&dir)
+        };
+        let url = "https://example.com/mod.ts".to_string();
+        let err = loader.load(&url).await.unwrap_err();
+        assert!(err.to_string().contains("is not in the module cache"));
+
+        loader
+            .cache
+            .set_source(&url, "export const a = 1", HashMap::new())
+            .await
+            .unwrap();
+        let dep = loader.load(&url).await.unwrap();
+        assert_eq!(dep.filename, url);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn fetches_only_missing_modules() {
+        let dir = temp_dir("fetch");
+        fs::write(dir.join("main.ts"), "import './a.ts'").unwrap();
+        fs::write(dir.join("a.ts"), "import './b.ts'").unwrap();
+        fs::write(dir.join("b.ts"), "export {}").unwrap();
+        fs::write(dir.join("lazy.ts"), "import './b.ts'\nimport './c.ts'").unwrap();
+        fs::write(dir.join("c.ts"), "export {}").unwrap();
+        let base = dir.to_string_lossy().to_string();
+
+        let graph = DependencyGraph::new(loader(&dir))
+            .load("./main.ts", &base)
+            .await
+            .unwrap();
+        assert_eq!(graph.iter().count(), 3);
+
+        let main = dir.join("main.ts").to_string_lossy().to_string();
+        let modules = graph.fetch("./lazy.ts", &main).await.unwrap();
+        let mut names = modules
+            .iter()
+            .map(|dep| Path::new(&dep.filename).file_name().unwrap().to_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["c.ts", "lazy.ts"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn load_errors_name_the_importer() {
+        let dir = temp_dir("missing");
+        fs::write(dir.join("main.ts"), "import './missing.ts'").unwrap();
+        let base = dir.to_string_lossy().to_string();
+        let err = DependencyGraph::new(loader(&dir))
+            .load("./main.ts", &base)
+            .await
+            .unwrap_err();
+        let main = dir.join("main.ts").to_string_lossy().to_string();
+        assert_eq!(
+            err.to_string(),
+            format!("cannot load `./missing.ts` imported from `{main}`")
+        );
+        fs::remove_dir_all(dir).unwrap();
     }
 }
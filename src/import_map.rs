@@ -0,0 +1,243 @@
+use anyhow::{anyhow, bail};
+use std::path::Path;
+use url::Url;
+
+type SpecifierMap = Vec<(String, Url)>;
+
+/**
+# Import map
+
+[WICG import maps](https://github.com/WICG/import-maps), local modules are
+matched as `file://` urls and resolved back to paths
+
+```json
+{
+  "imports": { "std/": "https://deno.land/std@0.182.0/" },
+  "scopes": { "./vendor/": { "lodash": "./vendor/lodash.ts" } }
+}
+```
+*/
+#[derive(Debug, Clone, Default)]
+pub struct ImportMap {
+    /// sorted by key, longest first
+    imports: SpecifierMap,
+    /// sorted by scope prefix, longest first
+    scopes: Vec<(String, SpecifierMap)>,
+}
+
+impl ImportMap {
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let path = std::fs::canonicalize(path)
+            .map_err(|err| anyhow!("read import map `{}` failure: {err}", path.display()))?;
+        let json = std::fs::read_to_string(&path)?;
+        let base = Url::from_file_path(&path)
+            .map_err(|_| anyhow!("invalid import map path `{}`", path.display()))?;
+        Self::parse(&json, &base)
+            .map_err(|err| anyhow!("invalid import map `{}`: {err}", path.display()))
+    }
+
+    pub fn parse(json: &str, base: &Url) -> anyhow::Result<Self> {
+        Self::from_value(&serde_json::from_str(json)?, base)
+    }
+
+    pub fn from_value(value: &serde_json::Value, base: &Url) -> anyhow::Result<Self> {
+        let Some(value) = value.as_object() else {
+            bail!("import map must be a JSON object");
+        };
+
+        let imports = match value.get("imports") {
+            Some(imports) => parse_specifier_map(imports, base)?,
+            None => vec![],
+        };
+
+        let mut scopes = vec![];
+        if let Some(value) = value.get("scopes") {
+            let Some(value) = value.as_object() else {
+                bail!("`scopes` must be a JSON object");
+            };
+            for (prefix, map) in value {
+                let Ok(prefix) = base.join(prefix) else {
+                    eprintln!("Ignored invalid scope `{prefix}` in import map");
+                    continue;
+                };
+                scopes.push((prefix.to_string(), parse_specifier_map(map, base)?));
+            }
+        }
+        scopes.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        Ok(Self { imports, scopes })
+    }
+
+    /// resolve `specifier` imported from `referrer`, `None` when the map has no entry for it
+    pub fn resolve(&self, specifier: &str, referrer: &str) -> Option<String> {
+        let referrer = to_url(referrer)?;
+        let as_url = normalize(specifier, &referrer);
+        let key = as_url.as_ref().map(Url::as_str).unwrap_or(specifier);
+
+        let referrer = referrer.as_str();
+        let scoped = self.scopes.iter().filter(|(prefix, _)| {
+            prefix == referrer || (prefix.ends_with('/') && referrer.starts_with(prefix.as_str()))
+        });
+        for (_, map) in scoped {
+            if let Some(url) = resolve_in(map, key) {
+                return Some(from_url(&url));
+            }
+        }
+        resolve_in(&self.imports, key).map(|url| from_url(&url))
+    }
+}
+
+fn parse_specifier_map(value: &serde_json::Value, base: &Url) -> anyhow::Result<SpecifierMap> {
+    let Some(value) = value.as_object() else {
+        bail!("specifier map must be a JSON object");
+    };
+    let mut map = vec![];
+    for (key, target) in value {
+        let key = match normalize(key, base) {
+            Some(url) => url.to_string(),
+            None => key.clone(),
+        };
+        let target = target.as_str().and_then(|target| base.join(target).ok());
+        match target {
+            Some(target) if key.ends_with('/') && !target.as_str().ends_with('/') => {
+                eprintln!("Ignored import map entry `{key}`, its address must end with `/`");
+            }
+            Some(target) => map.push((key, target)),
+            None => eprintln!("Ignored invalid address of `{key}` in import map"),
+        }
+    }
+    map.sort_by(|(a, _), (b, _)| b.cmp(a));
+    Ok(map)
+}
+
+fn resolve_in(map: &SpecifierMap, key: &str) -> Option<Url> {
+    for (prefix, target) in map {
+        if prefix == key {
+            return Some(target.clone());
+        }
+        if prefix.ends_with('/') && key.starts_with(prefix.as_str()) {
+            return target.join(&key[prefix.len()..]).ok();
+        }
+    }
+    None
+}
+
+/// relative and absolute specifiers are compared as urls, bare specifiers as they are
+fn normalize(specifier: &str, base: &Url) -> Option<Url> {
+    if specifier.starts_with('/') || specifier.starts_with("./") || specifier.starts_with("../") {
+        return base.join(specifier).ok();
+    }
+    Url::parse(specifier).ok()
+}
+
+fn to_url(module: &str) -> Option<Url> {
+    if module.starts_with("http://") || module.starts_with("https://") {
+        return Url::parse(module).ok();
+    }
+    let path = Path::new(module);
+    if path.is_dir() {
+        return Url::from_directory_path(path).ok();
+    }
+    Url::from_file_path(path).ok()
+}
+
+fn from_url(url: &Url) -> String {
+    if url.scheme() == "file" {
+        if let Ok(path) = url.to_file_path() {
+            return path.to_string_lossy().to_string();
+        }
+    }
+    url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import_map(json: &str) -> ImportMap {
+        let base = Url::parse("file:///project/import_map.json").unwrap();
+        ImportMap::parse(json, &base).unwrap()
+    }
+
+    #[test]
+    fn resolves_exact_and_prefix_entries() {
+        let map = import_map(
+            r#"{
+                "imports": {
+                    "lodash": "./vendor/lodash.ts",
+                    "std/": "https://deno.land/std@0.182.0/",
+                    "std/fs/": "./std_fs/"
+                }
+            }"#,
+        );
+        let referrer = "/project/main.ts";
+        assert_eq!(
+            map.resolve("lodash", referrer).as_deref(),
+            Some("/project/vendor/lodash.ts")
+        );
+        assert_eq!(
+            map.resolve("std/path/mod.ts", referrer).as_deref(),
+            Some("https://deno.land/std@0.182.0/path/mod.ts")
+        );
+        // the longest prefix wins
+        assert_eq!(
+            map.resolve("std/fs/mod.ts", referrer).as_deref(),
+            Some("/project/std_fs/mod.ts")
+        );
+        assert_eq!(map.resolve("lodash/fp", referrer), None);
+        assert_eq!(map.resolve("./local.ts", referrer), None);
+    }
+
+    #[test]
+    fn remaps_relative_and_remote_specifiers() {
+        let map = import_map(
+            r#"{
+                "imports": {
+                    "./config.ts": "./config.prod.ts",
+                    "https://example.com/": "./mirror/"
+                }
+            }"#,
+        );
+        assert_eq!(
+            map.resolve("./config.ts", "/project/main.ts").as_deref(),
+            Some("/project/config.prod.ts")
+        );
+        assert_eq!(
+            map.resolve("https://example.com/a/mod.ts", "/project/main.ts")
+                .as_deref(),
+            Some("/project/mirror/a/mod.ts")
+        );
+    }
+
+    #[test]
+    fn scopes_take_precedence_for_their_modules() {
+        let map = import_map(
+            r#"{
+                "imports": { "lodash": "./vendor/lodash.ts" },
+                "scopes": {
+                    "./legacy/": { "lodash": "./vendor/lodash3.ts" },
+                    "./legacy/new/": { "lodash": "./vendor/lodash4.ts" }
+                }
+            }"#,
+        );
+        assert_eq!(
+            map.resolve("lodash", "/project/legacy/app.ts").as_deref(),
+            Some("/project/vendor/lodash3.ts")
+        );
+        assert_eq!(
+            map.resolve("lodash", "/project/legacy/new/app.ts")
+                .as_deref(),
+            Some("/project/vendor/lodash4.ts")
+        );
+        assert_eq!(
+            map.resolve("lodash", "/project/main.ts").as_deref(),
+            Some("/project/vendor/lodash.ts")
+        );
+    }
+
+    #[test]
+    fn ignores_prefix_entries_without_trailing_slash() {
+        let map = import_map(r#"{ "imports": { "std/": "https://deno.land/std" } }"#);
+        assert_eq!(map.resolve("std/path/mod.ts", "/project/main.ts"), None);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::cache::hash;
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+pub const LOCKFILE_NAME: &str = "edon.lock";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LockfileContent {
+    version: String,
+    /// url of a remote module -> sha256 of its source
+    remote: BTreeMap<String, String>,
+}
+
+impl Default for LockfileContent {
+    fn default() -> Self {
+        Self {
+            version: "1".to_string(),
+            remote: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LockfileInner {
+    path: PathBuf,
+    content: LockfileContent,
+    overwrite: bool,
+}
+
+/**
+# Lockfile
+
+records the integrity of every remote module resolved into a graph, a module
+whose source no longer matches its recorded hash fails to load unless the
+lockfile is opened with `overwrite`
+*/
+#[derive(Debug, Clone)]
+pub struct Lockfile {
+    inner: Arc<Mutex<LockfileInner>>,
+}
+
+impl Lockfile {
+    pub fn open(path: impl Into<PathBuf>, overwrite: bool) -> anyhow::Result<Self> {
+        let path = path.into();
+        let content = match fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|err| anyhow!("invalid lockfile `{}`: {err}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => LockfileContent::default(),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Self {
+            inner: Arc::new(Mutex::new(LockfileInner {
+                path,
+                content,
+                overwrite,
+            })),
+        })
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.inner.lock().unwrap().path.clone()
+    }
+
+    /// verify the source of `url`, unknown modules are added to the lockfile
+    pub fn check(&self, url: &str, source: &str) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock().unwrap();
+        let actual = hash(source.as_bytes());
+
+        match inner.content.remote.get(url) {
+            Some(expected) if *expected == actual => return Ok(()),
+            Some(expected) if !inner.overwrite => bail!(
+                "integrity check failed for remote module `{url}`\n  lockfile: {}\n  expected: {expected}\n  actual:   {actual}\nrun with `--lock-write` if the change is intended",
+                inner.path.display()
+            ),
+            _ => {}
+        }
+        inner.content.remote.insert(url.to_string(), actual);
+        write(&inner.path, &inner.content)
+    }
+}
+
+fn write(path: &Path, content: &LockfileContent) -> anyhow::Result<()> {
+    let mut data = serde_json::to_string_pretty(content)?;
+    data.push('\n');
+    fs::write(path, data)
+        .map_err(|err| anyhow!("write lockfile `{}` failure: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lockfile_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("edon-{name}-{}.lock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn records_unknown_modules() {
+        let path = lockfile_path("record");
+        let lockfile = Lockfile::open(&path, false).unwrap();
+        lockfile
+            .check("https://example.com/mod.ts", "export {}")
+            .unwrap();
+
+        let reopened = Lockfile::open(&path, false).unwrap();
+        reopened
+            .check("https://example.com/mod.ts", "export {}")
+            .unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_hash_mismatch() {
+        let path = lockfile_path("mismatch");
+        let lockfile = Lockfile::open(&path, false).unwrap();
+        lockfile
+            .check("https://example.com/mod.ts", "export {}")
+            .unwrap();
+
+        let err = lockfile
+            .check("https://example.com/mod.ts", "export const changed = 1")
+            .unwrap_err();
+        assert!(err.to_string().contains("integrity check failed"));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn overwrite_accepts_hash_mismatch() {
+        let path = lockfile_path("overwrite");
+        Lockfile::open(&path, false)
+            .unwrap()
+            .check("https://example.com/mod.ts", "export {}")
+            .unwrap();
+
+        let lockfile = Lockfile::open(&path, true).unwrap();
+        lockfile
+            .check("https://example.com/mod.ts", "export const changed = 1")
+            .unwrap();
+        let reopened = Lockfile::open(&path, false).unwrap();
+        reopened
+            .check("https://example.com/mod.ts", "export const changed = 1")
+            .unwrap();
+        fs::remove_file(path).unwrap();
+    }
+}
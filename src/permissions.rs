@@ -0,0 +1,551 @@
+use crate::{
+    config::{Config, PermissionConfig},
+    runtime::{OpError, Runtime},
+};
+use colored::Colorize;
+use std::{
+    collections::BTreeSet,
+    env,
+    fmt::Display,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+use url::Url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionName {
+    Read,
+    Write,
+    Net,
+    Env,
+    Run,
+}
+
+impl PermissionName {
+    pub fn flag(&self) -> String {
+        format!("--allow-{self}")
+    }
+
+    /// key of the resource in a permission descriptor, e.g. `{ name: "read", path: "./data" }`
+    pub fn descriptor_key(&self) -> &'static str {
+        match self {
+            Self::Read | Self::Write => "path",
+            Self::Net => "host",
+            Self::Env => "variable",
+            Self::Run => "command",
+        }
+    }
+}
+
+impl FromStr for PermissionName {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "read" => Ok(Self::Read),
+            "write" => Ok(Self::Write),
+            "net" => Ok(Self::Net),
+            "env" => Ok(Self::Env),
+            "run" => Ok(Self::Run),
+            _ => Err(format!("unknown permission name `{name}`")),
+        }
+    }
+}
+
+impl Display for PermissionName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Net => "net",
+            Self::Env => "env",
+            Self::Run => "run",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PermissionState {
+    Granted,
+    /// neither granted nor denied, the user may still be asked
+    Prompt,
+    Denied,
+}
+
+impl Display for PermissionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = match self {
+            Self::Granted => "granted",
+            Self::Prompt => "prompt",
+            Self::Denied => "denied",
+        };
+        write!(f, "{state}")
+    }
+}
+
+/// granted and denied resources of one kind, normalized by [`Permissions::normalize`]
+#[derive(Debug, Clone, Default)]
+pub struct Permission {
+    all: bool,
+    allowed: BTreeSet<String>,
+    denied_all: bool,
+    denied: BTreeSet<String>,
+}
+
+impl Permission {
+    pub fn grant_all(&mut self) {
+        self.all = true;
+    }
+
+    pub fn grant(&mut self, resource: String) {
+        self.allowed.insert(resource);
+    }
+
+    /// a denied permission is never prompted again
+    pub fn deny(&mut self, resource: Option<String>) {
+        match resource {
+            Some(resource) => {
+                self.denied.insert(resource);
+            }
+            None => self.denied_all = true,
+        }
+    }
+
+    /// revoking a single resource also revokes a grant of every resource
+    pub fn revoke(&mut self, name: PermissionName, resource: Option<&str>) {
+        self.all = false;
+        match resource {
+            Some(resource) => self
+                .allowed
+                .retain(|allowed| !matches(name, allowed, resource)),
+            None => self.allowed.clear(),
+        }
+    }
+
+    /// the state of `resource`, or of every resource of this kind
+    pub fn state(&self, name: PermissionName, resource: Option<&str>) -> PermissionState {
+        if self.all {
+            return PermissionState::Granted;
+        }
+        let Some(resource) = resource else {
+            return match self.denied_all {
+                true => PermissionState::Denied,
+                false => PermissionState::Prompt,
+            };
+        };
+        if self
+            .allowed
+            .iter()
+            .any(|allowed| matches(name, allowed, resource))
+        {
+            PermissionState::Granted
+        } else if self.denied_all
+            || self
+                .denied
+                .iter()
+                .any(|denied| matches(name, denied, resource))
+        {
+            PermissionState::Denied
+        } else {
+            PermissionState::Prompt
+        }
+    }
+}
+
+/// whether a granted or denied entry covers `resource`
+fn matches(name: PermissionName, entry: &str, resource: &str) -> bool {
+    match name {
+        PermissionName::Read | PermissionName::Write => Path::new(resource).starts_with(entry),
+        // a host covers every port, `host:port` only that port
+        PermissionName::Net => {
+            entry == resource
+                || resource
+                    .rsplit_once(':')
+                    .is_some_and(|(host, _)| host == entry)
+        }
+        PermissionName::Env | PermissionName::Run => entry == resource,
+    }
+}
+
+/**
+# Permissions
+
+everything a script can reach outside the runtime is denied unless granted by
+`--allow-*` flags or the `permissions` of the configuration file, or allowed
+by the user when `prompt` is set
+
+| name  | resource                          |
+| ----- | --------------------------------- |
+| read  | absolute path, grants its subtree |
+| write | absolute path, grants its subtree |
+| net   | `host` or `host:port`             |
+| env   | variable name                     |
+| run   | program name or path              |
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Permissions {
+    pub read: Permission,
+    pub write: Permission,
+    pub net: Permission,
+    pub env: Permission,
+    pub run: Permission,
+    /// ask on the terminal instead of denying
+    pub prompt: bool,
+}
+
+impl Permissions {
+    pub fn allow_all() -> Self {
+        let mut permissions = Self::default();
+        for name in [
+            PermissionName::Read,
+            PermissionName::Write,
+            PermissionName::Net,
+            PermissionName::Env,
+            PermissionName::Run,
+        ] {
+            permissions.get_mut(name).grant_all();
+        }
+        permissions
+    }
+
+    /// permissions granted by the configuration file, paths are relative to its directory
+    pub fn from_config(config: &Config) -> Self {
+        let permissions = &config.permissions;
+        if permissions.all {
+            return Self::allow_all();
+        }
+        let base = config.dir().map(Path::to_path_buf).unwrap_or_default();
+        let mut result = Self::default();
+        for (name, permission) in [
+            (PermissionName::Read, &permissions.read),
+            (PermissionName::Write, &permissions.write),
+            (PermissionName::Net, &permissions.net),
+            (PermissionName::Env, &permissions.env),
+            (PermissionName::Run, &permissions.run),
+        ] {
+            match permission {
+                PermissionConfig::All(true) => result.get_mut(name).grant_all(),
+                PermissionConfig::All(false) => {}
+                PermissionConfig::List(list) => result.grant(name, list, &base),
+            }
+        }
+        result
+    }
+
+    pub fn get(&self, name: PermissionName) -> &Permission {
+        match name {
+            PermissionName::Read => &self.read,
+            PermissionName::Write => &self.write,
+            PermissionName::Net => &self.net,
+            PermissionName::Env => &self.env,
+            PermissionName::Run => &self.run,
+        }
+    }
+
+    pub fn get_mut(&mut self, name: PermissionName) -> &mut Permission {
+        match name {
+            PermissionName::Read => &mut self.read,
+            PermissionName::Write => &mut self.write,
+            PermissionName::Net => &mut self.net,
+            PermissionName::Env => &mut self.env,
+            PermissionName::Run => &mut self.run,
+        }
+    }
+
+    /// grant a list of resources, everything when the list is empty
+    pub fn grant(&mut self, name: PermissionName, resources: &[String], base: &Path) {
+        let permission = self.get_mut(name);
+        if resources.is_empty() {
+            permission.grant_all();
+        }
+        for resource in resources {
+            permission.grant(Self::normalize(name, resource, base));
+        }
+    }
+
+    pub fn check(&mut self, name: PermissionName, resource: &str) -> Result<(), String> {
+        let resource = Self::normalize_cwd(name, resource);
+        match self.get(name).state(name, Some(&resource)) {
+            PermissionState::Granted => return Ok(()),
+            PermissionState::Prompt if self.prompt => match prompt(name, Some(&resource)) {
+                Answer::Once => return Ok(()),
+                Answer::Always => {
+                    self.get_mut(name).grant(resource);
+                    return Ok(());
+                }
+                Answer::Deny => self.get_mut(name).deny(Some(resource.clone())),
+            },
+            _ => {}
+        }
+        Err(format!(
+            "Requires {name} access to \"{resource}\", run again with the {} flag",
+            name.flag()
+        ))
+    }
+
+    /// check a permission that needs every resource of its kind, e.g. listing the environment
+    pub fn check_all(&mut self, name: PermissionName) -> Result<(), String> {
+        match self.get(name).state(name, None) {
+            PermissionState::Granted => return Ok(()),
+            PermissionState::Prompt if self.prompt => match prompt(name, None) {
+                Answer::Once => return Ok(()),
+                Answer::Always => {
+                    self.get_mut(name).grant_all();
+                    return Ok(());
+                }
+                Answer::Deny => self.get_mut(name).deny(None),
+            },
+            _ => {}
+        }
+        Err(format!(
+            "Requires {name} access, run again with the {} flag",
+            name.flag()
+        ))
+    }
+
+    pub fn query(&self, name: PermissionName, resource: Option<&str>) -> PermissionState {
+        let resource = resource.map(|resource| Self::normalize_cwd(name, resource));
+        self.get(name).state(name, resource.as_deref())
+    }
+
+    /// ask the user for a permission that is neither granted nor denied
+    pub fn request(&mut self, name: PermissionName, resource: Option<&str>) -> PermissionState {
+        let resource = resource.map(|resource| Self::normalize_cwd(name, resource));
+        let state = self.get(name).state(name, resource.as_deref());
+        if state != PermissionState::Prompt {
+            return state;
+        }
+        let answer = match self.prompt {
+            true => prompt(name, resource.as_deref()),
+            false => Answer::Deny,
+        };
+        let permission = self.get_mut(name);
+        match (answer, resource) {
+            (Answer::Deny, resource) => {
+                permission.deny(resource);
+                PermissionState::Denied
+            }
+            (_, Some(resource)) => {
+                permission.grant(resource);
+                PermissionState::Granted
+            }
+            (_, None) => {
+                permission.grant_all();
+                PermissionState::Granted
+            }
+        }
+    }
+
+    pub fn revoke(&mut self, name: PermissionName, resource: Option<&str>) -> PermissionState {
+        let resource = resource.map(|resource| Self::normalize_cwd(name, resource));
+        let permission = self.get_mut(name);
+        permission.revoke(name, resource.as_deref());
+        permission.state(name, resource.as_deref())
+    }
+
+    /// [`Self::normalize`] relative to the current directory
+    pub fn normalize_cwd(name: PermissionName, resource: &str) -> String {
+        let cwd = env::current_dir().unwrap_or_default();
+        Self::normalize(name, resource, &cwd)
+    }
+
+    /// absolute paths for `read` and `write`, `host[:port]` for `net`
+    pub fn normalize(name: PermissionName, resource: &str, base: &Path) -> String {
+        match name {
+            PermissionName::Read | PermissionName::Write => normalize_path(&base.join(resource))
+                .to_string_lossy()
+                .to_string(),
+            PermissionName::Net => match Url::parse(resource) {
+                Ok(url) if url.has_host() => match (url.host_str(), url.port_or_known_default()) {
+                    (Some(host), Some(port)) => format!("{host}:{port}"),
+                    (Some(host), None) => host.to_string(),
+                    _ => resource.to_string(),
+                },
+                _ => resource.to_lowercase(),
+            },
+            PermissionName::Env | PermissionName::Run => resource.to_string(),
+        }
+    }
+}
+
+enum Answer {
+    Once,
+    Always,
+    Deny,
+}
+
+/// ask on stderr, an unreadable stdin denies
+fn prompt(name: PermissionName, resource: Option<&str>) -> Answer {
+    let target = match resource {
+        Some(resource) => format!("{name} access to \"{resource}\""),
+        None => format!("{name} access"),
+    };
+    let mut stderr = io::stderr();
+    let _ = writeln!(stderr, "{} edon requests {target}.", "┏ ⚠️ ".yellow());
+    let _ = writeln!(
+        stderr,
+        "┠─ Run again with {} to bypass this prompt.",
+        name.flag()
+    );
+    loop {
+        let _ = write!(
+            stderr,
+            "┗ Allow? [y/A/n] (y = yes, allow once; A = allow always for this resource; n = no, deny) > "
+        );
+        let _ = stderr.flush();
+
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+            return Answer::Deny;
+        }
+        match input.trim() {
+            "y" | "Y" => return Answer::Once,
+            "A" => return Answer::Always,
+            "n" | "N" => return Answer::Deny,
+            _ => continue,
+        }
+    }
+}
+
+/// lexically resolve `.` and `..`, the path does not need to exist
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            component => result.push(component),
+        }
+    }
+    result
+}
+
+/// check a permission of the current runtime, throws a `PermissionDenied` error when not granted
+pub fn check(scope: &mut v8::HandleScope, name: PermissionName, resource: &str) -> bool {
+    let result = Runtime::state(scope)
+        .borrow_mut()
+        .permissions
+        .check(name, resource);
+    throw_denied(scope, result)
+}
+
+/// [`check`] for permissions that need every resource of their kind
+pub fn check_all(scope: &mut v8::HandleScope, name: PermissionName) -> bool {
+    let result = Runtime::state(scope)
+        .borrow_mut()
+        .permissions
+        .check_all(name);
+    throw_denied(scope, result)
+}
+
+fn throw_denied(scope: &mut v8::HandleScope, result: Result<(), String>) -> bool {
+    match result {
+        Ok(()) => true,
+        Err(message) => {
+            OpError::new("PermissionDenied", message).throw(scope);
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn granted(name: PermissionName, list: &[&str]) -> Permissions {
+        let mut permissions = Permissions::default();
+        let list = list.iter().map(|item| item.to_string()).collect::<Vec<_>>();
+        permissions.grant(name, &list, Path::new("/project"));
+        permissions
+    }
+
+    #[test]
+    fn paths_grant_their_subtree() {
+        let permissions = granted(PermissionName::Read, &["./data", "/etc/hosts"]);
+        let query = |path| permissions.query(PermissionName::Read, Some(path));
+        assert_eq!(query("/project/data"), PermissionState::Granted);
+        assert_eq!(query("/project/data/a/b.json"), PermissionState::Granted);
+        assert_eq!(query("/etc/hosts"), PermissionState::Granted);
+        // whole components only
+        assert_eq!(query("/project/database"), PermissionState::Prompt);
+        assert_eq!(query("/project/data/../secret"), PermissionState::Prompt);
+        assert_eq!(query("/etc"), PermissionState::Prompt);
+        assert_eq!(
+            permissions.query(PermissionName::Write, Some("/project/data")),
+            PermissionState::Prompt
+        );
+    }
+
+    #[test]
+    fn hosts_grant_every_port() {
+        let permissions = granted(PermissionName::Net, &["example.com", "127.0.0.1:8080"]);
+        let query = |host| permissions.query(PermissionName::Net, Some(host));
+        assert_eq!(query("example.com"), PermissionState::Granted);
+        assert_eq!(query("example.com:8443"), PermissionState::Granted);
+        assert_eq!(query("https://example.com/path"), PermissionState::Granted);
+        assert_eq!(query("127.0.0.1:8080"), PermissionState::Granted);
+        assert_eq!(query("http://127.0.0.1:8080/"), PermissionState::Granted);
+        assert_eq!(query("127.0.0.1:8081"), PermissionState::Prompt);
+        assert_eq!(query("api.example.com"), PermissionState::Prompt);
+    }
+
+    #[test]
+    fn names_match_exactly() {
+        let permissions = granted(PermissionName::Env, &["HOME"]);
+        assert_eq!(
+            permissions.query(PermissionName::Env, Some("HOME")),
+            PermissionState::Granted
+        );
+        assert_eq!(
+            permissions.query(PermissionName::Env, Some("HOMEPATH")),
+            PermissionState::Prompt
+        );
+        assert_eq!(
+            permissions.query(PermissionName::Env, None),
+            PermissionState::Prompt
+        );
+    }
+
+    #[test]
+    fn empty_list_grants_everything() {
+        let permissions = granted(PermissionName::Run, &[]);
+        assert_eq!(
+            permissions.query(PermissionName::Run, Some("git")),
+            PermissionState::Granted
+        );
+        assert_eq!(
+            permissions.query(PermissionName::Run, None),
+            PermissionState::Granted
+        );
+    }
+
+    #[test]
+    fn check_denies_without_prompt() {
+        let mut permissions = granted(PermissionName::Write, &["/tmp"]);
+        assert!(permissions
+            .check(PermissionName::Write, "/tmp/out.txt")
+            .is_ok());
+        let err = permissions
+            .check(PermissionName::Write, "/etc/passwd")
+            .unwrap_err();
+        assert!(err.contains("--allow-write"));
+        assert!(permissions.check_all(PermissionName::Write).is_err());
+    }
+
+    #[test]
+    fn revoking_a_resource_keeps_the_others() {
+        let mut permissions = granted(PermissionName::Read, &["/a", "/b"]);
+        assert_eq!(
+            permissions.revoke(PermissionName::Read, Some("/a/file")),
+            PermissionState::Prompt
+        );
+        assert_eq!(
+            permissions.query(PermissionName::Read, Some("/b/file")),
+            PermissionState::Granted
+        );
+    }
+}
@@ -0,0 +1,495 @@
+use crate::{
+    builtin::console::console_format,
+    compile_oxc::compile,
+    config::{CompilerOptions, Config},
+    graph::{DependencyGraph, Loader},
+    permissions::Permissions,
+    runtime::Runtime,
+};
+use anyhow::anyhow;
+use oxc_allocator::Allocator;
+use oxc_ast::{
+    ast::{FunctionBody, ImportDeclarationSpecifier, ImportOrExportKind, Statement},
+    syntax_directed_operations::BoundNames,
+    Visit,
+};
+use oxc_span::{GetSpan, SourceType, Span};
+use rustyline::{error::ReadlineError, DefaultEditor};
+use std::{env, path::PathBuf, sync::mpsc as std_mpsc, thread};
+use tokio::sync::mpsc;
+
+const HELP: &str = "\
+.exit    Exit the repl
+.help    Print this help message
+
+Press Ctrl+C to abort the current input, Ctrl+D to exit.";
+
+/// detect `await` outside of any function body
+#[derive(Debug, Default)]
+struct AwaitFinder {
+    found: bool,
+}
+
+impl<'a> Visit<'a> for AwaitFinder {
+    fn visit_function_body(&mut self, _body: &FunctionBody<'a>) {}
+
+    fn visit_await_expression(&mut self, _expr: &oxc_ast::ast::AwaitExpression<'a>) {
+        self.found = true;
+    }
+
+    fn visit_for_of_statement(&mut self, stmt: &oxc_ast::ast::ForOfStatement<'a>) {
+        self.found |= stmt.r#await;
+        oxc_ast::visit::walk::walk_for_of_statement(self, stmt);
+    }
+}
+
+/**
+# Read-eval-print loop
+
+keeps a single [`Runtime`] alive, every input is compiled and evaluated as a
+script in its global context
+*/
+pub struct Repl {
+    runtime: Runtime,
+    filename: String,
+    options: CompilerOptions,
+}
+
+impl Repl {
+    pub fn new(
+        current_dir: &str,
+        config: Config,
+        permissions: Permissions,
+    ) -> anyhow::Result<Self> {
+        let filename = PathBuf::from(current_dir)
+            .join("$edon$repl.ts")
+            .to_string_lossy()
+            .to_string();
+        let options = config.compiler_options.clone();
+        let loader = Loader {
+            import_map: config.import_map()?,
+            config,
+            ..Default::default()
+        };
+        let mut runtime = Runtime::from(DependencyGraph::new(loader)).with_permissions(permissions);
+        runtime.prepare()?;
+        Ok(Self {
+            runtime,
+            filename,
+            options,
+        })
+    }
+
+    pub async fn start(&mut self) -> anyhow::Result<i32> {
+        println!("Welcome to edon {}", env!("CARGO_PKG_VERSION"));
+        println!("Type \".help\" for more information.");
+
+        let (line_tx, mut line_rx) = mpsc::unbounded_channel::<String>();
+        let (ready_tx, ready_rx) = std_mpsc::channel::<()>();
+
+        // rustyline blocks, read lines on a separate thread so timers keep firing
+        thread::spawn(move || -> anyhow::Result<()> {
+            let mut editor = DefaultEditor::new()?;
+            let history = history_file();
+            if let Some(history) = &history {
+                let _ = editor.load_history(history);
+            }
+            while ready_rx.recv().is_ok() {
+                let mut input = String::new();
+                loop {
+                    let prompt = if input.is_empty() { "> " } else { "... " };
+                    match editor.readline(prompt) {
+                        Ok(line) => {
+                            input.push_str(&line);
+                            input.push('\n');
+                            if is_complete(&input) {
+                                break;
+                            }
+                        }
+                        Err(ReadlineError::Interrupted) => input.clear(),
+                        Err(_) => return Ok(()),
+                    }
+                }
+                let _ = editor.add_history_entry(input.trim_end());
+                if let Some(history) = &history {
+                    let _ = editor.save_history(history);
+                }
+                if line_tx.send(input).is_err() {
+                    break;
+                }
+            }
+            Ok(())
+        });
+
+        let _ = ready_tx.send(());
+        loop {
+            tokio::select! {
+                input = line_rx.recv() => {
+                    let Some(input) = input else {
+                        break;
+                    };
+                    match input.trim() {
+                        ".exit" => break,
+                        ".help" => println!("{HELP}"),
+                        "" => {}
+                        code => self.eval(code).await,
+                    }
+                    let _ = ready_tx.send(());
+                }
+                _ = self.runtime.tick(), if self.runtime.has_pending_ops() => {
+                    Runtime::report_rejection(&mut self.runtime.isolate);
+                }
+            }
+        }
+        Ok(0)
+    }
+
+    /// evaluate one input and print its completion value
+    pub async fn eval(&mut self, input: &str) {
+        let result = match transform(input, &self.filename, &self.options) {
+            Ok((code, is_async)) => self.execute(&code, is_async).await,
+            Err(err) => {
+                eprintln!("{err}");
+                return;
+            }
+        };
+
+        {
+            let isolate = self.runtime.isolate.as_mut();
+            let context = Runtime::state(isolate).borrow().context.clone();
+            let scope = &mut v8::HandleScope::with_context(isolate, context);
+            match result {
+                Ok(value) => {
+                    let value = v8::Local::new(scope, value);
+                    let level = if value.is_string() { 1 } else { 0 };
+                    println!("{}", console_format(scope, &value, level));
+                }
+                Err(exception) => {
+                    let exception = v8::Local::new(scope, exception);
+                    eprintln!("Uncaught {}", console_format(scope, &exception, 0));
+                }
+            }
+        }
+        Runtime::report_rejection(&mut self.runtime.isolate);
+    }
+
+    async fn execute(
+        &mut self,
+        code: &str,
+        is_async: bool,
+    ) -> Result<v8::Global<v8::Value>, v8::Global<v8::Value>> {
+        let promise = {
+            let isolate = self.runtime.isolate.as_mut();
+            let context = Runtime::state(isolate).borrow().context.clone();
+            let scope = &mut v8::HandleScope::with_context(isolate, context);
+            let tc_scope = &mut v8::TryCatch::new(scope);
+
+            let source = v8::String::new(tc_scope, code).unwrap();
+            let name = v8::String::new(tc_scope, &self.filename).unwrap();
+            let origin = v8::ScriptOrigin::new(
+                tc_scope,
+                name.into(),
+                0,
+                0,
+                false,
+                0,
+                name.into(),
+                false,
+                false,
+                false,
+            );
+            let result = v8::Script::compile(tc_scope, source, Some(&origin))
+                .and_then(|script| script.run(tc_scope));
+            let Some(value) = result else {
+                let exception = tc_scope
+                    .exception()
+                    .unwrap_or_else(|| v8::undefined(tc_scope).into());
+                return Err(v8::Global::new(tc_scope, exception));
+            };
+            if !is_async || !value.is_promise() {
+                return Ok(v8::Global::new(tc_scope, value));
+            }
+            let promise = v8::Local::<v8::Promise>::try_from(value).unwrap();
+            v8::Global::new(tc_scope, promise)
+        };
+
+        // drive the event loop until the top level await settles
+        loop {
+            let state = promise.open(&mut self.runtime.isolate).state();
+            if state != v8::PromiseState::Pending || !self.runtime.has_pending_ops() {
+                break;
+            }
+            self.runtime.tick().await;
+        }
+
+        let isolate = self.runtime.isolate.as_mut();
+        let context = Runtime::state(isolate).borrow().context.clone();
+        let scope = &mut v8::HandleScope::with_context(isolate, context);
+        let promise = v8::Local::new(scope, promise);
+        match promise.state() {
+            v8::PromiseState::Fulfilled => {
+                let value = promise.result(scope);
+                Ok(v8::Global::new(scope, value))
+            }
+            v8::PromiseState::Rejected => {
+                // reported as the result of the input
+                Runtime::state(scope)
+                    .borrow_mut()
+                    .rejections
+                    .remove(&promise.get_identity_hash());
+                let reason = promise.result(scope);
+                Err(v8::Global::new(scope, reason))
+            }
+            v8::PromiseState::Pending => {
+                let value = v8::Local::<v8::Value>::from(promise);
+                Ok(v8::Global::new(scope, value))
+            }
+        }
+    }
+}
+
+/// compile the input to a script, returns whether it was wrapped for top level await
+fn transform(
+    input: &str,
+    filename: &str,
+    options: &CompilerOptions,
+) -> anyhow::Result<(String, bool)> {
+    let input = rewrite_imports(input, filename);
+    let dep = compile(filename, &input, options)?;
+    match wrap_async(&dep.source)? {
+        Some(code) => Ok((code, true)),
+        None => Ok((dep.source, false)),
+    }
+}
+
+/// static imports are not allowed in scripts, turn them into `await import()`
+fn rewrite_imports(input: &str, filename: &str) -> String {
+    let allo = Allocator::default();
+    let source_type = SourceType::from_path(filename).unwrap_or_default();
+    let ret = oxc_parser::Parser::new(&allo, input, source_type).parse();
+    if !ret.errors.is_empty() {
+        return input.to_string();
+    }
+
+    let mut patches: Vec<(Span, String)> = vec![];
+    for stmt in &ret.program.body {
+        let Statement::ImportDeclaration(decl) = stmt else {
+            continue;
+        };
+        if decl.import_kind == ImportOrExportKind::Type {
+            patches.push((decl.span, String::new()));
+            continue;
+        }
+        let source = serde_json::to_string(decl.source.value.as_str()).unwrap_or_default();
+        let module = format!("await import({source})");
+        let mut names = vec![];
+        let mut patch = String::new();
+        for specifier in decl.specifiers.iter().flatten() {
+            match specifier {
+                ImportDeclarationSpecifier::ImportSpecifier(s) => {
+                    if s.import_kind != ImportOrExportKind::Type {
+                        names.push(format!("{}: {}", s.imported, s.local.name));
+                    }
+                }
+                ImportDeclarationSpecifier::ImportDefaultSpecifier(s) => {
+                    names.push(format!("default: {}", s.local.name))
+                }
+                ImportDeclarationSpecifier::ImportNamespaceSpecifier(s) => {
+                    patch.push_str(&format!("const {} = {module};", s.local.name))
+                }
+            }
+        }
+        if !names.is_empty() {
+            patch.push_str(&format!("const {{ {} }} = {module};", names.join(", ")));
+        }
+        if patch.is_empty() {
+            patch = format!("{module};");
+        }
+        patches.push((decl.span, patch));
+    }
+    apply(input, patches)
+}
+
+/// wrap code with top level await into an async function, top level
+/// declarations are hoisted to `var` so that they outlive the input
+fn wrap_async(code: &str) -> anyhow::Result<Option<String>> {
+    let allo = Allocator::default();
+    let source_type = SourceType::default().with_module(true);
+    let ret = oxc_parser::Parser::new(&allo, code, source_type).parse();
+    if !ret.errors.is_empty() {
+        return Err(anyhow!("parse repl input failure"));
+    }
+
+    let mut finder = AwaitFinder::default();
+    finder.visit_program(&ret.program);
+    if !finder.found {
+        return Ok(None);
+    }
+
+    let text = |span: Span| &code[span.start as usize..span.end as usize];
+    let mut hoisted = vec![];
+    let mut patches: Vec<(Span, String)> = vec![];
+    let last = ret.program.body.len().saturating_sub(1);
+    for (index, stmt) in ret.program.body.iter().enumerate() {
+        match stmt {
+            Statement::VariableDeclaration(decl) => {
+                let mut assigns = vec![];
+                for declarator in &decl.declarations {
+                    declarator
+                        .id
+                        .bound_names(&mut |ident| hoisted.push(ident.name.to_string()));
+                    if let Some(init) = &declarator.init {
+                        let id = text(declarator.id.span());
+                        assigns.push(format!("({id} = {});", text(init.span())));
+                    }
+                }
+                patches.push((decl.span, assigns.join(" ")));
+            }
+            Statement::FunctionDeclaration(func) => {
+                if let Some(id) = &func.id {
+                    hoisted.push(id.name.to_string());
+                    patches.push((func.span, format!("{} = {};", id.name, text(func.span))));
+                }
+            }
+            Statement::ClassDeclaration(class) => {
+                if let Some(id) = &class.id {
+                    hoisted.push(id.name.to_string());
+                    patches.push((class.span, format!("{} = {};", id.name, text(class.span))));
+                }
+            }
+            Statement::ExpressionStatement(expr) if index == last => {
+                let value = text(expr.expression.span());
+                patches.push((expr.span, format!("return ({value});")));
+            }
+            _ => {}
+        }
+    }
+
+    let body = apply(code, patches);
+    let declare = if hoisted.is_empty() {
+        String::new()
+    } else {
+        format!("var {};\n", hoisted.join(", "))
+    };
+    Ok(Some(format!("{declare}(async () => {{\n{body}\n}})()")))
+}
+
+fn apply(code: &str, mut patches: Vec<(Span, String)>) -> String {
+    let mut code = code.to_string();
+    patches.sort_by_key(|(span, _)| span.start);
+    for (span, patch) in patches.iter().rev() {
+        code.replace_range(span.start as usize..span.end as usize, patch);
+    }
+    code
+}
+
+/// whether brackets, strings and comments of the input are closed
+fn is_complete(input: &str) -> bool {
+    let mut depth = 0i32;
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '\'' | '"' | '`' => {
+                let mut closed = false;
+                while let Some(n) = chars.next() {
+                    if n == '\\' {
+                        chars.next();
+                    } else if n == c {
+                        closed = true;
+                        break;
+                    } else if n == '\n' && c != '`' {
+                        break;
+                    }
+                }
+                if !closed && c == '`' {
+                    return false;
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for n in chars.by_ref() {
+                    if n == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut closed = false;
+                while let Some(n) = chars.next() {
+                    if n == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        closed = true;
+                        break;
+                    }
+                }
+                if !closed {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+fn history_file() -> Option<PathBuf> {
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?;
+    Some(PathBuf::from(home).join(".edon_repl_history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_incomplete_input() {
+        assert!(is_complete("1 + 1"));
+        assert!(is_complete("const s = \"{\" // {"));
+        assert!(is_complete("f(`a\nb`)"));
+        assert!(!is_complete("function f() {"));
+        assert!(!is_complete("[1,\n2"));
+        assert!(!is_complete("`multi\nline"));
+        assert!(!is_complete("/* open comment"));
+    }
+
+    #[test]
+    fn rewrites_static_imports() {
+        assert_eq!(
+            rewrite_imports("import { a, b as c } from './mod.ts'", "repl.ts"),
+            "const { a: a, b: c } = await import(\"./mod.ts\");"
+        );
+        assert_eq!(
+            rewrite_imports("import d, * as ns from './mod.ts'", "repl.ts"),
+            "const ns = await import(\"./mod.ts\");const { default: d } = await import(\"./mod.ts\");"
+        );
+        assert_eq!(
+            rewrite_imports("import './side.ts'", "repl.ts"),
+            "await import(\"./side.ts\");"
+        );
+        assert_eq!(
+            rewrite_imports("import type { T } from './mod.ts'", "repl.ts"),
+            ""
+        );
+    }
+
+    #[test]
+    fn wraps_top_level_await() {
+        assert_eq!(wrap_async("const a = 1; a + 1").unwrap(), None);
+
+        let code = wrap_async("const a = await f(); function g() {} a").unwrap();
+        assert_eq!(
+            code.as_deref(),
+            Some("var a, g;\n(async () => {\n(a = await f()); g = function g() {}; return (a);\n})()")
+        );
+    }
+
+    #[test]
+    fn ignores_await_in_nested_functions() {
+        assert_eq!(
+            wrap_async("async function f() { await g() }").unwrap(),
+            None
+        );
+    }
+}
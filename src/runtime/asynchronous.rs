@@ -1,25 +1,92 @@
 use anyhow::anyhow;
+use futures::Future;
+use serde::Serialize;
 use std::task::Poll;
 use v8::Isolate;
 
-use super::Runtime;
+use super::{error::OpResult, OpError, Runtime};
+use crate::{builtin::console::console_format, compile::ModuleDependency};
+
+/// settles the promise of an async op, called inside the runtime context
+pub type Settle = Box<
+    dyn for<'s> FnOnce(
+        &mut v8::HandleScope<'s>,
+    ) -> Result<v8::Local<'s, v8::Value>, v8::Local<'s, v8::Value>>,
+>;
+
+/// give a closure the higher ranked signature of [`Settle`]
+pub fn settle<F>(f: F) -> Settle
+where
+    F: for<'s> FnOnce(
+            &mut v8::HandleScope<'s>,
+        ) -> Result<v8::Local<'s, v8::Value>, v8::Local<'s, v8::Value>>
+        + 'static,
+{
+    Box::new(f)
+}
 
-#[derive(Debug, PartialEq)]
 pub enum AsynchronousKind {
-    Import((String, v8::Global<v8::PromiseResolver>)),
+    /// a dynamic import of a url, with the modules loaded for it
+    Import(
+        (
+            String,
+            v8::Global<v8::PromiseResolver>,
+            anyhow::Result<Vec<ModuleDependency>>,
+        ),
+    ),
     Operation(u32),
-    // Callback(impl Future<Output = anyhow::Result<()>>),
+    /// a hot module replacement, with the modules the new version imports
+    Hot(
+        (
+            String,
+            ModuleDependency,
+            anyhow::Result<Vec<ModuleDependency>>,
+        ),
+    ),
+    Settle((v8::Global<v8::PromiseResolver>, Settle)),
+}
+
+impl std::fmt::Debug for AsynchronousKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Import((source, _, _)) => f.debug_tuple("Import").field(source).finish(),
+            Self::Operation(id) => f.debug_tuple("Operation").field(id).finish(),
+            Self::Hot((url, _, _)) => f.debug_tuple("Hot").field(url).finish(),
+            Self::Settle(_) => f.debug_tuple("Settle").finish(),
+        }
+    }
 }
 
 impl AsynchronousKind {
-    pub fn exec(&self, isolate: &mut Isolate) -> anyhow::Result<Poll<()>> {
+    pub fn exec(self, isolate: &mut Isolate) -> anyhow::Result<Poll<()>> {
         match self {
-            AsynchronousKind::Operation(id) => Self::operation(isolate, id.clone()),
-            AsynchronousKind::Import((source, resolver)) => Self::import(isolate, source, resolver),
-            // AsynchronousKind::Callback(f) => f.await,
+            AsynchronousKind::Operation(id) => Self::operation(isolate, id),
+            AsynchronousKind::Import((source, resolver, loaded)) => {
+                Self::import(isolate, &source, resolver, loaded)
+            }
+            AsynchronousKind::Hot((url, dep, loaded)) => {
+                Runtime::hot_replace(isolate, url, dep, loaded);
+                Ok(Poll::Ready(()))
+            }
+            AsynchronousKind::Settle((resolver, settle)) => Self::settle(isolate, resolver, settle),
         }
     }
 
+    fn settle(
+        isolate: &mut Isolate,
+        resolver: v8::Global<v8::PromiseResolver>,
+        settle: Settle,
+    ) -> anyhow::Result<Poll<()>> {
+        let context = Runtime::state(isolate).borrow().context.clone();
+        let scope = &mut v8::HandleScope::with_context(isolate, context);
+        let resolver = v8::Local::new(scope, resolver);
+        match settle(scope) {
+            Ok(value) => resolver.resolve(scope, value),
+            Err(reason) => resolver.reject(scope, reason),
+        };
+        Ok(Poll::Ready(()))
+    }
+
     fn operation(isolate: &mut Isolate, id: u32) -> anyhow::Result<Poll<()>> {
         let state_rc = Runtime::state(isolate);
 
@@ -45,66 +112,111 @@ impl AsynchronousKind {
         let tc_scope = &mut v8::TryCatch::new(scope);
         let script = v8::Script::compile(tc_scope, source, Some(&origin))
             .ok_or(anyhow!("compile script failure"))?;
-        script
-            .run(tc_scope)
-            .ok_or(anyhow!("run script failure {}", id))?;
-
-        if tc_scope.has_caught() {
-            return Err(anyhow!("exec error"));
+        // an exception thrown by the callback is reported with its message and stack
+        if script.run(tc_scope).is_none() || tc_scope.has_caught() {
+            let error = match tc_scope.exception() {
+                Some(exception) => console_format(tc_scope, &exception, 0),
+                None => format!("timer {id} was terminated"),
+            };
+            return Err(anyhow!("Uncaught {error}"));
         }
         Ok(Poll::Ready(()))
     }
     fn import(
         isolate: &mut Isolate,
         source: &String,
-        resolver: &v8::Global<v8::PromiseResolver>,
+        resolver: v8::Global<v8::PromiseResolver>,
+        loaded: anyhow::Result<Vec<ModuleDependency>>,
     ) -> anyhow::Result<Poll<()>> {
-        let state_rc = Runtime::state(isolate);
-        let graph_rc = Runtime::graph(isolate);
+        let context = Runtime::state(isolate).borrow().context.clone();
+        let namespace = match loaded {
+            Ok(modules) => {
+                Runtime::graph(isolate)
+                    .borrow()
+                    .table
+                    .borrow_mut()
+                    .extend(modules);
+                Self::namespace(isolate, source)
+                    .map_err(|err| OpError::new("Error", format!("{err:#}")))
+            }
+            Err(err) => Err(OpError::type_error(format!("{err:#}"))),
+        };
 
-        let context = {
-            let state = state_rc.borrow();
-            state.context.clone()
+        let scope = &mut v8::HandleScope::with_context(isolate, context);
+        let resolver = v8::Local::new(scope, resolver);
+        match namespace {
+            Ok(namespace) => {
+                let namespace = v8::Local::new(scope, namespace);
+                resolver.resolve(scope, namespace)
+            }
+            Err(err) => {
+                let err = err.to_v8(scope);
+                resolver.reject(scope, err)
+            }
         };
+        Ok(Poll::Ready(()))
+    }
 
-        if {
+    /// instantiate and evaluate a module of the graph, returns its namespace
+    fn namespace(isolate: &mut Isolate, source: &String) -> anyhow::Result<v8::Global<v8::Value>> {
+        let graph_rc = Runtime::graph(isolate);
+        {
             let graph = graph_rc.borrow();
             let table = graph.table.borrow();
-            table.get(source).is_none()
-        } {
-            let base = format!("");
-            let _ = futures::executor::block_on(Runtime::import(isolate, source, &base));
-        };
+            let dep = table
+                .get(source)
+                .ok_or(anyhow!("source `{}` not found", source))?;
+            dep.initialize(isolate)?;
+            dep.evaluate(isolate)?;
+        }
 
         let graph = graph_rc.borrow();
-        let table = graph.table.borrow();
-        let dep = table
+        let module = graph.module.borrow();
+        let instance = module
             .get(source)
-            .ok_or(anyhow!("source `{}` not found", source))?;
-
-        if dep.initialize(isolate).is_ok() {
-            dep.evaluate(isolate)?;
-
-            let scope = &mut v8::HandleScope::with_context(isolate, context);
-            let tc_scope = &mut v8::TryCatch::new(scope);
-
-            let resolver = resolver.open(tc_scope);
-
-            let graph = graph_rc.borrow();
-            let module = graph.module.borrow();
-            if let Some(instance) = module.get(source) {
-                let expose = v8::Local::new(tc_scope, &instance.expose);
-                let obj = expose.to_object(tc_scope).unwrap();
-                resolver.resolve(tc_scope, obj.into());
-            };
-        };
+            .ok_or(anyhow!("module `{}` is not instantiated", source))?;
+        Ok(instance.expose.clone())
+    }
+}
 
-        let module = graph.module.borrow();
-        if module.get(source).is_none() {
-            let t = table.get(source).unwrap();
-            t.initialize(isolate)?;
-        }
+impl Runtime {
+    /// a promise settled with the output of `future`, converted by serde_v8
+    pub fn promise<'s, T, F>(
+        scope: &mut v8::HandleScope<'s>,
+        future: F,
+    ) -> v8::Local<'s, v8::Promise>
+    where
+        T: Serialize + 'static,
+        F: Future<Output = OpResult<T>> + 'static,
+    {
+        Self::promise_with(scope, async move {
+            let result = future.await;
+            settle(move |scope| match result {
+                Ok(value) => {
+                    serde_v8::to_v8(scope, value).map_err(|err| OpError::from(err).to_v8(scope))
+                }
+                Err(err) => Err(err.to_v8(scope)),
+            })
+        })
+    }
 
-        Ok(Poll::Ready(()))
+    /// a promise settled by the closure `future` resolves to, for values serde can not express
+    pub fn promise_with<'s, F>(
+        scope: &mut v8::HandleScope<'s>,
+        future: F,
+    ) -> v8::Local<'s, v8::Promise>
+    where
+        F: Future<Output = Settle> + 'static,
+    {
+        let resolver = v8::PromiseResolver::new(scope).unwrap();
+        let promise = resolver.get_promise(scope);
+        let resolver = v8::Global::new(scope, resolver);
+        Self::state(scope)
+            .borrow()
+            .pending_ops
+            .push(Box::pin(async move {
+                Poll::Ready(AsynchronousKind::Settle((resolver, future.await)))
+            }));
+        promise
     }
 }
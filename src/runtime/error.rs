@@ -0,0 +1,151 @@
+use std::{fmt::Display, io};
+
+/**
+# Op error
+
+an error of a native op, thrown into JS as an instance of its class
+
+`TypeError`, `RangeError` and `SyntaxError` are the builtin classes,
+`AbortError` is a `DOMException`, the other classes are looked up in
+`Edon.errors` and fall back to an `Error` named after the class
+*/
+#[derive(Debug, Clone)]
+pub struct OpError {
+    pub class: &'static str,
+    pub message: String,
+}
+
+pub type OpResult<T> = Result<T, OpError>;
+
+impl OpError {
+    pub fn new(class: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            class,
+            message: message.into(),
+        }
+    }
+
+    pub fn type_error(message: impl Into<String>) -> Self {
+        Self::new("TypeError", message)
+    }
+
+    pub fn range_error(message: impl Into<String>) -> Self {
+        Self::new("RangeError", message)
+    }
+
+    pub fn abort(message: impl Into<String>) -> Self {
+        Self::new("AbortError", message)
+    }
+
+    pub fn bad_resource(id: u32) -> Self {
+        Self::new("BadResource", format!("bad resource id {id}"))
+    }
+
+    /// an op that only exists on some platforms, e.g. `symlink` on unix
+    #[cfg_attr(unix, allow(dead_code))]
+    pub fn not_supported(op: &str) -> Self {
+        Self::new(
+            "NotSupported",
+            format!("{op} is not supported on this platform"),
+        )
+    }
+
+    pub fn to_v8<'s>(&self, scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Value> {
+        let message = v8::String::new(scope, &self.message).unwrap();
+        match self.class {
+            "TypeError" => return v8::Exception::type_error(scope, message),
+            "RangeError" => return v8::Exception::range_error(scope, message),
+            "SyntaxError" => return v8::Exception::syntax_error(scope, message),
+            _ => {}
+        }
+
+        let constructor = match self.class {
+            "AbortError" => lookup(scope, &["DOMException"]),
+            class => lookup(scope, &["Edon", "errors", class]),
+        };
+        if let Some(constructor) = constructor {
+            let class = v8::String::new(scope, self.class).unwrap();
+            let args = [message.into(), class.into()];
+            if let Some(error) = constructor.new_instance(scope, &args) {
+                return error.into();
+            }
+        }
+
+        let error = v8::Exception::error(scope, message);
+        if let Some(obj) = error.to_object(scope) {
+            let key = v8::String::new(scope, "name").unwrap();
+            let name = v8::String::new(scope, self.class).unwrap();
+            obj.set(scope, key.into(), name.into());
+        }
+        error
+    }
+
+    pub fn throw(&self, scope: &mut v8::HandleScope) {
+        let exception = self.to_v8(scope);
+        scope.throw_exception(exception);
+    }
+}
+
+/// a constructor reachable from the global object by `path`
+fn lookup<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    path: &[&str],
+) -> Option<v8::Local<'s, v8::Function>> {
+    let mut value: v8::Local<v8::Value> = scope.get_current_context().global(scope).into();
+    for name in path {
+        let key = v8::String::new(scope, name).unwrap();
+        value = value.to_object(scope)?.get(scope, key.into())?;
+    }
+    v8::Local::<v8::Function>::try_from(value).ok()
+}
+
+impl Display for OpError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.class, self.message)
+    }
+}
+
+impl From<io::Error> for OpError {
+    fn from(err: io::Error) -> Self {
+        use io::ErrorKind::*;
+        let class = match err.kind() {
+            NotFound => "NotFound",
+            PermissionDenied => "PermissionDenied",
+            AlreadyExists => "AlreadyExists",
+            ConnectionRefused => "ConnectionRefused",
+            ConnectionReset => "ConnectionReset",
+            ConnectionAborted => "ConnectionAborted",
+            NotConnected => "NotConnected",
+            AddrInUse => "AddrInUse",
+            AddrNotAvailable => "AddrNotAvailable",
+            BrokenPipe => "BrokenPipe",
+            InvalidInput => "TypeError",
+            InvalidData => "InvalidData",
+            TimedOut => "TimedOut",
+            Interrupted => "Interrupted",
+            WriteZero => "WriteZero",
+            UnexpectedEof => "UnexpectedEof",
+            IsADirectory => "IsADirectory",
+            NotADirectory => "NotADirectory",
+            DirectoryNotEmpty => "DirectoryNotEmpty",
+            Unsupported => "NotSupported",
+            _ => "Error",
+        };
+        Self::new(class, err.to_string())
+    }
+}
+
+impl From<anyhow::Error> for OpError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<io::Error>() {
+            Ok(err) => err.into(),
+            Err(err) => Self::new("Error", format!("{err:#}")),
+        }
+    }
+}
+
+impl From<serde_v8::Error> for OpError {
+    fn from(err: serde_v8::Error) -> Self {
+        Self::type_error(err.to_string())
+    }
+}
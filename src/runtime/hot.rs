@@ -0,0 +1,370 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    path::PathBuf,
+    task::Poll,
+};
+
+use anyhow::anyhow;
+use colored::Colorize;
+use futures::future::try_join_all;
+use tokio::sync::mpsc;
+
+use super::{AsynchronousKind, ModuleInstance, OpError, Runtime};
+use crate::compile::{self, ModuleDependency};
+
+/// what the versions of one module registered through `import.meta.hot`
+#[derive(Debug, Default)]
+struct HotModule {
+    /// `import.meta.hot.data`, the same object for every version of the module
+    data: Option<v8::Global<v8::Object>>,
+    /// set by `accept`, with the callback that receives the namespace of the next version
+    accept: Option<Option<v8::Global<v8::Function>>>,
+    dispose: Vec<v8::Global<v8::Function>>,
+}
+
+impl HotModule {
+    /// what the current version registered, the data stays for the next one
+    fn take(&mut self) -> Self {
+        Self {
+            data: self.data.clone(),
+            accept: self.accept.take(),
+            dispose: mem::take(&mut self.dispose),
+        }
+    }
+}
+
+/// hot module replacement for `edon run --hot`
+#[derive(Debug)]
+pub struct HotReload {
+    /// local modules that changed on disk
+    pub changes: mpsc::UnboundedReceiver<PathBuf>,
+    /// changes no module accepts, the program has to be restarted for them
+    declined: mpsc::UnboundedSender<PathBuf>,
+    modules: HashMap<String, HotModule>,
+}
+
+impl HotReload {
+    pub fn new(
+        changes: mpsc::UnboundedReceiver<PathBuf>,
+        declined: mpsc::UnboundedSender<PathBuf>,
+    ) -> Self {
+        Self {
+            changes,
+            declined,
+            modules: HashMap::new(),
+        }
+    }
+}
+
+/// run `f` on the hot state of `url`, `None` outside hot mode
+fn hot_module<R>(
+    isolate: &v8::Isolate,
+    url: &str,
+    f: impl FnOnce(&mut HotModule) -> R,
+) -> Option<R> {
+    let state_rc = Runtime::state(isolate);
+    let mut state = state_rc.borrow_mut();
+    let hot = state.hot.as_mut()?;
+    Some(f(hot.modules.entry(url.to_string()).or_default()))
+}
+
+/// `import.meta.hot.accept(callback?)`, the module takes its own updates and the callback
+/// is called with the namespace of the next version
+fn accept(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue) {
+    let url = args.data().to_rust_string_lossy(scope);
+    let callback = args.get(0);
+    let callback = match v8::Local::<v8::Function>::try_from(callback) {
+        Ok(callback) => Some(v8::Global::new(scope, callback)),
+        Err(_) if callback.is_undefined() => None,
+        Err(_) => return OpError::type_error("accept callback must be a function").throw(scope),
+    };
+    hot_module(scope, &url, |module| module.accept = Some(callback));
+}
+
+/// `import.meta.hot.dispose(callback)`, called with `data` before the next version runs
+fn dispose(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments, _rv: v8::ReturnValue) {
+    let url = args.data().to_rust_string_lossy(scope);
+    let Ok(callback) = v8::Local::<v8::Function>::try_from(args.get(0)) else {
+        return OpError::type_error("dispose callback must be a function").throw(scope);
+    };
+    let callback = v8::Global::new(scope, callback);
+    hot_module(scope, &url, |module| module.dispose.push(callback));
+}
+
+/// call a hot callback, what it throws is printed instead of stopping the update
+fn call<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    callback: &v8::Global<v8::Function>,
+    arg: v8::Local<'s, v8::Value>,
+) {
+    let tc_scope = &mut v8::TryCatch::new(scope);
+    let callback = v8::Local::new(tc_scope, callback);
+    let undefined = v8::undefined(tc_scope).into();
+    callback.call(tc_scope, undefined, &[arg]);
+    if let Some(exception) = tc_scope.exception() {
+        let message = exception.to_rust_string_lossy(tc_scope);
+        eprintln!("{} Uncaught {message}", "HMR".red());
+    }
+}
+
+impl Runtime {
+    /// `import.meta.hot` of every module, only in hot mode so that modules can test for it
+    pub extern "C" fn import_meta(
+        context: v8::Local<v8::Context>,
+        module: v8::Local<v8::Module>,
+        meta: v8::Local<v8::Object>,
+    ) {
+        let scope = &mut unsafe { v8::CallbackScope::new(context) };
+        if Self::state(scope).borrow().hot.is_none() {
+            return;
+        }
+        let url = {
+            let graph = Self::graph(scope);
+            let graph = graph.borrow();
+            let hash = graph.hash.borrow();
+            hash.get(&module.get_identity_hash()).cloned()
+        };
+        let Some(url) = url else {
+            return;
+        };
+
+        let data = match hot_module(scope, &url, |module| module.data.clone()).flatten() {
+            Some(data) => v8::Local::new(scope, data),
+            None => {
+                let data = v8::Object::new(scope);
+                let global = v8::Global::new(scope, data);
+                hot_module(scope, &url, |module| module.data = Some(global));
+                data
+            }
+        };
+        let name = v8::String::new(scope, &url).unwrap();
+        let accept = v8::Function::builder(accept)
+            .data(name.into())
+            .build(scope)
+            .unwrap();
+        let dispose = v8::Function::builder(dispose)
+            .data(name.into())
+            .build(scope)
+            .unwrap();
+
+        let hot = v8::Object::new(scope);
+        for (key, value) in [
+            ("data", v8::Local::<v8::Value>::from(data)),
+            ("accept", accept.into()),
+            ("dispose", dispose.into()),
+        ] {
+            let key = v8::String::new(scope, key).unwrap();
+            hot.set(scope, key.into(), value);
+        }
+        let key = v8::String::new(scope, "hot").unwrap();
+        meta.create_data_property(scope, key.into(), hot.into());
+    }
+
+    /// recompile the module at `path` and load what the new version imports as a pending op,
+    /// which then replaces the module, or ask for a restart when the module is not in the graph
+    pub(super) fn hot_update(isolate: &mut v8::Isolate, path: PathBuf) {
+        let url = path.to_string_lossy().into_owned();
+        let table_rc = Self::graph(isolate).borrow().table.clone();
+        let table = table_rc.borrow();
+        if table.get(&url).is_none() {
+            return Self::hot_decline(isolate, &url);
+        }
+        let dep = std::fs::read_to_string(&url)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| compile::compile(&url, &source, &table.config().compiler_options));
+        let dep = match dep {
+            Ok(dep) => dep,
+            Err(err) => return eprintln!("{} Replacing {url} failed: {err:?}", "HMR".red()),
+        };
+        let loads = dep
+            .deps
+            .iter()
+            .map(|source| table.fetch(source, &url))
+            .collect::<Vec<_>>();
+        Self::state(isolate)
+            .borrow()
+            .pending_ops
+            .push(Box::pin(async move {
+                let loaded = try_join_all(loads)
+                    .await
+                    .map(|loaded| loaded.into_iter().flatten().collect());
+                Poll::Ready(AsynchronousKind::Hot((url, dep, loaded)))
+            }));
+    }
+
+    /// replace a module once the modules its new version imports are loaded
+    pub(super) fn hot_replace(
+        isolate: &mut v8::Isolate,
+        url: String,
+        dep: ModuleDependency,
+        loaded: anyhow::Result<Vec<ModuleDependency>>,
+    ) {
+        let replaced = loaded.and_then(|modules| {
+            Self::graph(isolate)
+                .borrow()
+                .table
+                .borrow_mut()
+                .extend(modules);
+            Self::replace_module(isolate, &url, dep)
+        });
+        match replaced {
+            Ok(true) => println!("{} Replaced {url}", "HMR".green()),
+            Ok(false) => Self::hot_decline(isolate, &url),
+            Err(err) => eprintln!("{} Replacing {url} failed: {err:?}", "HMR".red()),
+        }
+    }
+
+    fn hot_decline(isolate: &v8::Isolate, url: &str) {
+        if let Some(hot) = &Self::state(isolate).borrow().hot {
+            let _ = hot.declined.send(PathBuf::from(url));
+        }
+    }
+
+    /// the modules to instantiate again for a change of `url`, it and its importers up to
+    /// the modules accepting updates, which are returned as well, `None` once an importer
+    /// chain ends in a module that does not accept
+    fn hot_boundaries(isolate: &v8::Isolate, url: &str) -> Option<(HashSet<String>, Vec<String>)> {
+        let state_rc = Self::state(isolate);
+        let graph_rc = Self::graph(isolate);
+        let state = state_rc.borrow();
+        let hot = state.hot.as_ref()?;
+        let graph = graph_rc.borrow();
+        let table = graph.table.borrow();
+        let instances = graph.module.borrow();
+
+        let mut invalidated = HashSet::new();
+        let mut boundaries = vec![];
+        let mut queue = vec![url.to_string()];
+        while let Some(url) = queue.pop() {
+            if !invalidated.insert(url.clone()) {
+                continue;
+            }
+            if hot
+                .modules
+                .get(&url)
+                .is_some_and(|module| module.accept.is_some())
+            {
+                boundaries.push(url);
+                continue;
+            }
+            let importers = table
+                .iter()
+                .filter(|(filename, dep)| {
+                    instances.contains_key(*filename)
+                        && dep
+                            .deps
+                            .iter()
+                            .any(|source| table.resolve(source, &dep.filename) == url)
+                })
+                .map(|(filename, _)| filename.clone())
+                .collect::<Vec<_>>();
+            if importers.is_empty() {
+                return None;
+            }
+            queue.extend(importers);
+        }
+        Some((invalidated, boundaries))
+    }
+
+    /// run the new version `dep` of `url` and of its importers up to the accepting modules,
+    /// `false` when the change is declined
+    fn replace_module(
+        isolate: &mut v8::Isolate,
+        url: &str,
+        dep: ModuleDependency,
+    ) -> anyhow::Result<bool> {
+        let state_rc = Self::state(isolate);
+        let graph_rc = Self::graph(isolate);
+        let table_rc = graph_rc.borrow().table.clone();
+        let Some((invalidated, boundaries)) = Self::hot_boundaries(isolate, url) else {
+            return Ok(false);
+        };
+        let previous = table_rc.borrow_mut().replace(dep);
+
+        // v8 modules can not change, new versions are instantiated beside the old ones and
+        // importers link to them through the module map
+        let old = {
+            let graph = graph_rc.borrow();
+            let mut instances = graph.module.borrow_mut();
+            invalidated
+                .iter()
+                .filter_map(|url| Some((url.clone(), instances.remove(url)?)))
+                .collect::<Vec<_>>()
+        };
+        // put the previous version back when the new one fails
+        let restore = |old: Vec<(String, ModuleInstance)>, previous: Option<ModuleDependency>| {
+            let graph = graph_rc.borrow();
+            let mut instances = graph.module.borrow_mut();
+            instances.retain(|url, _| !invalidated.contains(url));
+            instances.extend(old);
+            if let Some(previous) = previous {
+                table_rc.borrow_mut().replace(previous);
+            }
+        };
+        let instantiated = boundaries
+            .iter()
+            .try_for_each(|url| match table_rc.borrow().get(url) {
+                Some(dep) => dep.initialize(isolate),
+                None => Err(anyhow!("module `{url}` is not in the graph")),
+            });
+        if let Err(err) = instantiated {
+            restore(old, previous);
+            return Err(err);
+        }
+
+        let records = invalidated
+            .iter()
+            .filter_map(|url| Some((url.clone(), hot_module(isolate, url, HotModule::take)?)))
+            .collect::<Vec<_>>();
+        let context = state_rc.borrow().context.clone();
+        {
+            let scope = &mut v8::HandleScope::with_context(isolate, context.clone());
+            for (_, record) in &records {
+                let data = match &record.data {
+                    Some(data) => v8::Local::new(scope, data).into(),
+                    None => v8::undefined(scope).into(),
+                };
+                for callback in &record.dispose {
+                    call(scope, callback, data);
+                }
+            }
+        }
+
+        let evaluated = boundaries
+            .iter()
+            .try_for_each(|url| match table_rc.borrow().get(url) {
+                Some(dep) => dep.evaluate(isolate),
+                None => Err(anyhow!("module `{url}` is not in the graph")),
+            });
+        if let Err(err) = evaluated {
+            restore(old, previous);
+            // the old versions accept the next change again, what the new ones registered
+            // before failing is dropped
+            for (url, record) in records {
+                hot_module(isolate, &url, |module| {
+                    module.accept = record.accept;
+                    module.dispose.clear();
+                });
+            }
+            return Err(err);
+        }
+
+        let scope = &mut v8::HandleScope::with_context(isolate, context);
+        for (url, record) in records {
+            let Some(Some(callback)) = record.accept else {
+                continue;
+            };
+            let namespace = {
+                let graph = graph_rc.borrow();
+                let instances = graph.module.borrow();
+                match instances.get(&url) {
+                    Some(instance) => v8::Local::new(scope, &instance.expose),
+                    None => continue,
+                }
+            };
+            call(scope, &callback, namespace);
+        }
+        Ok(true)
+    }
+}
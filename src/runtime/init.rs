@@ -1,5 +1,8 @@
 use crate::builtin::console::log;
-use crate::builtin::fetch::fetch;
+use crate::builtin::{
+    command, dns, edon_fs, encoding, fetch, net, process, resources, serve, signal, tls, url,
+    watcher, websocket,
+};
 
 use super::Runtime;
 
@@ -11,8 +14,6 @@ impl Runtime {
         let global = context.global(scope);
         let scope = &mut v8::ContextScope::new(scope, context);
 
-        Self::set_func(scope, global, "fetch", fetch);
-
         let console_key = v8::String::new(scope, "console").unwrap();
         let console_object = v8::Object::new(scope);
         global.set(scope, console_key.into(), console_object.into());
@@ -22,9 +23,53 @@ impl Runtime {
         Self::set_func(scope, console_object, "error", log);
         Self::set_func(scope, console_object, "warn", log);
 
+        let edon = process::init(scope);
+        Self::set_obj(scope, global, "Edon", edon);
+
         scope.escape(context)
     }
 
+    /// the `this` of every bootstrap module, native ops grouped by module,
+    /// `internal` is shared between the modules and never exposed to scripts
+    pub fn init_ops<'s>(scope: &mut v8::HandleScope<'s>) -> v8::Local<'s, v8::Object> {
+        let ops = v8::Object::new(scope);
+
+        let timer = v8::Object::new(scope);
+        Self::set_func(scope, timer, "send", Self::timer_send);
+        Self::set_obj(scope, ops, "timer", timer);
+
+        let core = resources::init(scope);
+        Self::set_obj(scope, ops, "core", core);
+        let encoding = encoding::init(scope);
+        Self::set_obj(scope, ops, "encoding", encoding);
+        let url = url::init(scope);
+        Self::set_obj(scope, ops, "url", url);
+        let fetch = fetch::init(scope);
+        Self::set_obj(scope, ops, "fetch", fetch);
+        let fs = edon_fs::init(scope);
+        Self::set_obj(scope, ops, "fs", fs);
+        let watcher = watcher::init(scope);
+        Self::set_obj(scope, ops, "watcher", watcher);
+        let net = net::init(scope);
+        Self::set_obj(scope, ops, "net", net);
+        let command = command::init(scope);
+        Self::set_obj(scope, ops, "command", command);
+        let dns = dns::init(scope);
+        Self::set_obj(scope, ops, "dns", dns);
+        let signal = signal::init(scope);
+        Self::set_obj(scope, ops, "signal", signal);
+        let tls = tls::init(scope);
+        Self::set_obj(scope, ops, "tls", tls);
+        let serve = serve::init(scope);
+        Self::set_obj(scope, ops, "serve", serve);
+        let websocket = websocket::init(scope);
+        Self::set_obj(scope, ops, "websocket", websocket);
+
+        let internal = v8::Object::new(scope);
+        Self::set_obj(scope, ops, "internal", internal);
+        ops
+    }
+
     pub fn set_func(
         scope: &mut v8::HandleScope,
         obj: v8::Local<v8::Object>,
@@ -1,19 +1,25 @@
 use std::{
-    cell::RefCell, collections::HashMap, ffi::c_void, future::poll_fn, num::NonZeroI32,
-    path::PathBuf, pin::Pin, rc::Rc, task::Poll,
+    cell::RefCell, collections::HashMap, ffi::c_void, future::poll_fn, num::NonZeroI32, pin::Pin,
+    rc::Rc, sync::Once, task::Poll,
 };
 
-use crate::{compile, graph::DependencyGraph};
+use crate::{compile, config::V8Options, graph::DependencyGraph, permissions::Permissions};
 use futures::{stream::FuturesUnordered, Future, StreamExt};
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use v8::{Isolate, OwnedIsolate};
 
 mod asynchronous;
 mod constants;
+mod error;
+mod hot;
 mod init;
+mod resource;
 mod static_fn;
 
-pub use asynchronous::AsynchronousKind;
+pub use asynchronous::{settle, AsynchronousKind, Settle};
+pub use error::{OpError, OpResult};
+pub use hot::HotReload;
+pub use resource::{CancelHandle, Resource, ResourceId, ResourceTable};
 type Async = Pin<Box<dyn Future<Output = Poll<AsynchronousKind>>>>;
 
 #[derive(Debug)]
@@ -35,6 +41,13 @@ pub struct RuntimeState {
     pub pending_ops: FuturesUnordered<Async>,
     pub sender: Sender<usize>,
     pub receiver: Receiver<usize>,
+    pub exit_code: i32,
+    /// rejected promises without a handler, keyed by promise identity hash
+    pub rejections: HashMap<NonZeroI32, v8::Global<v8::Value>>,
+    pub permissions: Permissions,
+    pub resources: ResourceTable,
+    /// set for `edon run --hot`
+    pub hot: Option<HotReload>,
 }
 /**
 # Ts Runtime
@@ -42,18 +55,83 @@ pub struct RuntimeState {
 pub struct Runtime {
     pub isolate: v8::OwnedIsolate,
     pub sender: Sender<usize>,
+    pub args: Vec<String>,
 }
 
+static V8_INIT: Once = Once::new();
+
+const BOOTSTRAP_MAIN: &str = "bootstrap.ts";
+
+/// bootstrap modules in the order they run, each one installs its globals from the
+/// default export, which is called with the ops object as `this`
+const BOOTSTRAP: &[(&str, &str)] = &[
+    (
+        "bootstrap/errors.ts",
+        include_str!("../../bootstrap/errors.ts"),
+    ),
+    (
+        "bootstrap/web/event.ts",
+        include_str!("../../bootstrap/web/event.ts"),
+    ),
+    (
+        "bootstrap/web/encoding.ts",
+        include_str!("../../bootstrap/web/encoding.ts"),
+    ),
+    (
+        "bootstrap/web/streams.ts",
+        include_str!("../../bootstrap/web/streams.ts"),
+    ),
+    (
+        "bootstrap/web/url.ts",
+        include_str!("../../bootstrap/web/url.ts"),
+    ),
+    (
+        "bootstrap/web/form.ts",
+        include_str!("../../bootstrap/web/form.ts"),
+    ),
+    (
+        "bootstrap/web/fetch.ts",
+        include_str!("../../bootstrap/web/fetch.ts"),
+    ),
+    (
+        "bootstrap/web/websocket.ts",
+        include_str!("../../bootstrap/web/websocket.ts"),
+    ),
+    ("bootstrap/fs.ts", include_str!("../../bootstrap/fs.ts")),
+    ("bootstrap/net.ts", include_str!("../../bootstrap/net.ts")),
+    (
+        "bootstrap/command.ts",
+        include_str!("../../bootstrap/command.ts"),
+    ),
+    (
+        "bootstrap/signal.ts",
+        include_str!("../../bootstrap/signal.ts"),
+    ),
+    (
+        "bootstrap/serve.ts",
+        include_str!("../../bootstrap/serve.ts"),
+    ),
+    (BOOTSTRAP_MAIN, include_str!("../../bootstrap/main.ts")),
+];
+
 impl Runtime {
-    fn isolate() -> (OwnedIsolate, v8::Global<v8::Context>) {
-        let platform = v8::new_default_platform(2, true).make_shared();
-        v8::V8::initialize_platform(platform);
-        v8::V8::initialize();
+    fn isolate(options: &V8Options) -> (OwnedIsolate, v8::Global<v8::Context>) {
+        // the platform can only be initialized once per process
+        V8_INIT.call_once(|| {
+            if !options.flags.is_empty() {
+                v8::V8::set_flags_from_string(&options.flags.join(" "));
+            }
+            let platform = v8::new_default_platform(options.threads, true).make_shared();
+            v8::V8::initialize_platform(platform);
+            v8::V8::initialize();
+        });
 
         let params = v8::CreateParams::default();
         let mut isolate = v8::Isolate::new(params);
 
         isolate.set_host_import_module_dynamically_callback(Self::dynamically_import);
+        isolate.set_promise_reject_callback(Self::promise_reject);
+        isolate.set_host_initialize_import_meta_object_callback(Self::import_meta);
 
         let global_context = {
             let scope = &mut v8::HandleScope::new(isolate.as_mut());
@@ -64,7 +142,7 @@ impl Runtime {
         (isolate, global_context)
     }
     pub fn from(graph: DependencyGraph) -> Self {
-        let (mut isolate, global_context) = Self::isolate();
+        let (mut isolate, global_context) = Self::isolate(&graph.config().v8);
 
         let (sender, receiver) = mpsc::channel::<usize>(1024);
 
@@ -75,6 +153,11 @@ impl Runtime {
                 sender: sender.clone(),
                 receiver,
                 pending_ops: FuturesUnordered::new(),
+                exit_code: 0,
+                rejections: HashMap::new(),
+                permissions: Permissions::default(),
+                resources: ResourceTable::default(),
+                hot: None,
             }))) as *mut c_void,
         );
 
@@ -87,7 +170,29 @@ impl Runtime {
             }))) as *mut c_void,
         );
 
-        Self { isolate, sender }
+        Self {
+            isolate,
+            sender,
+            args: vec![],
+        }
+    }
+
+    /// arguments forwarded to the script
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// nothing outside the runtime is reachable without permissions
+    pub fn with_permissions(self, permissions: Permissions) -> Self {
+        Self::state(&self.isolate).borrow_mut().permissions = permissions;
+        self
+    }
+
+    /// replace changed modules while the program runs instead of restarting it
+    pub fn with_hot(self, hot: HotReload) -> Self {
+        Self::state(&self.isolate).borrow_mut().hot = Some(hot);
+        self
     }
 
     pub fn state(isolate: &Isolate) -> Rc<RefCell<RuntimeState>> {
@@ -110,92 +215,156 @@ impl Runtime {
         state
     }
 
-    fn bootstrap(&mut self, entry: &String) -> anyhow::Result<()> {
+    fn bootstrap(&mut self, entry: Option<&String>) -> anyhow::Result<()> {
         let isolate = self.isolate.as_mut();
         let state_rc = Self::state(isolate);
         let graph_rc = Self::graph(isolate);
 
-        // bootstrap.js
-        let mut bootstrap_js = PathBuf::new();
-        bootstrap_js.push("bootstrap.ts");
-        let dependency = compile::compile(
-            bootstrap_js.to_string_lossy().as_ref(),
-            &include_str!("../../bootstrap/main.ts").to_string(),
-        )?;
-
-        dependency.initialize(isolate)?;
-        dependency.evaluate(isolate)?;
-
-        //
-        let graph = graph_rc.borrow();
-        let module = graph.module.borrow();
-        let info = module
-            .get(&bootstrap_js.to_string_lossy().to_string())
-            .unwrap();
-
-        let state = state_rc.borrow();
-        let scope = &mut v8::HandleScope::with_context(isolate, &state.context);
+        for (filename, source) in BOOTSTRAP {
+            let dependency = compile::compile(filename, source, &Default::default())?;
+            dependency.initialize(isolate)?;
+            dependency.evaluate(isolate)?;
+        }
 
-        let expose = v8::Local::new(scope, &info.expose);
-        let obj = expose.to_object(scope).unwrap();
+        let context = state_rc.borrow().context.clone();
+        let scope = &mut v8::HandleScope::with_context(isolate, context);
+
+        // the default export of every module, in order
+        let defaults = {
+            let graph = graph_rc.borrow();
+            let module = graph.module.borrow();
+            BOOTSTRAP
+                .iter()
+                .map(|(filename, _)| {
+                    let info = module.get(*filename).unwrap();
+                    let expose = v8::Local::new(scope, &info.expose);
+                    let obj = expose.to_object(scope).unwrap();
+                    let default = v8::String::new(scope, "default").unwrap();
+                    let default = obj.get(scope, default.into()).unwrap();
+                    (
+                        *filename,
+                        v8::Local::<v8::Function>::try_from(default).unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let tc_scope = &mut v8::TryCatch::new(scope);
 
-        let default = v8::String::new(scope, "default").unwrap();
-        let default = obj.get(scope, default.into()).unwrap();
+        let this = Self::init_ops(tc_scope);
+
+        let args = self
+            .args
+            .iter()
+            .map(|arg| v8::String::new(tc_scope, arg).unwrap().into())
+            .collect::<Vec<_>>();
+        let args = v8::Array::new_with_elements(tc_scope, &args);
+        args.set_integrity_level(tc_scope, v8::IntegrityLevel::Frozen);
+        let global = tc_scope.get_current_context().global(tc_scope);
+        let edon = v8::String::new(tc_scope, "Edon").unwrap();
+        let edon = global.get(tc_scope, edon.into()).unwrap();
+        let edon = edon.to_object(tc_scope).unwrap();
+        Self::set_obj(tc_scope, edon, "args", args.into());
+
+        let entry = match entry {
+            Some(entry) => v8::String::new(tc_scope, entry).unwrap().into(),
+            None => v8::undefined(tc_scope).into(),
+        };
+        for (filename, fun) in defaults {
+            // only the main module takes the entry, its rejections are reported by the event loop
+            if filename == BOOTSTRAP_MAIN {
+                fun.call(tc_scope, this.into(), &[entry]);
+                continue;
+            }
+            fun.call(tc_scope, this.into(), &[]);
+            if let Some(exception) = tc_scope.exception() {
+                let message = exception.to_rust_string_lossy(tc_scope);
+                return Err(anyhow::anyhow!("bootstrap {filename} failed: {message}"));
+            }
+        }
+        Ok(())
+    }
 
-        let fun = v8::Local::<v8::Function>::try_from(default).unwrap();
-        let tc_scope = &mut v8::TryCatch::new(scope);
+    /// run the entry until the event loop is drained, returns the process exit code
+    pub async fn run(&mut self, entry: &String) -> anyhow::Result<i32> {
+        self.bootstrap(Some(entry))?;
+        self.run_event_loop().await
+    }
 
-        let this = v8::Object::new(tc_scope);
-        let timer = v8::Object::new(tc_scope);
+    /// bootstrap the global scope without an entry module, e.g. for the repl
+    pub fn prepare(&mut self) -> anyhow::Result<()> {
+        self.bootstrap(None)
+    }
 
-        Self::set_func(tc_scope, timer, "send", Self::timer_send);
-        Self::set_obj(tc_scope, this, "timer", timer);
+    pub async fn run_event_loop(&mut self) -> anyhow::Result<i32> {
+        let state_rc = Self::state(&self.isolate);
 
-        let entry = v8::String::new(tc_scope, &entry).unwrap();
-        fun.call(tc_scope, this.into(), &[entry.into()]);
-        Ok(())
+        loop {
+            if Self::report_rejection(&mut self.isolate) {
+                break Ok(1);
+            }
+            if !self.has_pending_ops() {
+                break Ok(state_rc.borrow().exit_code);
+            }
+            self.tick().await;
+        }
     }
 
-    pub async fn run(&mut self, entry: &String) -> anyhow::Result<()> {
-        self.bootstrap(entry)?;
+    pub fn has_pending_ops(&self) -> bool {
+        !Self::state(&self.isolate).borrow().pending_ops.is_empty()
+    }
 
+    /// wait for the next pending op and execute it
+    pub async fn tick(&mut self) {
         let isolate = self.isolate.as_mut();
         let state_rc = Self::state(isolate);
 
-        loop {
-            if {
-                let state = state_rc.borrow();
-                state.pending_ops.is_empty()
-            } {
-                break Ok(());
+        poll_fn(|cx| {
+            let change = state_rc
+                .borrow_mut()
+                .hot
+                .as_mut()
+                .map(|hot| hot.changes.poll_recv(cx));
+            if let Some(Poll::Ready(Some(path))) = change {
+                Self::hot_update(isolate, path);
+                isolate.perform_microtask_checkpoint();
+                return Poll::Ready(());
             }
-            poll_fn(|cx| loop {
-                let result = {
-                    let mut state = state_rc.borrow_mut();
-                    let result = state.pending_ops.poll_next_unpin(cx);
-                    if Poll::Pending == result {
-                        continue;
-                    }
-                    result
-                };
-                if let Poll::Ready(Some(Poll::Ready(op))) = result {
-                    match op.exec(isolate) {
-                        Ok(v) => break v,
-                        Err(err) => eprintln!("{err:?}"),
+            let result = {
+                let mut state = state_rc.borrow_mut();
+                state.pending_ops.poll_next_unpin(cx)
+            };
+            match result {
+                Poll::Ready(Some(Poll::Ready(op))) => {
+                    let result = op.exec(isolate);
+                    isolate.perform_microtask_checkpoint();
+                    match result {
+                        Ok(v) => v,
+                        Err(err) => {
+                            eprintln!("{err:?}");
+                            state_rc.borrow_mut().exit_code = 1;
+                            Poll::Ready(())
+                        }
                     }
-                    break Poll::Ready(());
                 }
-                break Poll::Pending;
-            })
-            .await;
-        }
+                Poll::Ready(_) => Poll::Ready(()),
+                Poll::Pending => Poll::Pending,
+            }
+        })
+        .await
     }
+}
 
-    async fn import(isolate: &mut Isolate, source: &String, base: &String) -> anyhow::Result<()> {
-        let graph_rc = Self::graph(isolate);
-        let graph = graph_rc.borrow();
-        let mut table = graph.table.borrow_mut();
-
-        table.append(source, base).await
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        // release the slots before the isolate, they hold v8::Global handles
+        let isolate = self.isolate.as_mut();
+        unsafe {
+            let state =
+                isolate.get_data(constants::ASYNC_STATE_SLOT) as *const RefCell<RuntimeState>;
+            drop(Rc::from_raw(state));
+            let graph =
+                isolate.get_data(constants::ASYNC_GRAPH_SLOT) as *const RefCell<RuntimeGraph>;
+            drop(Rc::from_raw(graph));
+        }
     }
 }
@@ -0,0 +1,106 @@
+use std::{any::Any, cell::Cell, collections::BTreeMap, future::Future, rc::Rc};
+
+use tokio::sync::Notify;
+
+pub type ResourceId = u32;
+
+/// a native object owned by the runtime and referenced from JS by its id
+pub trait Resource: Any {
+    fn name(&self) -> &'static str;
+
+    /// called when the resource is closed from JS, pending ops holding a clone
+    /// of the resource should fail or finish early
+    fn close(self: Rc<Self>) {}
+}
+
+/**
+# Resource table
+
+async ops capture an `Rc` of their resource so that the table itself is never
+borrowed while a future is polled
+*/
+#[derive(Default)]
+pub struct ResourceTable {
+    next_id: ResourceId,
+    resources: BTreeMap<ResourceId, Rc<dyn Resource>>,
+}
+
+impl std::fmt::Debug for ResourceTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.resources.iter().map(|(id, r)| (id, r.name())))
+            .finish()
+    }
+}
+
+impl ResourceTable {
+    pub fn add<T: Resource>(&mut self, resource: T) -> ResourceId {
+        self.add_rc(Rc::new(resource))
+    }
+
+    pub fn add_rc<T: Resource>(&mut self, resource: Rc<T>) -> ResourceId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.resources.insert(id, resource);
+        id
+    }
+
+    pub fn get<T: Resource>(&self, id: ResourceId) -> Option<Rc<T>> {
+        let resource: Rc<dyn Any> = self.resources.get(&id)?.clone();
+        resource.downcast().ok()
+    }
+
+    /// remove a resource without closing it, e.g. to wrap it in another one
+    pub fn take<T: Resource>(&mut self, id: ResourceId) -> Option<Rc<T>> {
+        let resource = self.get::<T>(id)?;
+        self.resources.remove(&id);
+        Some(resource)
+    }
+
+    pub fn close(&mut self, id: ResourceId) -> bool {
+        match self.resources.remove(&id) {
+            Some(resource) => {
+                resource.close();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// aborts the futures it runs once it is canceled, closing it from JS cancels it
+#[derive(Debug, Default)]
+pub struct CancelHandle {
+    canceled: Cell<bool>,
+    notify: Notify,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.canceled.set(true);
+        self.notify.notify_waiters();
+    }
+
+    /// the output of `future`, `None` when the handle is canceled first
+    pub async fn run<F: Future>(&self, future: F) -> Option<F::Output> {
+        // registered before the check, a cancel in between still wakes it up
+        let notified = self.notify.notified();
+        if self.canceled.get() {
+            return None;
+        }
+        tokio::select! {
+            _ = notified => None,
+            output = future => Some(output),
+        }
+    }
+}
+
+impl Resource for CancelHandle {
+    fn name(&self) -> &'static str {
+        "cancelHandle"
+    }
+
+    fn close(self: Rc<Self>) {
+        self.cancel();
+    }
+}
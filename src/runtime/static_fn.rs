@@ -1,7 +1,6 @@
 use super::{asynchronous::AsynchronousKind, Runtime};
-use crate::{builtin::console::console_format, graph::resolve};
+use crate::builtin::console::console_format;
 use std::{task::Poll, time::Duration};
-use url::Url;
 
 impl Runtime {
     pub fn resolve_module_callback<'s>(
@@ -18,18 +17,12 @@ impl Runtime {
 
         let source = source.to_rust_string_lossy(scope);
 
-        let url = if source.starts_with("http") {
-            let url = Url::parse(&source).expect(format!("parse url failed: {}", source).as_str());
-            let url = url.join(&source).unwrap();
-            let url = url.as_str().to_string();
-            url
-        } else {
+        let url = {
             let module_id = referrer.get_identity_hash();
 
             let hash = state.hash.borrow();
             let url = hash.get(&module_id).unwrap();
-            resolve(&source, url)
-            // url.clone()
+            state.table.borrow().resolve(&source, url)
         };
 
         let module = state.module.borrow();
@@ -49,8 +42,17 @@ impl Runtime {
         _import_assertions: v8::Local<'a, v8::FixedArray>,
     ) -> Option<v8::Local<'a, v8::Promise>> {
         let state_rc = Self::state(scope);
+        let graph_rc = Self::graph(scope);
         let resource = resource.to_rust_string_lossy(scope).to_string();
         let source = source.to_rust_string_lossy(scope).to_string();
+        let (url, load) = {
+            let graph = graph_rc.borrow();
+            let table = graph.table.borrow();
+            (
+                table.resolve(&source, &resource),
+                table.fetch(&source, &resource),
+            )
+        };
 
         let resolver = v8::PromiseResolver::new(scope).unwrap();
         let promise = resolver.get_promise(scope);
@@ -59,27 +61,53 @@ impl Runtime {
         let state = state_rc.borrow();
 
         state.pending_ops.push(Box::pin(async move {
-            Poll::Ready(AsynchronousKind::Import((
-                resolve(&source, &resource),
-                resolver,
-            )))
+            Poll::Ready(AsynchronousKind::Import((url, resolver, load.await)))
         }));
 
-        let builder = v8::FunctionBuilder::new(Runtime::promise_error);
-        let err_handler = v8::FunctionBuilder::<v8::Function>::build(builder, scope).unwrap();
+        Some(promise)
+    }
 
-        promise.catch(scope, err_handler)
+    pub extern "C" fn promise_reject(message: v8::PromiseRejectMessage) {
+        let scope = &mut unsafe { v8::CallbackScope::new(&message) };
+        let state_rc = Self::state(scope);
+        let promise = message.get_promise();
+        let id = promise.get_identity_hash();
+
+        match message.get_event() {
+            v8::PromiseRejectEvent::PromiseRejectWithNoHandler => {
+                let reason = message
+                    .get_value()
+                    .unwrap_or_else(|| v8::undefined(scope).into());
+                let reason = v8::Global::new(scope, reason);
+                state_rc.borrow_mut().rejections.insert(id, reason);
+            }
+            v8::PromiseRejectEvent::PromiseHandlerAddedAfterReject => {
+                state_rc.borrow_mut().rejections.remove(&id);
+            }
+            _ => {}
+        }
     }
 
-    pub fn promise_error(
-        scope: &mut v8::HandleScope,
-        info: v8::FunctionCallbackArguments,
-        mut rv: v8::ReturnValue,
-    ) {
-        let error = console_format(scope, &info.get(0), 0);
-        println!("Uncaught {error}");
-        rv.set(v8::Number::new(scope, 0f64).into())
+    /// print the first unhandled rejection, returns whether there was one
+    pub fn report_rejection(isolate: &mut v8::Isolate) -> bool {
+        let state_rc = Self::state(isolate);
+        let (context, rejection) = {
+            let mut state = state_rc.borrow_mut();
+            let Some(id) = state.rejections.keys().next().copied() else {
+                return false;
+            };
+            (state.context.clone(), state.rejections.remove(&id))
+        };
+        let Some(reason) = rejection else {
+            return false;
+        };
+        let scope = &mut v8::HandleScope::with_context(isolate, context);
+        let reason = v8::Local::new(scope, reason);
+        let error = console_format(scope, &reason, 0);
+        eprintln!("Uncaught (in promise) {error}");
+        true
     }
+
     pub fn timer_send(
         scope: &mut v8::HandleScope,
         info: v8::FunctionCallbackArguments,
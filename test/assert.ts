@@ -0,0 +1,107 @@
+// assertions shared by the `*_test.ts` files, run them all with `edon test -A test`
+// a failed assertion throws, which makes `edon test` report the file as failed
+
+export class AssertionError extends Error {
+  constructor(message: string) {
+    super(message)
+    this.name = "AssertionError"
+  }
+}
+
+function format(value: unknown): string {
+  if (value instanceof Uint8Array) {
+    return `Uint8Array [${value.join(", ")}]`
+  }
+  if (typeof value === "string") {
+    return JSON.stringify(value)
+  }
+  try {
+    return JSON.stringify(value) ?? String(value)
+  } catch {
+    return String(value)
+  }
+}
+
+function equal(a: unknown, b: unknown): boolean {
+  if (Object.is(a, b)) {
+    return true
+  }
+  if (a instanceof Date && b instanceof Date) {
+    return a.getTime() === b.getTime()
+  }
+  if (ArrayBuffer.isView(a) && ArrayBuffer.isView(b)) {
+    const x = new Uint8Array(a.buffer, a.byteOffset, a.byteLength)
+    const y = new Uint8Array(b.buffer, b.byteOffset, b.byteLength)
+    return x.length === y.length && x.every((byte, i) => byte === y[i])
+  }
+  if (typeof a !== "object" || typeof b !== "object" || a === null || b === null) {
+    return false
+  }
+  if (Array.isArray(a) !== Array.isArray(b)) {
+    return false
+  }
+  const keys = Object.keys(a)
+  if (keys.length !== Object.keys(b).length) {
+    return false
+  }
+  return keys.every((key) => equal((a as any)[key], (b as any)[key]))
+}
+
+export function assert(condition: unknown, message = "assertion failed"): asserts condition {
+  if (!condition) {
+    throw new AssertionError(message)
+  }
+}
+
+// deep equality of plain objects, arrays, typed arrays and dates
+export function assertEquals(actual: unknown, expected: unknown, message?: string) {
+  if (!equal(actual, expected)) {
+    throw new AssertionError(
+      `${message ? `${message}: ` : ""}expected ${format(expected)}, got ${format(actual)}`,
+    )
+  }
+}
+
+// `fn` must reject, with an instance of `ErrorClass` when given, returns the error
+export async function assertRejects(
+  fn: () => Promise<unknown>,
+  ErrorClass?: new (...args: any[]) => Error,
+  includes?: string,
+): Promise<Error> {
+  try {
+    await fn()
+  } catch (error) {
+    if (ErrorClass && !(error instanceof ErrorClass)) {
+      throw new AssertionError(`expected ${ErrorClass.name}, got ${format(String(error))}`)
+    }
+    if (includes && !String((error as Error).message).includes(includes)) {
+      throw new AssertionError(
+        `expected the message to include ${format(includes)}, got ${format((error as Error).message)}`,
+      )
+    }
+    return error as Error
+  }
+  throw new AssertionError("expected the promise to reject")
+}
+
+// synchronous counterpart of [`assertRejects`]
+export function assertThrows(
+  fn: () => unknown,
+  ErrorClass?: new (...args: any[]) => Error,
+  includes?: string,
+): Error {
+  try {
+    fn()
+  } catch (error) {
+    if (ErrorClass && !(error instanceof ErrorClass)) {
+      throw new AssertionError(`expected ${ErrorClass.name}, got ${format(String(error))}`)
+    }
+    if (includes && !String((error as Error).message).includes(includes)) {
+      throw new AssertionError(
+        `expected the message to include ${format(includes)}, got ${format((error as Error).message)}`,
+      )
+    }
+    return error as Error
+  }
+  throw new AssertionError("expected the function to throw")
+}
@@ -0,0 +1,56 @@
+// edon test --allow-run test/command_test.ts
+import { assert, assertEquals, assertRejects, assertThrows } from "./assert.ts"
+
+const decoder = new TextDecoder()
+
+const output = await new Edon.Command("echo", { args: ["hello", "world"] }).output()
+assert(output.success)
+assertEquals(output.code, 0)
+assertEquals(output.signal, null)
+assertEquals(decoder.decode(output.stdout), "hello world\n")
+assertEquals(output.stderr, new Uint8Array())
+
+const failed = await new Edon.Command("sh", { args: ["-c", "echo oops >&2; exit 3"] }).output()
+assert(!failed.success)
+assertEquals(failed.code, 3)
+assertEquals(decoder.decode(failed.stderr), "oops\n")
+
+// the environment is added to the one of the runtime, `cwd` changes the directory
+const env = await new Edon.Command("sh", {
+  args: ["-c", "echo $GREETING from $(pwd)"],
+  env: { GREETING: "hi" },
+  cwd: "/",
+}).output()
+assertEquals(decoder.decode(env.stdout), "hi from /\n")
+
+const silent = await new Edon.Command("echo", { args: ["dropped"], stdout: "null" }).output()
+assertThrows(() => silent.stdout, TypeError, "stdout is not piped")
+
+// streamed both ways
+const cat = new Edon.Command("cat", { stdin: "piped" }).spawn()
+assert(typeof cat.pid === "number")
+await cat.stdin.write("line one\n")
+await cat.stdin.write(new TextEncoder().encode("line two\n"))
+cat.stdin.close()
+let text = ""
+for await (const chunk of cat.stdout) {
+  text += decoder.decode(chunk)
+}
+assertEquals(text, "line one\nline two\n")
+assertEquals(await cat.status, { success: true, code: 0, signal: null })
+
+const sleep = new Edon.Command("sleep", { args: ["10"], stdout: "null", stderr: "null" }).spawn()
+assertThrows(() => sleep.stdout, TypeError, "stdout is not piped")
+assertThrows(() => sleep.stdin, TypeError, "stdin is not piped")
+sleep.kill()
+assertEquals(await sleep.status, { success: false, code: 128 + 15, signal: "SIGTERM" })
+assertThrows(() => sleep.kill("SIGKILL"), TypeError, "already exited")
+
+const other = new Edon.Command("sleep", { args: ["10"] }).spawn()
+assertThrows(() => other.kill("SIGNOPE"), TypeError, "unknown signal")
+other.kill("SIGKILL")
+assertEquals((await other.status).signal, "SIGKILL")
+
+await assertRejects(() => new Edon.Command("cat", { stdin: "piped" }).output(), TypeError)
+await assertRejects(() => new Edon.Command("echo", { stdout: "pipe" as any }).output(), TypeError)
+await assertRejects(() => new Edon.Command("no-such-command").output(), Edon.errors.NotFound)
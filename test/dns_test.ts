@@ -0,0 +1,44 @@
+// edon test --allow-net test/dns_test.ts
+import { assertEquals, assertRejects } from "./assert.ts"
+
+// a stub name server over udp that answers every A query with 10.0.0.1 and every TXT query with
+// "hello", anything else gets an empty answer
+const A = 1
+const TXT = 16
+
+function answer(query: Uint8Array): Uint8Array {
+  // the question starts after the 12 byte header, its name is a list of labels ending in 0
+  let end = 12
+  while (query[end] !== 0) {
+    end += query[end] + 1
+  }
+  const question = query.subarray(12, end + 5)
+  const type = (query[end + 1] << 8) | query[end + 2]
+  const data = type === A ? [10, 0, 0, 1] : type === TXT ? [5, ...new TextEncoder().encode("hello")] : null
+
+  // id, a recursive response without error, one question and zero or one answer
+  const header = [query[0], query[1], 0x81, 0x80, 0, 1, 0, data ? 1 : 0, 0, 0, 0, 0]
+  // the answer names the question through a pointer to offset 12, class IN with a ttl of 60
+  const record = data ? [0xc0, 12, 0, type, 0, 1, 0, 0, 0, 60, 0, data.length, ...data] : []
+  return new Uint8Array([...header, ...question, ...record])
+}
+
+const server = Edon.listenDatagram({ transport: "udp", hostname: "127.0.0.1", port: 0 })
+const serving = (async () => {
+  for await (const [query, from] of server) {
+    await server.send(answer(query), from)
+  }
+})()
+
+const nameServer = { ipAddr: "127.0.0.1", port: server.addr.port }
+assertEquals(await Edon.resolveDns("service.internal", "A", { nameServer }), ["10.0.0.1"])
+assertEquals(await Edon.resolveDns("service.internal", "TXT", { nameServer }), [["hello"]])
+await assertRejects(
+  () => Edon.resolveDns("service.internal", "MX", { nameServer }),
+  Edon.errors.NotFound,
+  "no records found",
+)
+await assertRejects(() => Edon.resolveDns("service.internal", "PTR" as any), TypeError, "unsupported dns record type")
+
+server.close()
+await serving
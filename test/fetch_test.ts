@@ -0,0 +1,83 @@
+// edon test --allow-net test/fetch_test.ts
+import { assert, assertEquals, assertRejects } from "./assert.ts"
+
+const controller = new AbortController()
+const server = Edon.serve(
+  { hostname: "127.0.0.1", port: 0, signal: controller.signal, onListen() {} },
+  async (request) => {
+    const url = new URL(request.url)
+    switch (url.pathname) {
+      case "/echo":
+        return Response.json({
+          method: request.method,
+          type: request.headers.get("content-type"),
+          body: await request.text(),
+        })
+      case "/redirect":
+        return Response.redirect(new URL("/echo", url), Number(url.searchParams.get("status") ?? 302))
+      case "/loop":
+        return Response.redirect(url, 302)
+      case "/slow":
+        await new Promise((resolve) => setTimeout(resolve, 1000))
+        return new Response("too late")
+      default:
+        return new Response("x".repeat(64 * 1024), { headers: { "content-type": "text/plain" } })
+    }
+  },
+)
+const base = `http://127.0.0.1:${server.addr.port}`
+
+// the body streams in chunks
+const res = await fetch(`${base}/`, { headers: { accept: "text/plain" } })
+assertEquals(res.status, 200)
+assertEquals(res.statusText, "OK")
+assertEquals(res.headers.get("content-type"), "text/plain")
+let size = 0
+for await (const chunk of res.body!) {
+  size += chunk.length
+}
+assertEquals(size, 64 * 1024)
+assert(res.bodyUsed)
+
+// request bodies keep their content type
+const posted = await fetch(`${base}/echo`, { method: "POST", body: new URLSearchParams({ a: "1" }) })
+assertEquals(await posted.json(), {
+  method: "POST",
+  type: "application/x-www-form-urlencoded;charset=UTF-8",
+  body: "a=1",
+})
+
+// redirects are followed, a 303 turns into a GET without a body
+const followed = await fetch(`${base}/redirect?status=303`, { method: "POST", body: "dropped" })
+assert(followed.redirected)
+assertEquals(followed.url, `${base}/echo`)
+assertEquals(await followed.json(), { method: "GET", type: null, body: "" })
+
+// a 307 keeps the method and the body
+const kept = await fetch(`${base}/redirect?status=307`, { method: "PUT", body: "kept" })
+assertEquals((await kept.json()).body, "kept")
+
+const manual = await fetch(`${base}/redirect`, { redirect: "manual" })
+assertEquals(manual.status, 302)
+assertEquals(manual.headers.get("location"), `${base}/echo`)
+assert(!manual.redirected)
+
+await assertRejects(() => fetch(`${base}/redirect`, { redirect: "error" }), TypeError)
+await assertRejects(() => fetch(`${base}/loop`), TypeError, "too many redirects")
+
+// aborting rejects with the reason of the signal
+const aborting = new AbortController()
+const pending = fetch(`${base}/slow`, { signal: aborting.signal })
+aborting.abort()
+const aborted = await assertRejects(() => pending)
+assertEquals(aborted.name, "AbortError")
+
+const request = new Request(`${base}/echo`, { method: "POST", body: "cloned", headers: { "X-Trace": "1" } })
+assertEquals(request.headers.get("x-trace"), "1")
+const cloned = (await fetch(request)).clone()
+assertEquals((await cloned.json()).body, "cloned")
+
+await assertRejects(() => fetch("http://127.0.0.1:1/"), TypeError, "fetch failed")
+
+controller.abort()
+await server.finished
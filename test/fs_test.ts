@@ -0,0 +1,95 @@
+// edon test -A test/fs_test.ts
+import { assert, assertEquals, assertRejects, assertThrows } from "./assert.ts"
+
+const dir = await Edon.makeTempDir({ prefix: "edon-fs-" })
+assert(Edon.statSync(dir).isDirectory)
+
+await Edon.writeTextFile(`${dir}/hello.txt`, "hello")
+await Edon.writeTextFile(`${dir}/hello.txt`, ", world", { append: true })
+assertEquals(await Edon.readTextFile(`${dir}/hello.txt`), "hello, world")
+Edon.writeFileSync(`${dir}/bytes.bin`, new Uint8Array([1, 2, 3]))
+assertEquals(Edon.readFileSync(`${dir}/bytes.bin`), new Uint8Array([1, 2, 3]))
+assertEquals(await Edon.readFile(`${dir}/hello.txt`), new TextEncoder().encode("hello, world"))
+
+await Edon.mkdir(`${dir}/a/b/c`, { recursive: true })
+await Edon.copyFile(`${dir}/hello.txt`, `${dir}/a/b/c/copy.txt`)
+assertEquals(Edon.readTextFileSync(`${dir}/a/b/c/copy.txt`), "hello, world")
+await Edon.rename(`${dir}/bytes.bin`, `${dir}/a/moved.bin`)
+assertEquals((await Edon.stat(`${dir}/a/moved.bin`)).size, 3)
+await assertRejects(() => Edon.stat(`${dir}/bytes.bin`), Edon.errors.NotFound)
+
+// a symlink is followed by `stat` and `realPath`, not by `lstat`
+await Edon.symlink(`${dir}/hello.txt`, `${dir}/link.txt`)
+const link = await Edon.lstat(`${dir}/link.txt`)
+assert(link.isSymlink && !link.isFile)
+assertEquals((await Edon.stat(`${dir}/link.txt`)).size, 12)
+assertEquals(Edon.realPathSync(`${dir}/link.txt`), Edon.realPathSync(`${dir}/hello.txt`))
+
+await Edon.truncate(`${dir}/hello.txt`, 5)
+await Edon.chmod(`${dir}/hello.txt`, 0o600)
+const info = Edon.statSync(`${dir}/hello.txt`)
+assertEquals(info.size, 5)
+assertEquals(info.mode & 0o777, 0o600)
+assert(info.isFile && !info.isDirectory)
+assert(info.mtime instanceof Date)
+
+const names = []
+for await (const entry of Edon.readDir(dir)) {
+  names.push(`${entry.name}${entry.isDirectory ? "/" : ""}`)
+}
+assertEquals(names.sort(), ["a/", "hello.txt", "link.txt"])
+assertEquals(Edon.readDirSync(`${dir}/a/b`), [{ name: "c", isFile: false, isDirectory: true, isSymlink: false }])
+
+// failures throw the matching `Edon.errors` class
+const missing = await assertRejects(() => Edon.readTextFile(`${dir}/missing.txt`), Edon.errors.NotFound)
+assert(missing.message.includes("missing.txt"))
+assertThrows(
+  () => Edon.writeTextFileSync(`${dir}/hello.txt`, "again", { createNew: true }),
+  Edon.errors.AlreadyExists,
+)
+await assertRejects(() => Edon.writeTextFile(`${dir}/new.txt`, "x", { create: false }), Edon.errors.NotFound)
+await assertRejects(() => Edon.remove(`${dir}/a`), Edon.errors.DirectoryNotEmpty)
+await assertRejects(() => Edon.mkdir(`${dir}/a`), Edon.errors.AlreadyExists)
+Edon.writeFileSync(`${dir}/invalid.txt`, new Uint8Array([0xff, 0xfe]))
+await assertRejects(() => Edon.readTextFile(`${dir}/invalid.txt`), Edon.errors.InvalidData)
+
+const file = Edon.makeTempFileSync({ dir, prefix: "tmp-", suffix: ".txt" })
+assert(file.startsWith(`${dir}/tmp-`) && file.endsWith(".txt"))
+assertEquals(Edon.readTextFileSync(file), "")
+
+// runs `code` in `edon eval` with narrower permissions than this test, resolves to what it printed
+async function narrowed(flags: string[], code: string, ...args: string[]) {
+  const output = await new Edon.Command(Edon.execPath(), {
+    args: ["eval", "--no-prompt", ...flags, `try { ${code} } catch (error) { console.log(error.name) }`, ...args],
+    cwd: dir,
+  }).output()
+  return new TextDecoder().decode(output.stdout).trim()
+}
+
+// a file that can not be read can not be moved to where it can, nor found through a link
+await Edon.mkdir(`${dir}/public`)
+await Edon.writeTextFile(`${dir}/secret.txt`, "secret")
+await Edon.symlink(`${dir}/secret.txt`, `${dir}/public/link.txt`)
+const moved = await narrowed(
+  [`--allow-read=${dir}/public`, `--allow-write=${dir}`],
+  "Edon.renameSync(Edon.args[0], Edon.args[1]); console.log('renamed')",
+  `${dir}/secret.txt`,
+  `${dir}/public/secret.txt`,
+)
+assertEquals(moved, "PermissionDenied")
+assertEquals(await Edon.readTextFile(`${dir}/secret.txt`), "secret")
+const resolved = await narrowed(
+  [`--allow-read=${dir}/public`],
+  "console.log(Edon.realPathSync(Edon.args[0]))",
+  `${dir}/public/link.txt`,
+)
+assertEquals(resolved, "PermissionDenied")
+await Edon.remove(`${dir}/public`, { recursive: true })
+await Edon.remove(`${dir}/secret.txt`)
+
+// a link can point anywhere, creating one needs read and write access everywhere
+await Edon.permissions.revoke({ name: "read", path: "/etc" })
+await assertRejects(() => Edon.symlink("/etc/passwd", `${dir}/passwd`), Edon.errors.PermissionDenied)
+await Edon.writeTextFile(`${dir}/passwd`, "writing still works")
+
+await Edon.remove(dir, { recursive: true })
@@ -0,0 +1,62 @@
+// edon test test/headers_test.ts
+import { assert, assertEquals, assertRejects, assertThrows } from "./assert.ts"
+
+// names are case-insensitive, values of a name are combined except `set-cookie`
+const headers = new Headers({ "Content-Type": "text/plain", Accept: "text/html" })
+headers.append("accept", " application/json ")
+headers.append("Set-Cookie", "a=1")
+headers.append("set-cookie", "b=2")
+assertEquals(headers.get("ACCEPT"), "text/html, application/json")
+assert(headers.has("content-type"))
+assertEquals(headers.getSetCookie(), ["a=1", "b=2"])
+assertEquals([...headers], [
+  ["accept", "text/html, application/json"],
+  ["content-type", "text/plain"],
+  ["set-cookie", "a=1"],
+  ["set-cookie", "b=2"],
+])
+headers.set("Accept", "*/*")
+headers.delete("set-cookie")
+assertEquals([...headers.keys()], ["accept", "content-type"])
+assertEquals(new Headers(headers).get("accept"), "*/*")
+assertEquals(new Headers([["x-a", "1"]]).get("x-a"), "1")
+assertThrows(() => headers.set("bad name", "x"), TypeError)
+assertThrows(() => headers.set("x", "a\nb"), TypeError)
+assertThrows(() => new Headers([["only-name"]] as any), TypeError)
+
+// bodies are read once, a clone reads its own copy
+const response = new Response("hello", { status: 201, statusText: "Created", headers: { "x-id": "1" } })
+assertEquals(response.status, 201)
+assert(response.ok)
+assertEquals(response.headers.get("content-type"), "text/plain;charset=UTF-8")
+const clone = response.clone()
+assertEquals(await response.text(), "hello")
+assert(response.bodyUsed)
+await assertRejects(() => response.text(), TypeError, "Body already consumed")
+assertEquals(new Uint8Array(await clone.arrayBuffer()), new TextEncoder().encode("hello"))
+
+assertEquals(await Response.json({ ok: true }).json(), { ok: true })
+assertEquals(Response.json([]).headers.get("content-type"), "application/json")
+assertEquals(Response.error().type, "error")
+assertEquals(Response.error().status, 0)
+const redirect = Response.redirect("https://example.com/next", 307)
+assertEquals(redirect.headers.get("location"), "https://example.com/next")
+assertThrows(() => redirect.headers.set("location", "/"), TypeError, "immutable")
+assertThrows(() => Response.redirect("/", 200), RangeError)
+assertThrows(() => new Response(null, { status: 99 }), RangeError)
+assertThrows(() => new Response("body", { status: 204 }), TypeError)
+assertEquals(await new Response(new Uint8Array([104, 105])).text(), "hi")
+assertEquals(await new Response(new URLSearchParams({ q: "a b" })).text(), "q=a+b")
+
+// methods are normalized, a GET request has no body
+const request = new Request("https://example.com/", { method: "post", body: "data", redirect: "manual" })
+assertEquals(request.method, "POST")
+assertEquals(request.redirect, "manual")
+assertEquals(new Request("https://example.com/", { method: "patch" }).method, "patch")
+const moved = new Request(request)
+assertEquals(await moved.text(), "data")
+assert(request.bodyUsed)
+assertThrows(() => new Request(request), TypeError, "already used")
+assertThrows(() => new Request("https://example.com/", { body: "x" }), TypeError)
+assertThrows(() => new Request("https://example.com/", { method: "CONNECT" }), TypeError)
+assertThrows(() => new Request("https://example.com/", { redirect: "never" as any }), TypeError)
@@ -0,0 +1,65 @@
+// edon test -A test/hot_test.ts
+// runs `edon run --hot` on a module that counts, then edits it while it runs
+import { assert, assertEquals } from "./assert.ts"
+import { lines, until } from "./lines.ts"
+
+function counter(greeting: string, extra = "") {
+  return `
+const hot = import.meta.hot
+hot.data.count ??= 0
+let running = true
+function tick() {
+  if (running) {
+    console.log(\`${greeting} #\${++hot.data.count}\`)
+    setTimeout(tick, 50)
+  }
+}
+${extra}
+tick()
+hot.dispose(() => {
+  running = false
+})
+hot.accept()
+`
+}
+
+const dir = await Edon.makeTempDir({ prefix: "edon-hot-" })
+const entry = `${dir}/counter.ts`
+await Edon.writeTextFile(entry, counter("hello"))
+
+const child = new Edon.Command(Edon.execPath(), {
+  args: ["run", "--hot", entry],
+  env: { NO_COLOR: "1" },
+}).spawn()
+const timeout = setTimeout(() => child.kill("SIGKILL"), 30_000)
+const stdout = lines(child.stdout)
+const stderr = lines(child.stderr)
+
+await until(stdout, /^hello #3$/)
+
+// the new version takes over the count and the old one stops ticking
+await Edon.writeTextFile(entry, counter("bonjour"))
+const [bonjour, before] = await until(stdout, /^bonjour #(\d+)$/)
+assert(Number(bonjour[1]) > 3, "the count did not carry over")
+assert(before.every((line) => line.startsWith("hello #")))
+const [replaced] = await until(stdout, /^HMR Replaced (.*)$/)
+assert(replaced[1].endsWith("/counter.ts"))
+for (let i = 0; i < 3; i++) {
+  assertEquals((await stdout()).replace(/ #\d+$/, ""), "bonjour")
+}
+
+// a version that throws is reported, the next one still replaces the module
+await Edon.writeTextFile(entry, counter("broken", 'throw new Error("broken version")'))
+const [failed] = await until(stderr, /^HMR Replacing .* failed: (.*)$/)
+if (!failed[1].includes("broken version")) {
+  await until(stderr, /broken version/)
+}
+await Edon.writeTextFile(entry, counter("hola"))
+const [hola] = await until(stdout, /^hola #(\d+)$/)
+assert(Number(hola[1]) > Number(bonjour[1]), "the count did not survive the failed update")
+await until(stdout, /^HMR Replaced /)
+
+clearTimeout(timeout)
+child.kill()
+await child.status
+await Edon.remove(dir, { recursive: true })
@@ -0,0 +1,34 @@
+// reading the output of a child process line by line, for tests that drive `edon run`
+
+// the lines of a stream, one at a time, the output is ascii so chunks decode on their own
+export function lines(stream: ReadableStream<Uint8Array>) {
+  const reader = stream.getReader()
+  const decoder = new TextDecoder()
+  let buffered = ""
+  return async function next(): Promise<string> {
+    while (!buffered.includes("\n")) {
+      const { value, done } = await reader.read()
+      if (done) {
+        throw new Error(`the process exited, last output: ${buffered}`)
+      }
+      buffered += decoder.decode(value)
+    }
+    const index = buffered.indexOf("\n")
+    const line = buffered.slice(0, index)
+    buffered = buffered.slice(index + 1)
+    return line
+  }
+}
+
+// read lines until one matches, returns it with the lines read before it
+export async function until(next: () => Promise<string>, pattern: RegExp): Promise<[RegExpMatchArray, string[]]> {
+  const before = []
+  for (;;) {
+    const line = await next()
+    const match = line.match(pattern)
+    if (match) {
+      return [match, before]
+    }
+    before.push(line)
+  }
+}
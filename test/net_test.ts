@@ -0,0 +1,64 @@
+// edon test --allow-net --allow-read --allow-write test/net_test.ts
+import { assertEquals, assertRejects, assertThrows } from "./assert.ts"
+
+const encoder = new TextEncoder()
+const decoder = new TextDecoder()
+
+async function readAll(conn: { read(buffer: Uint8Array): Promise<number | null> }) {
+  const buffer = new Uint8Array(1024)
+  let text = ""
+  for (let read = await conn.read(buffer); read !== null; read = await conn.read(buffer)) {
+    text += decoder.decode(buffer.subarray(0, read))
+  }
+  return text
+}
+
+// an upper-casing echo server, the client half-closes to mark the end of its request
+async function echo(listener: any) {
+  for await (const conn of listener) {
+    const text = await readAll(conn)
+    await conn.write(encoder.encode(text.toUpperCase()))
+    conn.close()
+  }
+}
+
+const tcp = Edon.listen({ hostname: "127.0.0.1", port: 0 })
+assertEquals(tcp.addr.transport, "tcp")
+assertEquals(tcp.addr.hostname, "127.0.0.1")
+const tcpDone = echo(tcp)
+const client = await Edon.connect({ hostname: "127.0.0.1", port: tcp.addr.port })
+assertEquals(client.remoteAddr, { transport: "tcp", hostname: "127.0.0.1", port: tcp.addr.port })
+assertEquals(await client.write(encoder.encode("hello over tcp")), 14)
+await client.closeWrite()
+assertEquals(await readAll(client), "HELLO OVER TCP")
+client.close()
+// closing twice is a no-op, using a closed connection is not
+client.close()
+await assertRejects(() => client.read(new Uint8Array(1)), Edon.errors.BadResource)
+
+// the port is taken until the listener is closed
+assertThrows(() => Edon.listen({ hostname: "127.0.0.1", port: tcp.addr.port }), Edon.errors.AddrInUse)
+tcp.close()
+await tcpDone
+await assertRejects(() => tcp.accept(), Edon.errors.BadResource)
+await assertRejects(
+  () => Edon.connect({ hostname: "127.0.0.1", port: tcp.addr.port }),
+  Edon.errors.ConnectionRefused,
+)
+
+const dir = await Edon.makeTempDir({ prefix: "edon-net-" })
+const path = `${dir}/test.sock`
+const unix = Edon.listen({ transport: "unix", path })
+assertEquals(unix.addr, { transport: "unix", path })
+const unixDone = echo(unix)
+const local = await Edon.connect({ transport: "unix", path })
+await local.write(encoder.encode("hello over a unix socket"))
+await local.closeWrite()
+assertEquals(await readAll(local), "HELLO OVER A UNIX SOCKET")
+local.close()
+unix.close()
+await unixDone
+// closing the listener removes its socket file
+await assertRejects(() => Edon.stat(path), Edon.errors.NotFound)
+await assertRejects(() => Edon.connect({ transport: "unix", path }), Edon.errors.NotFound)
+await Edon.remove(dir)
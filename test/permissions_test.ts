@@ -0,0 +1,20 @@
+// edon test -A --no-prompt test/permissions_test.ts
+import { assertEquals, assertThrows } from "./assert.ts"
+
+const home = { name: "env", variable: "HOME" }
+assertEquals(await Edon.permissions.query(home), { state: "granted" })
+assertEquals(await Edon.permissions.query({ name: "read", path: Edon.cwd() }), { state: "granted" })
+Edon.env.get("HOME")
+
+// revoking a kind of permission drops every grant of that kind, other kinds are kept
+assertEquals(await Edon.permissions.revoke({ name: "env" }), { state: "prompt" })
+assertEquals(await Edon.permissions.query(home), { state: "prompt" })
+assertEquals(await Edon.permissions.query({ name: "net", host: "example.com:443" }), { state: "granted" })
+
+// without a terminal a request is denied, and stays denied
+assertEquals(await Edon.permissions.request(home), { state: "denied" })
+assertEquals(await Edon.permissions.query(home), { state: "denied" })
+assertThrows(() => Edon.env.get("HOME"), Edon.errors.PermissionDenied, "Requires env access to \"HOME\"")
+
+assertThrows(() => Edon.permissions.query({ name: "camera" }), TypeError)
+assertThrows(() => Edon.permissions.query("env"), TypeError, "permission descriptor must be an object")
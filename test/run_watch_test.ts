@@ -0,0 +1,53 @@
+// edon test -A test/run_watch_test.ts
+// runs `edon run --watch` on a module with an import, then edits both while it runs
+import { assertEquals } from "./assert.ts"
+import { lines, until } from "./lines.ts"
+
+function entry(exit: boolean) {
+  return `
+import { message } from "./message.ts"
+console.log(message)
+${exit ? "" : "setInterval(() => {}, 1000)"}
+`
+}
+
+// the watcher adds the modules of a run once they are loaded, give it a moment before editing
+const loaded = () => new Promise((resolve) => setTimeout(resolve, 200))
+
+const dir = Edon.realPathSync(await Edon.makeTempDir({ prefix: "edon-watch-run-" }))
+await Edon.writeTextFile(`${dir}/message.ts`, 'export const message = "hello"')
+await Edon.writeTextFile(`${dir}/main.ts`, entry(false))
+
+const child = new Edon.Command(Edon.execPath(), {
+  args: ["run", "--watch", `${dir}/main.ts`],
+  env: { NO_COLOR: "1" },
+  stderr: "null",
+}).spawn()
+const timeout = setTimeout(() => child.kill("SIGKILL"), 30_000)
+const stdout = lines(child.stdout)
+
+assertEquals(await stdout(), "Watcher Process started")
+assertEquals(await stdout(), "hello")
+await loaded()
+
+// a change of an imported module restarts the running program
+await Edon.writeTextFile(`${dir}/message.ts`, 'export const message = "bonjour"')
+await until(stdout, /^Watcher File change detected: .*\/message\.ts, restarting$/)
+assertEquals(await stdout(), "bonjour")
+await loaded()
+
+// a program that finished waits for the next change
+await Edon.writeTextFile(`${dir}/main.ts`, entry(true))
+await until(stdout, /^Watcher File change detected: .*\/main\.ts, restarting$/)
+assertEquals(await stdout(), "bonjour")
+assertEquals(await stdout(), "Watcher Process finished with exit code 0, restarting on file change")
+await loaded()
+
+await Edon.writeTextFile(`${dir}/message.ts`, 'export const message = "hola"')
+await until(stdout, /^Watcher File change detected: .*\/message\.ts, restarting$/)
+assertEquals(await stdout(), "hola")
+
+clearTimeout(timeout)
+child.kill()
+await child.status
+await Edon.remove(dir, { recursive: true })
@@ -0,0 +1,83 @@
+// edon test --allow-net test/serve_test.ts
+import { assert, assertEquals, assertThrows } from "./assert.ts"
+
+const controller = new AbortController()
+let listening: { hostname: string; port: number } | undefined
+const server = Edon.serve(
+  {
+    hostname: "127.0.0.1",
+    port: 0,
+    signal: controller.signal,
+    onListen(addr) {
+      listening = addr
+    },
+    onError(error) {
+      return new Response(`handled: ${(error as Error).message}`, { status: 503 })
+    },
+  },
+  async (request, info) => {
+    const url = new URL(request.url)
+    switch (url.pathname) {
+      case "/echo":
+        return new Response(await request.text(), { headers: { "x-method": request.method } })
+      case "/stream":
+        return new Response(
+          new ReadableStream({
+            start(controller) {
+              controller.enqueue("chunked ")
+              controller.enqueue(new TextEncoder().encode("response"))
+              controller.close()
+            },
+          }),
+        )
+      case "/throw":
+        throw new Error("boom")
+      case "/invalid":
+        return "not a response" as any
+      default:
+        return Response.json({ path: url.pathname, query: url.searchParams.get("q"), from: info.remoteAddr.hostname })
+    }
+  },
+)
+assertEquals(listening, { hostname: "127.0.0.1", port: server.addr.port, transport: "tcp" })
+assert(server.addr.port > 0)
+const base = `http://127.0.0.1:${server.addr.port}`
+
+const echoed = await fetch(`${base}/echo`, { method: "POST", body: "hello" })
+assertEquals(echoed.status, 200)
+assertEquals(echoed.headers.get("x-method"), "POST")
+assertEquals(await echoed.text(), "hello")
+
+const big = "x".repeat(1024 * 1024)
+assertEquals((await (await fetch(`${base}/echo`, { method: "PUT", body: big })).text()).length, big.length)
+
+assertEquals(await (await fetch(`${base}/stream`)).text(), "chunked response")
+assertEquals(await (await fetch(`${base}/json?q=1`)).json(), { path: "/json", query: "1", from: "127.0.0.1" })
+
+// requests are handled concurrently
+const texts = await Promise.all(
+  ["1", "2", "3"].map((body) => fetch(`${base}/echo`, { method: "POST", body }).then((res) => res.text())),
+)
+assertEquals(texts, ["1", "2", "3"])
+
+// errors of the handler go through `onError`
+const thrown = await fetch(`${base}/throw`)
+assertEquals(thrown.status, 503)
+assertEquals(await thrown.text(), "handled: boom")
+const invalid = await fetch(`${base}/invalid`)
+assertEquals(await invalid.text(), "handled: the handler must return a Response")
+
+assertThrows(() => Edon.serve({ port: 0 }), TypeError, "handler")
+assertThrows(() => Edon.upgradeWebSocket(new Request(base)), TypeError, "Edon.serve")
+
+// aborting the signal stops accepting connections, `finished` settles once drained
+controller.abort()
+await server.finished
+let refused = false
+await fetch(`${base}/json`).catch(() => (refused = true))
+assert(refused, "the server still accepts connections")
+
+// a server can also be stopped with `shutdown`
+const other = Edon.serve({ hostname: "127.0.0.1", port: 0, onListen() {} }, () => new Response("ok"))
+assertEquals(await (await fetch(`http://127.0.0.1:${other.addr.port}/`)).text(), "ok")
+await other.shutdown()
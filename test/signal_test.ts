@@ -0,0 +1,64 @@
+// edon test --allow-run test/signal_test.ts
+import { assertEquals, assertThrows } from "./assert.ts"
+import { lines } from "./lines.ts"
+
+// the signal is sent by a child process, a listener resolves once it arrives
+async function send(signal: string) {
+  const status = await new Edon.Command("kill", { args: [`-${signal.slice(3)}`, String(Edon.pid)] }).output()
+  assertEquals(status.code, 0)
+}
+
+const received: string[] = []
+let arrived!: () => void
+const first = () => {
+  received.push("first")
+  throw new Error("a throwing listener does not stop the others")
+}
+const second = () => {
+  received.push("second")
+  arrived()
+}
+Edon.addSignalListener("SIGUSR1", first)
+Edon.addSignalListener("SIGUSR1", second)
+
+await new Promise<void>((resolve) => {
+  arrived = resolve
+  send("SIGUSR1")
+})
+assertEquals(received, ["first", "second"])
+
+// the signal stays bound after a listener threw
+await new Promise<void>((resolve) => {
+  arrived = resolve
+  send("SIGUSR1")
+})
+assertEquals(received, ["first", "second", "first", "second"])
+
+// removing a listener only drops that one
+Edon.removeSignalListener("SIGUSR1", first)
+await new Promise<void>((resolve) => {
+  arrived = resolve
+  send("SIGUSR1")
+})
+assertEquals(received.slice(4), ["second"])
+// once the last one is gone the signal no longer keeps the program running
+Edon.removeSignalListener("SIGUSR1", second)
+
+// and it gets its default action back, a program that stopped listening exits on SIGTERM
+const child = new Edon.Command(Edon.execPath(), {
+  args: [
+    "eval",
+    `const listener = () => console.log("caught")
+    Edon.addSignalListener("SIGTERM", listener)
+    Edon.removeSignalListener("SIGTERM", listener)
+    console.log("ready")
+    setTimeout(() => console.log("survived"), 10_000)`,
+  ],
+}).spawn()
+assertEquals(await lines(child.stdout)(), "ready")
+child.kill("SIGTERM")
+assertEquals(await child.status, { success: false, code: 128 + 15, signal: "SIGTERM" })
+
+assertThrows(() => Edon.addSignalListener("SIGKILL", () => {}), TypeError, "can not be listened to")
+assertThrows(() => Edon.addSignalListener("SIGNOPE", () => {}), TypeError, "unknown signal")
+assertThrows(() => Edon.addSignalListener("SIGUSR2", "nope" as any), TypeError, "must be a function")
@@ -0,0 +1,41 @@
+// edon test --allow-net test/udp_test.ts
+import { assertEquals, assertRejects } from "./assert.ts"
+
+const encoder = new TextEncoder()
+const decoder = new TextDecoder()
+
+const agent = Edon.listenDatagram({ transport: "udp", hostname: "127.0.0.1", port: 0 })
+const client = Edon.listenDatagram({ transport: "udp", hostname: "127.0.0.1", port: 0 })
+assertEquals(agent.addr.transport, "udp")
+assertEquals(agent.addr.hostname, "127.0.0.1")
+
+// every datagram arrives on its own, with the address it came from
+const metrics = ["requests:1|c", "latency:12|ms"]
+for (const metric of metrics) {
+  assertEquals(await client.send(encoder.encode(metric), agent.addr), metric.length)
+}
+const received = []
+for await (const [data, from] of agent) {
+  assertEquals(from, { transport: "udp", hostname: "127.0.0.1", port: client.addr.port })
+  received.push(decoder.decode(data))
+  if (received.length === metrics.length) {
+    break
+  }
+}
+assertEquals(received, metrics)
+
+// a datagram longer than the buffer is truncated
+await agent.send(encoder.encode("truncated"), client.addr)
+const [data] = await client.receive(new Uint8Array(5))
+assertEquals(decoder.decode(data), "trunc")
+
+// closing ends the iteration, receiving afterwards fails
+const ended = (async () => {
+  for await (const _ of client) {
+    throw new Error("unexpected datagram")
+  }
+})()
+client.close()
+await ended
+await assertRejects(() => client.receive(), Edon.errors.BadResource)
+agent.close()
@@ -0,0 +1,36 @@
+// edon test --allow-read --allow-write test/watch_test.ts
+import { assert, assertRejects, assertThrows } from "./assert.ts"
+
+const dir = Edon.realPathSync(await Edon.makeTempDir({ prefix: "edon-watch-" }))
+const watcher = Edon.watchFs(dir)
+// events are delivered as the platform reports them, give up if the last one never comes
+const timeout = setTimeout(() => watcher.close(), 5000)
+
+await Edon.writeTextFile(`${dir}/a.txt`, "one")
+await Edon.writeTextFile(`${dir}/a.txt`, "two", { append: true })
+await Edon.rename(`${dir}/a.txt`, `${dir}/b.txt`)
+await Edon.remove(`${dir}/b.txt`)
+
+const events: { kind: string; paths: string[] }[] = []
+for await (const event of watcher) {
+  events.push({ kind: event.kind, paths: event.paths.map((path) => path.slice(dir.length)) })
+  if (event.kind === "remove" && event.paths.some((path) => path.endsWith("/b.txt"))) {
+    break
+  }
+}
+clearTimeout(timeout)
+
+const seen = (kind: string, path: string) =>
+  events.some((event) => event.kind === kind && event.paths.includes(path))
+assert(seen("create", "/a.txt"), "missing create of the file")
+assert(seen("modify", "/a.txt"), "missing modify of the file")
+assert(seen("rename", "/a.txt") || seen("rename", "/b.txt"), "missing rename")
+assert(seen("remove", "/b.txt"), "missing remove")
+assert(events.every((event) => ["create", "modify", "remove", "rename"].includes(event.kind)))
+
+// breaking out of the loop closed the watcher, closing again is a no-op
+watcher.close()
+await assertRejects(() => watcher[Symbol.asyncIterator]().next(), Edon.errors.BadResource)
+
+assertThrows(() => Edon.watchFs(`${dir}/missing`), Edon.errors.NotFound)
+await Edon.remove(dir, { recursive: true })
@@ -0,0 +1,62 @@
+// edon test --allow-net test/websocket_test.ts
+import { assert, assertEquals, assertThrows } from "./assert.ts"
+
+const controller = new AbortController()
+let serverClosed: (event: CloseEvent) => void
+const serverClose = new Promise<CloseEvent>((resolve) => (serverClosed = resolve))
+const server = Edon.serve(
+  { hostname: "127.0.0.1", port: 0, signal: controller.signal, onListen() {} },
+  (request) => {
+    if (new URL(request.url).pathname !== "/echo") {
+      return new Response("not found", { status: 404 })
+    }
+    const { socket, response } = Edon.upgradeWebSocket(request, { protocol: "echo" })
+    socket.onmessage = (event) => socket.send(event.data)
+    socket.onclose = (event) => serverClosed(event)
+    return response
+  },
+)
+const url = `ws://127.0.0.1:${server.addr.port}/echo`
+
+function next<T extends Event>(ws: WebSocket, type: string): Promise<T> {
+  return new Promise((resolve) => ws.addEventListener(type, (event) => resolve(event as T), { once: true }))
+}
+
+const ws = new WebSocket(url, ["echo"])
+assertEquals(ws.readyState, WebSocket.CONNECTING)
+assertThrows(() => ws.send("too early"), DOMException)
+await next(ws, "open")
+assertEquals(ws.readyState, WebSocket.OPEN)
+assertEquals(ws.protocol, "echo")
+assertEquals(ws.url, url)
+
+// text and binary messages are echoed back
+ws.send("hello")
+assertEquals((await next<MessageEvent>(ws, "message")).data, "hello")
+ws.send(new Uint8Array([1, 2, 3]))
+const binary = (await next<MessageEvent>(ws, "message")).data
+assert(binary instanceof ArrayBuffer)
+assertEquals(new Uint8Array(binary), new Uint8Array([1, 2, 3]))
+
+assertThrows(() => ws.close(1001), DOMException)
+const closed = next<CloseEvent>(ws, "close")
+ws.close(1000, "done")
+assertEquals(ws.readyState, WebSocket.CLOSING)
+const event = await closed
+assertEquals([event.code, event.reason, event.wasClean], [1000, "done", true])
+assertEquals(ws.readyState, WebSocket.CLOSED)
+
+// a server that does not upgrade fails the handshake, `error` comes right before `close`
+const rejected = new WebSocket(`ws://127.0.0.1:${server.addr.port}/missing`)
+const failed = next(rejected, "error")
+const failedClose = next<CloseEvent>(rejected, "close")
+await failed
+assertEquals((await failedClose).wasClean, false)
+assertEquals(rejected.readyState, WebSocket.CLOSED)
+
+assertThrows(() => new WebSocket("ftp://127.0.0.1/"), DOMException)
+assertThrows(() => new WebSocket(url, ["echo", "echo"]), DOMException)
+
+controller.abort()
+await server.finished
+assertEquals((await serverClose).code, 1000)